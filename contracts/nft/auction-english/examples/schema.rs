@@ -17,5 +17,8 @@ fn main() {
     export_schema(&schema_for!(msg::QueryOptions<msg::TokenPriceOffset>), &out_dir);
     export_schema(&schema_for!(msg::ConfigResponse), &out_dir);
     export_schema(&schema_for!(msg::AuctionResponse), &out_dir);
+    export_schema(&schema_for!(msg::MinOutbidResponse), &out_dir);
+    export_schema(&schema_for!(msg::AuctionTimeRemainingResponse), &out_dir);
+    export_schema(&schema_for!(msg::WouldWinAuctionResponse), &out_dir);
     export_schema(&schema_for!(msg::AuctionsResponse), &out_dir);
 }
\ No newline at end of file
@@ -42,4 +42,7 @@ pub enum ContractError {
 
     #[error("Invalid config: {0}")]
     InvalidConfig(String),
+
+    #[error("Not enough bids to settle: expected at least {0}, actual {1}")]
+    InsufficientBids(u32, u32),
 }
@@ -1,14 +1,14 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{Decimal, DepsMut, Env, Event, MessageInfo, Uint128, Response};
+use cosmwasm_std::{Coin, Decimal, DepsMut, Env, Event, MessageInfo, Uint128, Response};
 use cw2::set_contract_version;
 use cw_utils::{maybe_addr, must_pay, nonpayable};
 
 use crate::error::ContractError;
 use crate::helpers::{
-    map_validate, finalize_sale, price_validate, only_seller, only_owner,
-    only_operator, transfer_nft, transfer_token, validate_auction_times,
-    validate_config
+    map_validate, finalize_sale, only_enough_bids, count_bidder, clear_bidders, price_validate, only_seller, only_owner,
+    only_operator, transfer_nft, return_nft, transfer_token, validate_auction_times,
+    validate_auction_prices, validate_config
 };
 use crate::msg::{InstantiateMsg, ExecuteMsg};
 use crate::state::{
@@ -42,6 +42,8 @@ pub fn instantiate(
         max_duration: msg.max_duration,
         closed_duration: msg.closed_duration,
         buffer_duration: msg.buffer_duration,
+        max_expiry_horizon_seconds: msg.max_expiry_horizon_seconds,
+        min_bids_to_settle: msg.min_bids_to_settle,
     };
     validate_config(&config)?;
     CONFIG.save(deps.storage, &config)?;
@@ -71,6 +73,8 @@ pub fn execute(
             max_duration,
             closed_duration,
             buffer_duration,
+            max_expiry_horizon_seconds,
+            min_bids_to_settle,
         } => execute_update_config(
             deps,
             env,
@@ -84,6 +88,8 @@ pub fn execute(
             max_duration,
             closed_duration,
             buffer_duration,
+            max_expiry_horizon_seconds,
+            min_bids_to_settle,
         ),
         ExecuteMsg::SetAuction {
             token_id,
@@ -92,6 +98,7 @@ pub fn execute(
             starting_price,
             reserve_price,
             funds_recipient,
+            allowed_bidders,
         } => execute_set_auction(
             deps,
             env,
@@ -104,9 +111,22 @@ pub fn execute(
                 starting_price,
                 reserve_price,
                 funds_recipient: maybe_addr(api, funds_recipient)?,
+                allowed_bidders: allowed_bidders
+                    .map(|_allowed_bidders| map_validate(api, &_allowed_bidders))
+                    .transpose()?,
                 highest_bid: None
             },
         ),
+        ExecuteMsg::UpdateAuctionReserve {
+            token_id,
+            reserve_price,
+        } => execute_update_auction_reserve(
+            deps,
+            env,
+            info,
+            token_id,
+            reserve_price,
+        ),
         ExecuteMsg::SetAuctionBid {
             token_id,
             price,
@@ -163,6 +183,8 @@ pub fn execute_update_config(
     max_duration: Option<u64>,
     closed_duration: Option<u64>,
     buffer_duration: Option<u64>,
+    max_expiry_horizon_seconds: Option<u64>,
+    min_bids_to_settle: Option<u32>,
 ) -> Result<Response, ContractError> {
     let mut config = CONFIG.load(deps.storage)?;
     
@@ -195,6 +217,12 @@ pub fn execute_update_config(
     if let Some(_buffer_duration) = buffer_duration {
         config.buffer_duration = _buffer_duration;
     }
+    if let Some(_max_expiry_horizon_seconds) = max_expiry_horizon_seconds {
+        config.max_expiry_horizon_seconds = Some(_max_expiry_horizon_seconds);
+    }
+    if let Some(_min_bids_to_settle) = min_bids_to_settle {
+        config.min_bids_to_settle = Some(_min_bids_to_settle);
+    }
     validate_config(&config)?;
     CONFIG.save(deps.storage, &config)?;
     Ok(Response::new())
@@ -211,14 +239,7 @@ pub fn execute_set_auction(
     
     let config = CONFIG.load(deps.storage)?;
     validate_auction_times(&auction, &config, &env.block.time)?;
-    
-    price_validate(&auction.starting_price, &config)?;
-    if let Some(_reserve_price) = &auction.reserve_price {
-        price_validate(&_reserve_price, &config)?;
-        if _reserve_price.amount < auction.starting_price.amount {
-            return Err(ContractError::InvalidReservePrice(_reserve_price.amount, auction.starting_price.amount));
-        }
-    }
+    validate_auction_prices(&auction, &config)?;
 
     only_owner(deps.as_ref(), &info, &config.cw721_address, &auction.token_id)?;
 
@@ -239,11 +260,56 @@ pub fn execute_set_auction(
         .add_attribute("seller", auction.seller)
         .add_attribute("start_time", auction.start_time.to_string())
         .add_attribute("end_time", auction.end_time.to_string())
+        .add_attribute("expires_in_seconds", (auction.end_time.seconds() - env.block.time.seconds()).to_string())
         .add_attribute("starting_price", auction.starting_price.to_string());
 
     Ok(response.add_event(event))
 }
 
+/// Seller of an auction can lower its reserve price until the auction ends
+pub fn execute_update_auction_reserve(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: TokenId,
+    reserve_price: Coin,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let mut auction = auctions().load(deps.storage, token_id.clone())?;
+    only_seller(&info, &auction.seller)?;
+
+    // Validate auction has not ended
+    let config = CONFIG.load(deps.storage)?;
+    let auction_status = auction.get_auction_status(&env.block.time, config.closed_duration);
+    match &auction_status {
+        AuctionStatus::Pending | AuctionStatus::Open => {},
+        _ => return Err(ContractError::InvalidStatus(auction_status.to_string())),
+    }
+
+    // Validate reserve price is only lowered, and the edited auction prices remain valid
+    match &auction.reserve_price {
+        Some(_reserve_price) if reserve_price.amount <= _reserve_price.amount => {},
+        Some(_) => return Err(ContractError::ReservePriceRestriction(
+            "reserve price can only be lowered".to_string(),
+        )),
+        None => return Err(ContractError::ReservePriceRestriction(
+            "cannot add a reserve price to an auction without one".to_string(),
+        )),
+    }
+
+    auction.reserve_price = Some(reserve_price);
+    validate_auction_prices(&auction, &config)?;
+    auctions().save(deps.storage, token_id.clone(), &auction)?;
+
+    let event = Event::new("update-auction-reserve")
+        .add_attribute("collection", config.cw721_address.to_string())
+        .add_attribute("token_id", token_id.to_string())
+        .add_attribute("reserve_price", auction.reserve_price.unwrap().to_string());
+
+    Ok(Response::new().add_event(event))
+}
+
 /// Places a bid for an NFT on an existing auction
 pub fn execute_set_auction_bid(
     deps: DepsMut,
@@ -264,6 +330,13 @@ pub fn execute_set_auction_bid(
         _ => return Err(ContractError::InvalidStatus(auction_status.to_string())),
     }
 
+    // Validate bidder is allowed, if the auction restricts its bidders
+    if let Some(allowed_bidders) = &auction.allowed_bidders {
+        if !allowed_bidders.contains(&auction_bid.bidder) {
+            return Err(ContractError::Unauthorized(String::from("bidder is not allowed on this auction")));
+        }
+    }
+
     // Validate bid is higher than the minimum viable bid
     if auction_bid.price.amount < auction.get_next_bid_min(config.min_bid_increment) {
         return Err(ContractError::BidTooLow {});
@@ -294,6 +367,7 @@ pub fn execute_set_auction_bid(
     }
     
     auctions().save(deps.storage, auction.token_id.clone(), &auction)?;
+    count_bidder(deps.storage, &auction.token_id, &auction_bid.bidder)?;
 
     let event = Event::new("set-auction-bid")
         .add_attribute("token_id", &token_id.to_string())
@@ -343,7 +417,7 @@ pub fn execute_close_auction(
         )?;
     } else {
         // if sale does not occur return NFT to seller, then refund highest_bid if it exists
-        transfer_nft(&auction.token_id, &auction.seller, &config.cw721_address, &mut response)?;
+        return_nft(&auction.token_id, &auction.seller, &config.cw721_address, &mut response)?;
         if auction.highest_bid.is_some() {
             let bid = auction.highest_bid.unwrap();
             transfer_token(
@@ -355,7 +429,8 @@ pub fn execute_close_auction(
         }   
     }
 
-    auctions().remove(deps.storage, token_id)?;
+    auctions().remove(deps.storage, token_id.clone())?;
+    clear_bidders(deps.storage, &token_id)?;
 
     let event = Event::new("close-auction")
         .add_attribute("collection", &config.cw721_address.to_string())
@@ -412,7 +487,8 @@ pub fn execute_finalize_auction(
         &mut response,
     )?;
 
-    auctions().remove(deps.storage, token_id)?;
+    auctions().remove(deps.storage, token_id.clone())?;
+    clear_bidders(deps.storage, &token_id)?;
 
     let event = Event::new("finalize-auction")
         .add_attribute("collection", &config.cw721_address.to_string())
@@ -423,7 +499,7 @@ pub fn execute_finalize_auction(
 
 /// If an auction is expired, and the seller has not made a determination within the closed_duration window,
 /// then anyone can void the auction (refund the highest bid and return the NFT to the owner). Note, this
-/// is only possible if the auction reserve price has not been met.
+/// is only possible if the auction reserve price has not been met, or if it has too few bids to settle.
 pub fn execute_void_auction(
     deps: DepsMut,
     env: Env,
@@ -432,16 +508,16 @@ pub fn execute_void_auction(
 ) -> Result<Response, ContractError> {
     nonpayable(&info)?;
     let auction = auctions().load(deps.storage, token_id.clone())?;
-    
+    let config = CONFIG.load(deps.storage)?; 
+
     // If reserve price has been met, the auction must be finalized
-    if auction.is_reserve_price_met() {
+    if auction.is_reserve_price_met() && only_enough_bids(deps.as_ref(), &auction.token_id, &config).is_ok() {
         return Err(ContractError::ReservePriceRestriction(
             "must finalize auction when reserve price is met".to_string(),
         ));
     }
 
     // Validate the Auction is Expired
-    let config = CONFIG.load(deps.storage)?; 
     let auction_status = auction.get_auction_status(&env.block.time, config.closed_duration);
     match &auction_status {
         AuctionStatus::Expired => {},
@@ -460,9 +536,10 @@ pub fn execute_void_auction(
         )?;
     }
     // Return the NFT to the seller
-    transfer_nft(&auction.token_id, &auction.seller, &config.cw721_address, &mut response)?;
+    return_nft(&auction.token_id, &auction.seller, &config.cw721_address, &mut response)?;
     // Remove the auction
-    auctions().remove(deps.storage, token_id)?;
+    auctions().remove(deps.storage, token_id.clone())?;
+    clear_bidders(deps.storage, &token_id)?;
 
     let event = Event::new("void-auction")
         .add_attribute("token_id", &auction.token_id.to_string())
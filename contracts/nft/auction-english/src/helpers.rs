@@ -1,9 +1,9 @@
 use crate::error::ContractError;
 use crate::state::{
-    Config, TokenId, Auction
+    Config, TokenId, Auction, AUCTION_BID_COUNTS, AUCTION_BIDDERS,
 };
 use cosmwasm_std::{
-    to_binary, Addr, Api, StdResult, Timestamp, WasmMsg, Order, Deps,
+    to_binary, Addr, Api, StdResult, Timestamp, WasmMsg, Order, Deps, Storage,
     Event, Coin, coin, Uint128, Response, MessageInfo, BankMsg, SubMsg, Decimal
 };
 use pg721::msg::{CollectionInfoResponse, QueryMsg as Pg721QueryMsg};
@@ -33,7 +33,9 @@ pub fn finalize_sale(
     payment_recipient: &Addr,
     config: &Config,
     res: &mut Response,
-) -> StdResult<()> {
+) -> Result<(), ContractError> {
+    only_enough_bids(deps, token_id, config)?;
+
     payout(deps, payment_amount, payment_recipient, &config, res)?;
 
     transfer_nft(&token_id, bidder, &config.cw721_address, res)?;
@@ -107,6 +109,42 @@ fn payout(
     Ok(())
 }
 
+/// Every auction sale needs min_bids_to_settle bids, however its reserve came to be met
+pub fn only_enough_bids(deps: Deps, token_id: &TokenId, config: &Config) -> Result<(), ContractError> {
+    if let Some(min_bids_to_settle) = config.min_bids_to_settle {
+        let bid_count = AUCTION_BID_COUNTS.may_load(deps.storage, token_id)?.unwrap_or_default();
+        if bid_count < min_bids_to_settle {
+            return Err(ContractError::InsufficientBids(min_bids_to_settle, bid_count));
+        }
+    }
+    Ok(())
+}
+
+/// Counts the bidder towards min_bids_to_settle unless they already bid on the auction
+pub fn count_bidder(storage: &mut dyn Storage, token_id: &str, bidder: &Addr) -> StdResult<()> {
+    if AUCTION_BIDDERS.may_load(storage, (token_id, bidder))?.is_some() {
+        return Ok(());
+    }
+    AUCTION_BIDDERS.save(storage, (token_id, bidder), &true)?;
+    AUCTION_BID_COUNTS.update(storage, token_id, |count| -> StdResult<u32> {
+        Ok(count.unwrap_or_default() + 1)
+    })?;
+    Ok(())
+}
+
+/// Forgets the bidders counted on an auction once it is removed
+pub fn clear_bidders(storage: &mut dyn Storage, token_id: &str) -> StdResult<()> {
+    let bidders = AUCTION_BIDDERS
+        .prefix(token_id)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for bidder in bidders {
+        AUCTION_BIDDERS.remove(storage, (token_id, &bidder));
+    }
+    AUCTION_BID_COUNTS.remove(storage, token_id);
+    Ok(())
+}
+
 // Validate Bid or Ask price
 pub fn price_validate(price: &Coin, config: &Config) -> Result<(), ContractError> {
     if
@@ -120,6 +158,19 @@ pub fn price_validate(price: &Coin, config: &Config) -> Result<(), ContractError
     Ok(())
 }
 
+/// Validate an auction's starting and reserve prices, on creation and after every edit
+pub fn validate_auction_prices(auction: &Auction, config: &Config) -> Result<(), ContractError> {
+    price_validate(&auction.starting_price, config)?;
+    if let Some(_reserve_price) = &auction.reserve_price {
+        price_validate(_reserve_price, config)?;
+        if _reserve_price.amount < auction.starting_price.amount {
+            return Err(ContractError::InvalidReservePrice(_reserve_price.amount, auction.starting_price.amount));
+        }
+    }
+
+    Ok(())
+}
+
 /// Checks to enforce only NFT owner can call
 pub fn only_owner(
     deps: Deps,
@@ -180,6 +231,19 @@ pub fn transfer_nft(token_id: &TokenId, recipient: &Addr, collection: &Addr, res
     Ok(())
 }
 
+/// Transfer an escrowed NFT back to its seller, emitting a consistent `nft-returned` event
+pub fn return_nft(token_id: &TokenId, seller: &Addr, collection: &Addr, response: &mut Response) -> StdResult<()> {
+    transfer_nft(token_id, seller, collection, response)?;
+
+    let event = Event::new("nft-returned")
+        .add_attribute("collection", collection.to_string())
+        .add_attribute("token_id", token_id.to_string())
+        .add_attribute("recipient", seller.to_string());
+    response.events.push(event);
+
+    Ok(())
+}
+
 pub fn transfer_token(coin_send: Coin, recipient: String, event_label: &str, response: &mut Response) -> StdResult<()> {
     let token_transfer_msg = BankMsg::Send {
         to_address: recipient.clone(),
@@ -205,6 +269,11 @@ pub fn validate_auction_times(auction: &Auction, config: &Config, now: &Timestam
     if &auction.start_time.plus_seconds(config.max_duration) < &auction.end_time {
         return Err(ContractError::InvalidStartEndTime(String::from("duration is above maximum")));
     }
+    if let Some(max_expiry_horizon_seconds) = config.max_expiry_horizon_seconds {
+        if now.plus_seconds(max_expiry_horizon_seconds) < auction.end_time {
+            return Err(ContractError::InvalidStartEndTime(String::from("end time is beyond the maximum horizon")));
+        }
+    }
     Ok(())
 }
 
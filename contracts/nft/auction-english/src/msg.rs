@@ -29,6 +29,10 @@ pub struct InstantiateMsg {
     pub closed_duration: u64,
     /// The duration an Auction is extended by when a bid is placed in the final minutes
     pub buffer_duration: u64,
+    /// The furthest an auction end time may be from the current block time, in seconds
+    pub max_expiry_horizon_seconds: Option<u64>,
+    /// The minimum number of distinct bidders an auction needs before it can settle with a sale
+    pub min_bids_to_settle: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -45,6 +49,8 @@ pub enum ExecuteMsg {
         max_duration: Option<u64>,
         closed_duration: Option<u64>,
         buffer_duration: Option<u64>,
+        max_expiry_horizon_seconds: Option<u64>,
+        min_bids_to_settle: Option<u32>,
     },
     /// Create an auction for a specified token
     SetAuction {
@@ -54,6 +60,13 @@ pub enum ExecuteMsg {
         starting_price: Coin,
         reserve_price: Option<Coin>,
         funds_recipient: Option<String>,
+        allowed_bidders: Option<Vec<String>>,
+    },
+    /// Sellers can lower the reserve price of an auction that has not ended, auctions created
+    /// without a reserve price cannot be given one
+    UpdateAuctionReserve {
+        token_id: TokenId,
+        reserve_price: Coin,
     },
     /// Place a bid on an existing auction
     SetAuctionBid {
@@ -109,6 +122,22 @@ pub enum QueryMsg {
     Auction {
         token_id: TokenId,
     },
+    /// Get the smallest bid that would become the new highest bid on an auction
+    /// Return type: `MinOutbidResponse`
+    MinOutbid {
+        token_id: TokenId,
+    },
+    /// Get the seconds until an auction ends, and whether a bid now would extend it
+    /// Return type: `AuctionTimeRemainingResponse`
+    AuctionTimeRemaining {
+        token_id: TokenId,
+    },
+    /// Get whether a bidder currently leads an auction, and whether their bid meets the reserve
+    /// Return type: `WouldWinAuctionResponse`
+    WouldWinAuction {
+        token_id: TokenId,
+        bidder: String,
+    },
     /// Get the auctions sorted by the start time
     /// Return type: `AuctionsResponse`
     AuctionsByStartTime {
@@ -151,6 +180,27 @@ pub struct AuctionResponse {
     pub next_bid_min: Option<Uint128>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MinOutbidResponse {
+    pub min_outbid: Option<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AuctionTimeRemainingResponse {
+    /// Zero once the auction has ended
+    pub seconds_remaining: u64,
+    /// Whether the auction ends within buffer_duration, so a bid now extends it
+    pub in_extension_window: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WouldWinAuctionResponse {
+    /// Whether the bidder holds the highest bid
+    pub is_highest_bidder: bool,
+    /// Whether the bidder holds the highest bid and it meets the reserve price
+    pub meets_reserve: bool,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AuctionsResponse {
     pub auctions: Vec<Auction>,
@@ -2,6 +2,7 @@
 use crate::error::ContractError;
 use crate::msg::{
     ExecuteMsg, QueryMsg, QueryOptions, AuctionResponse, AuctionsResponse, TokenTimestampOffset,
+    MinOutbidResponse, AuctionTimeRemainingResponse, WouldWinAuctionResponse,
 };
 use crate::state::{Auction, AuctionStatus, AuctionBid};
 use cosmwasm_std::{Addr, Empty, Timestamp, coin, coins, Coin, Decimal, Uint128};
@@ -114,6 +115,8 @@ fn setup_contracts(
         max_duration: SIX_MOS,
         closed_duration: ONE_DAY,
         buffer_duration: TEN_MINS,
+        max_expiry_horizon_seconds: None,
+        min_bids_to_settle: None,
     };
     let auction_english = router
         .instantiate_contract(
@@ -238,6 +241,7 @@ fn auction(
         starting_price: coin(starting_price, NATIVE_DENOM),
         reserve_price: Some(coin(reserve_price, NATIVE_DENOM)),
         funds_recipient,
+        allowed_bidders: None,
     };
     let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
     assert!(res.is_ok());
@@ -281,6 +285,7 @@ fn try_auction_creation_and_removal() {
         starting_price: coin(110, NATIVE_DENOM),
         reserve_price: Some(coin(210, NATIVE_DENOM)),
         funds_recipient: None,
+        allowed_bidders: None,
     };
     let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
     assert!(res.is_err());
@@ -293,6 +298,7 @@ fn try_auction_creation_and_removal() {
         starting_price: coin(110, NATIVE_DENOM),
         reserve_price: Some(coin(210, NATIVE_DENOM)),
         funds_recipient: None,
+        allowed_bidders: None,
     };
     let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
     assert!(res.is_err());
@@ -305,6 +311,7 @@ fn try_auction_creation_and_removal() {
         starting_price: coin(110, NATIVE_DENOM),
         reserve_price: Some(coin(210, "ujuno")),
         funds_recipient: None,
+        allowed_bidders: None,
     };
     let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
     assert!(res.is_err());
@@ -317,6 +324,7 @@ fn try_auction_creation_and_removal() {
         starting_price: coin(200, NATIVE_DENOM),
         reserve_price: Some(coin(100, NATIVE_DENOM)),
         funds_recipient: None,
+        allowed_bidders: None,
     };
     let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
     assert!(res.is_err());
@@ -355,6 +363,7 @@ fn try_auction_creation_and_removal() {
         reserve_price: Some(coin(210, NATIVE_DENOM)),
         seller: creator.clone(),
         funds_recipient: None,
+        allowed_bidders: None,
         highest_bid: None,
     }, current_auction);
     
@@ -376,6 +385,15 @@ fn try_auction_creation_and_removal() {
     };
     let res = router.execute_contract(creator.clone(), auction_english.clone(), &close_auction, &[]);
     assert!(res.is_ok());
+    // Check the NFT return is reported
+    let returned_event = res
+        .unwrap()
+        .events
+        .into_iter()
+        .find(|e| e.ty == "wasm-nft-returned")
+        .unwrap();
+    assert_eq!(returned_event.attributes[2].value, TOKEN_ID.to_string());
+    assert_eq!(returned_event.attributes[3].value, creator.to_string());
 
     // Validate Auction is deleted
     let query_auction = QueryMsg::Auction {
@@ -487,6 +505,7 @@ fn try_auction_bid_creation_and_removal() {
         starting_price: coin(110u128, NATIVE_DENOM),
         reserve_price: Some(coin(210u128, NATIVE_DENOM)),
         funds_recipient: None,
+        allowed_bidders: None,
         highest_bid: Some(AuctionBid {
             bidder: bidder2.clone(),
             price: coin(150u128, NATIVE_DENOM),
@@ -721,6 +740,15 @@ fn try_auction_void() {
     };
     let res = router.execute_contract(bidder2.clone(), auction_english.clone(), &void_auction, &[]);
     assert!(res.is_ok());
+    // Check the NFT return is reported
+    let returned_event = res
+        .unwrap()
+        .events
+        .into_iter()
+        .find(|e| e.ty == "wasm-nft-returned")
+        .unwrap();
+    assert_eq!(returned_event.attributes[2].value, token_id);
+    assert_eq!(returned_event.attributes[3].value, creator.to_string());
 
     // Check NFT is transferred back to the original owner
     let query_owner_msg = Cw721QueryMsg::OwnerOf {
@@ -782,6 +810,7 @@ fn try_auction_queries() {
         starting_price: coin(100u128 + token_id as u128, NATIVE_DENOM),
         reserve_price: Some(coin(200u128 + token_id as u128, NATIVE_DENOM)),
         funds_recipient: None,
+        allowed_bidders: None,
         highest_bid: None,
     }, res.auction.unwrap());
     assert_eq!(AuctionStatus::Pending, res.auction_status.unwrap());
@@ -808,6 +837,7 @@ fn try_auction_queries() {
             starting_price: coin(100u128 + n as u128, NATIVE_DENOM),
             reserve_price: Some(coin(200u128 + n as u128, NATIVE_DENOM)),
             funds_recipient: None,
+            allowed_bidders: None,
             highest_bid: None
         }, res.clone().auctions.into_iter().nth(n as usize - 1).unwrap());
     }
@@ -834,6 +864,7 @@ fn try_auction_queries() {
             starting_price: coin(100u128 + n as u128, NATIVE_DENOM),
             reserve_price: Some(coin(200u128 + n as u128, NATIVE_DENOM)),
             funds_recipient: None,
+            allowed_bidders: None,
             highest_bid: None
         }, res.clone().auctions.into_iter().nth(n as usize).unwrap());
     }
@@ -863,6 +894,7 @@ fn try_auction_queries() {
         starting_price: coin(100u128 + n as u128, NATIVE_DENOM),
         reserve_price: Some(coin(200u128 + n as u128, NATIVE_DENOM)),
         funds_recipient: None,
+        allowed_bidders: None,
         highest_bid: Some(AuctionBid { price: coin(250u128, "ujunox".to_string()), bidder: bidder2.clone() }),
     }, res.clone().auctions.into_iter().nth(0).unwrap());
     let n = 1;
@@ -874,6 +906,7 @@ fn try_auction_queries() {
         starting_price: coin(100u128 + n as u128, NATIVE_DENOM),
         reserve_price: Some(coin(200u128 + n as u128, NATIVE_DENOM)),
         funds_recipient: None,
+        allowed_bidders: None,
         highest_bid: Some(AuctionBid { price: coin(140u128, "ujunox".to_string()), bidder: bidder.clone() }),
     }, res.clone().auctions.into_iter().nth(1).unwrap());
     let n = 4;
@@ -885,6 +918,7 @@ fn try_auction_queries() {
         starting_price: coin(100u128 + n as u128, NATIVE_DENOM),
         reserve_price: Some(coin(200u128 + n as u128, NATIVE_DENOM)),
         funds_recipient: None,
+        allowed_bidders: None,
         highest_bid: None,
     }, res.clone().auctions.into_iter().nth(2).unwrap());
 
@@ -918,6 +952,7 @@ fn try_auction_queries() {
             starting_price: coin(100u128 + n as u128, NATIVE_DENOM),
             reserve_price: Some(coin(200u128 + n as u128, NATIVE_DENOM)),
             funds_recipient: None,
+            allowed_bidders: None,
             highest_bid: highest_bid
         }, res.clone().auctions.into_iter().nth(n as usize - 2).unwrap());
     }
@@ -947,6 +982,642 @@ fn try_auction_queries() {
         starting_price: coin(100u128 + n as u128, NATIVE_DENOM),
         reserve_price: Some(coin(200u128 + n as u128, NATIVE_DENOM)),
         funds_recipient: None,
+        allowed_bidders: None,
         highest_bid: Some(AuctionBid { price: coin(140u128, "ujunox".to_string()), bidder: bidder.clone() }),
     }, res.clone().auctions.into_iter().nth(0).unwrap());
-}
\ No newline at end of file
+}
+#[test]
+fn try_update_auction_reserve() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (auction_english, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Mint NFT for owner
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &auction_english, TOKEN_ID.to_string());
+    auction(
+        &mut router,
+        &creator,
+        &auction_english,
+        TOKEN_ID.to_string(),
+        block_time.plus_seconds(ONE_DAY),
+        block_time.plus_seconds(ONE_DAY * 2),
+        110u128,
+        210u128,
+        None,
+    );
+
+    // Only the seller can update the reserve price
+    let update_reserve = ExecuteMsg::UpdateAuctionReserve {
+        token_id: TOKEN_ID.to_string(),
+        reserve_price: coin(150u128, NATIVE_DENOM),
+    };
+    let res = router.execute_contract(bidder.clone(), auction_english.clone(), &update_reserve, &[]);
+    assert!(res.is_err());
+
+    // The seller can lower the reserve price
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &update_reserve, &[]);
+    assert!(res.is_ok());
+
+    let query_auction = QueryMsg::Auction {
+        token_id: TOKEN_ID.to_string()
+    };
+    let res: AuctionResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_auction)
+        .unwrap();
+    assert_eq!(res.auction.unwrap().reserve_price, Some(coin(150u128, NATIVE_DENOM)));
+
+    // The reserve price cannot be raised
+    let update_reserve = ExecuteMsg::UpdateAuctionReserve {
+        token_id: TOKEN_ID.to_string(),
+        reserve_price: coin(200u128, NATIVE_DENOM),
+    };
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &update_reserve, &[]);
+    assert_eq!(&res.unwrap_err().root_cause().to_string(), "Reserve price restriction: reserve price can only be lowered");
+
+    // The reserve price cannot be lowered below the starting price
+    let update_reserve = ExecuteMsg::UpdateAuctionReserve {
+        token_id: TOKEN_ID.to_string(),
+        reserve_price: coin(100u128, NATIVE_DENOM),
+    };
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &update_reserve, &[]);
+    assert!(res.is_err());
+
+    // The reserve price cannot be updated once the auction has ended
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY * 2 + 10u64).seconds());
+    let update_reserve = ExecuteMsg::UpdateAuctionReserve {
+        token_id: TOKEN_ID.to_string(),
+        reserve_price: coin(120u128, NATIVE_DENOM),
+    };
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &update_reserve, &[]);
+    assert_eq!(&res.unwrap_err().root_cause().to_string(), "Auction invalid status: Closed");
+
+    // A reserve price cannot be added to an auction created without one
+    let token_id = "2";
+    mint(&mut router, &creator, &collection, token_id.to_string());
+    approve(&mut router, &creator, &collection, &auction_english, token_id.to_string());
+    let block_time = router.block_info().time;
+    let set_auction = ExecuteMsg::SetAuction {
+        token_id: token_id.to_string(),
+        start_time: block_time.plus_seconds(ONE_DAY),
+        end_time: block_time.plus_seconds(ONE_DAY * 2),
+        starting_price: coin(110u128, NATIVE_DENOM),
+        reserve_price: None,
+        funds_recipient: None,
+        allowed_bidders: None,
+    };
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
+    assert!(res.is_ok());
+    let update_reserve = ExecuteMsg::UpdateAuctionReserve {
+        token_id: token_id.to_string(),
+        reserve_price: coin(150u128, NATIVE_DENOM),
+    };
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &update_reserve, &[]);
+    assert_eq!(
+        &res.unwrap_err().root_cause().to_string(),
+        "Reserve price restriction: cannot add a reserve price to an auction without one"
+    );
+}
+
+#[test]
+fn try_query_min_outbid() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (auction_english, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // No auction exists yet
+    let query_min_outbid = QueryMsg::MinOutbid {
+        token_id: TOKEN_ID.to_string()
+    };
+    let res: MinOutbidResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_min_outbid)
+        .unwrap();
+    assert_eq!(res.min_outbid, None);
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &auction_english, TOKEN_ID.to_string());
+    auction(
+        &mut router,
+        &creator,
+        &auction_english,
+        TOKEN_ID.to_string(),
+        block_time.plus_seconds(ONE_DAY),
+        block_time.plus_seconds(ONE_DAY * 2),
+        110u128,
+        210u128,
+        None,
+    );
+
+    // Without bids the minimum is the starting price
+    let res: MinOutbidResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_min_outbid)
+        .unwrap();
+    assert_eq!(res.min_outbid, Some(coin(110u128, NATIVE_DENOM)));
+
+    // With a bid the minimum is the highest bid plus the min_bid_increment
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY + TEN_MINS).seconds());
+    auction_bid(&mut router, &bidder, &auction_english, TOKEN_ID.to_string(), 150u128);
+    let res: MinOutbidResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_min_outbid)
+        .unwrap();
+    assert_eq!(res.min_outbid, Some(coin(153u128, NATIVE_DENOM)));
+}
+
+#[test]
+fn try_max_expiry_horizon() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, _bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (auction_english, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Auctions must end within 30 days
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: None,
+        min_price: None,
+        min_bid_increment: None,
+        min_duration: None,
+        max_duration: None,
+        closed_duration: None,
+        buffer_duration: None,
+        max_expiry_horizon_seconds: Some(ONE_DAY * 30),
+        min_bids_to_settle: None,
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), auction_english.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &auction_english, TOKEN_ID.to_string());
+
+    // An end time beyond the horizon is rejected, even within max_duration
+    let set_auction = ExecuteMsg::SetAuction {
+        token_id: TOKEN_ID.to_string(),
+        start_time: block_time.plus_seconds(ONE_DAY),
+        end_time: block_time.plus_seconds(ONE_DAY * 31),
+        starting_price: coin(110, NATIVE_DENOM),
+        reserve_price: None,
+        funds_recipient: None,
+        allowed_bidders: None,
+    };
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidStartEndTime(String::from("end time is beyond the maximum horizon")).to_string(),
+    );
+
+    // An end time within the horizon is accepted
+    let set_auction = ExecuteMsg::SetAuction {
+        token_id: TOKEN_ID.to_string(),
+        start_time: block_time.plus_seconds(ONE_DAY),
+        end_time: block_time.plus_seconds(ONE_DAY * 30),
+        starting_price: coin(110, NATIVE_DENOM),
+        reserve_price: None,
+        funds_recipient: None,
+        allowed_bidders: None,
+    };
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn try_auction_allowed_bidders() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (auction_english, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Only bidder may bid on the auction
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &auction_english, TOKEN_ID.to_string());
+    let set_auction = ExecuteMsg::SetAuction {
+        token_id: TOKEN_ID.to_string(),
+        start_time: block_time.plus_seconds(ONE_DAY),
+        end_time: block_time.plus_seconds(ONE_DAY * 2),
+        starting_price: coin(110, NATIVE_DENOM),
+        reserve_price: None,
+        funds_recipient: None,
+        allowed_bidders: Some(vec![bidder.to_string()]),
+    };
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
+    assert!(res.is_ok());
+
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY + TEN_MINS).seconds());
+
+    // An outsider is rejected
+    let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(120, NATIVE_DENOM),
+    };
+    let res = router.execute_contract(bidder2.clone(), auction_english.clone(), &set_auction_bid, &coins(120, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::Unauthorized(String::from("bidder is not allowed on this auction")).to_string(),
+    );
+
+    // An allowed bidder succeeds
+    auction_bid(&mut router, &bidder, &auction_english, TOKEN_ID.to_string(), 120u128);
+    let query_auction = QueryMsg::Auction {
+        token_id: TOKEN_ID.to_string()
+    };
+    let res: AuctionResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_auction)
+        .unwrap();
+    assert_eq!(res.auction.unwrap().highest_bid.unwrap().bidder, bidder);
+}
+
+#[test]
+fn try_min_bids_to_settle() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (auction_english, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Auctions need at least 2 bids to be closed with a sale
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: None,
+        min_price: None,
+        min_bid_increment: None,
+        min_duration: None,
+        max_duration: None,
+        closed_duration: None,
+        buffer_duration: None,
+        max_expiry_horizon_seconds: None,
+        min_bids_to_settle: Some(2),
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), auction_english.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &auction_english, TOKEN_ID.to_string());
+    auction(
+        &mut router,
+        &creator,
+        &auction_english,
+        TOKEN_ID.to_string(),
+        block_time.plus_seconds(ONE_DAY),
+        block_time.plus_seconds(ONE_DAY * 2),
+        110u128,
+        210u128,
+        None,
+    );
+
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY + TEN_MINS).seconds());
+    auction_bid(&mut router, &bidder, &auction_english, TOKEN_ID.to_string(), 150u128);
+
+    // A single bid is not enough to accept
+    let close_auction = ExecuteMsg::CloseAuction {
+        token_id: TOKEN_ID.to_string(),
+        accept_highest_bid: true
+    };
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &close_auction, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InsufficientBids(2, 1).to_string(),
+    );
+
+    // Outbidding themselves does not count the same bidder again
+    auction_bid(&mut router, &bidder, &auction_english, TOKEN_ID.to_string(), 160u128);
+    auction_bid(&mut router, &bidder, &auction_english, TOKEN_ID.to_string(), 170u128);
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &close_auction, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InsufficientBids(2, 1).to_string(),
+    );
+
+    // After a bid from a second bidder the highest bid can be accepted
+    auction_bid(&mut router, &bidder2, &auction_english, TOKEN_ID.to_string(), 180u128);
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &close_auction, &[]);
+    assert!(res.is_ok());
+
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder2.to_string());
+}
+
+#[test]
+fn try_min_bids_to_settle_lowered_reserve() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (auction_english, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Auctions need at least 2 bids to settle
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: None,
+        min_price: None,
+        min_bid_increment: None,
+        min_duration: None,
+        max_duration: None,
+        closed_duration: None,
+        buffer_duration: None,
+        max_expiry_horizon_seconds: None,
+        min_bids_to_settle: Some(2),
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), auction_english.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &auction_english, TOKEN_ID.to_string());
+    auction(
+        &mut router,
+        &creator,
+        &auction_english,
+        TOKEN_ID.to_string(),
+        block_time.plus_seconds(ONE_DAY),
+        block_time.plus_seconds(ONE_DAY * 2),
+        110u128,
+        210u128,
+        None,
+    );
+
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY + TEN_MINS).seconds());
+    auction_bid(&mut router, &bidder, &auction_english, TOKEN_ID.to_string(), 150u128);
+
+    // Lowering the reserve to the single bid meets it
+    let update_reserve = ExecuteMsg::UpdateAuctionReserve {
+        token_id: TOKEN_ID.to_string(),
+        reserve_price: coin(150u128, NATIVE_DENOM),
+    };
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &update_reserve, &[]);
+    assert!(res.is_ok());
+
+    // The auction still cannot settle with a single bid
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY * 2 + TEN_MINS).seconds());
+    let finalize_auction = ExecuteMsg::FinalizeAuction {
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res = router.execute_contract(bidder.clone(), auction_english.clone(), &finalize_auction, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InsufficientBids(2, 1).to_string(),
+    );
+
+    // Once expired it can be voided, returning the NFT to the seller
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY * 3 + TEN_MINS).seconds());
+    let void_auction = ExecuteMsg::VoidAuction {
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res = router.execute_contract(bidder.clone(), auction_english.clone(), &void_auction, &[]);
+    assert!(res.is_ok());
+
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, creator.to_string());
+}
+
+#[test]
+fn try_update_auction_reserve_min_price() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, _bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (auction_english, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &auction_english, TOKEN_ID.to_string());
+    auction(
+        &mut router,
+        &creator,
+        &auction_english,
+        TOKEN_ID.to_string(),
+        block_time.plus_seconds(ONE_DAY),
+        block_time.plus_seconds(ONE_DAY * 2),
+        110u128,
+        210u128,
+        None,
+    );
+
+    // Raise min_price above the auction's starting price
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: None,
+        min_price: Some(Uint128::from(120u128)),
+        min_bid_increment: None,
+        min_duration: None,
+        max_duration: None,
+        closed_duration: None,
+        buffer_duration: None,
+        max_expiry_horizon_seconds: None,
+        min_bids_to_settle: None,
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), auction_english.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    // Editing the auction revalidates its prices against the new min_price
+    let update_reserve = ExecuteMsg::UpdateAuctionReserve {
+        token_id: TOKEN_ID.to_string(),
+        reserve_price: coin(150u128, NATIVE_DENOM),
+    };
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &update_reserve, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidPrice {}.to_string(),
+    );
+
+    let query_auction = QueryMsg::Auction {
+        token_id: TOKEN_ID.to_string()
+    };
+    let res: AuctionResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_auction)
+        .unwrap();
+    assert_eq!(res.auction.unwrap().reserve_price, Some(coin(210u128, NATIVE_DENOM)));
+}
+
+#[test]
+fn try_set_auction_expires_in_seconds() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, _bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (auction_english, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &auction_english, TOKEN_ID.to_string());
+    let set_auction = ExecuteMsg::SetAuction {
+        token_id: TOKEN_ID.to_string(),
+        start_time: block_time.plus_seconds(ONE_DAY),
+        end_time: block_time.plus_seconds(ONE_DAY * 2),
+        starting_price: coin(110, NATIVE_DENOM),
+        reserve_price: None,
+        funds_recipient: None,
+        allowed_bidders: None,
+    };
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]).unwrap();
+
+    // The event carries the seconds remaining until the auction ends
+    let set_auction_event = res.events.iter().find(|e| e.ty == "wasm-set-auction").unwrap();
+    let expires_in_seconds = set_auction_event
+        .attributes
+        .iter()
+        .find(|a| a.key == "expires_in_seconds")
+        .unwrap();
+    assert_eq!(expires_in_seconds.value, (ONE_DAY * 2).to_string());
+}
+
+#[test]
+fn try_query_auction_time_remaining() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, _bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (auction_english, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &auction_english, TOKEN_ID.to_string());
+    auction(
+        &mut router,
+        &creator,
+        &auction_english,
+        TOKEN_ID.to_string(),
+        block_time.plus_seconds(ONE_DAY),
+        block_time.plus_seconds(ONE_DAY * 2),
+        110u128,
+        210u128,
+        None,
+    );
+
+    let query_time_remaining = QueryMsg::AuctionTimeRemaining {
+        token_id: TOKEN_ID.to_string(),
+    };
+
+    // Well before the end, outside the extension window
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY + TEN_MINS).seconds());
+    let res: AuctionTimeRemainingResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_time_remaining)
+        .unwrap();
+    assert_eq!(res, AuctionTimeRemainingResponse {
+        seconds_remaining: ONE_DAY - TEN_MINS,
+        in_extension_window: false,
+    });
+
+    // Within buffer_duration of the end, a bid would extend the auction
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY * 2 - 60).seconds());
+    let res: AuctionTimeRemainingResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_time_remaining)
+        .unwrap();
+    assert_eq!(res, AuctionTimeRemainingResponse {
+        seconds_remaining: 60,
+        in_extension_window: true,
+    });
+
+    // Once ended, no time remains
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY * 2 + 60).seconds());
+    let res: AuctionTimeRemainingResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_time_remaining)
+        .unwrap();
+    assert_eq!(res, AuctionTimeRemainingResponse {
+        seconds_remaining: 0,
+        in_extension_window: false,
+    });
+}
+
+#[test]
+fn try_query_would_win_auction() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (auction_english, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &auction_english, TOKEN_ID.to_string());
+    auction(
+        &mut router,
+        &creator,
+        &auction_english,
+        TOKEN_ID.to_string(),
+        block_time.plus_seconds(ONE_DAY),
+        block_time.plus_seconds(ONE_DAY * 2),
+        110u128,
+        210u128,
+        None,
+    );
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY).seconds());
+
+    let would_win = |router: &App, bidder: &Addr| -> WouldWinAuctionResponse {
+        router
+            .wrap()
+            .query_wasm_smart(auction_english.clone(), &QueryMsg::WouldWinAuction {
+                token_id: TOKEN_ID.to_string(),
+                bidder: bidder.to_string(),
+            })
+            .unwrap()
+    };
+
+    // The leader is below the reserve
+    auction_bid(&mut router, &bidder, &auction_english, TOKEN_ID.to_string(), 150u128);
+    assert_eq!(would_win(&router, &bidder), WouldWinAuctionResponse {
+        is_highest_bidder: true,
+        meets_reserve: false,
+    });
+    assert_eq!(would_win(&router, &bidder2), WouldWinAuctionResponse {
+        is_highest_bidder: false,
+        meets_reserve: false,
+    });
+
+    // Outbid above the reserve, the lead changes hands
+    auction_bid(&mut router, &bidder2, &auction_english, TOKEN_ID.to_string(), 220u128);
+    assert_eq!(would_win(&router, &bidder), WouldWinAuctionResponse {
+        is_highest_bidder: false,
+        meets_reserve: false,
+    });
+    assert_eq!(would_win(&router, &bidder2), WouldWinAuctionResponse {
+        is_highest_bidder: true,
+        meets_reserve: true,
+    });
+}
@@ -1,12 +1,13 @@
 use crate::msg::{
     QueryMsg, QueryOptions, TokenTimestampOffset, TokenPriceOffset,
-    AuctionResponse, AuctionsResponse, ConfigResponse
+    AuctionResponse, AuctionsResponse, ConfigResponse, MinOutbidResponse, AuctionTimeRemainingResponse,
+    WouldWinAuctionResponse,
 };
 use crate::state::{
     CONFIG, TokenId, auctions, AuctionStatus
 };
 use crate::helpers::option_bool_to_order;
-use cosmwasm_std::{entry_point, to_binary, Addr, Binary, Deps, Env, StdResult, Uint128};
+use cosmwasm_std::{coin, entry_point, to_binary, Addr, Binary, Deps, Env, StdResult, Uint128};
 use cw_storage_plus::Bound;
 
 // Query limits
@@ -22,6 +23,16 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::Auction {
             token_id,
         } => to_binary(&query_auction(deps, env, token_id)?),
+        QueryMsg::MinOutbid {
+            token_id,
+        } => to_binary(&query_min_outbid(deps, token_id)?),
+        QueryMsg::AuctionTimeRemaining {
+            token_id,
+        } => to_binary(&query_auction_time_remaining(deps, env, token_id)?),
+        QueryMsg::WouldWinAuction {
+            token_id,
+            bidder,
+        } => to_binary(&query_would_win_auction(deps, token_id, api.addr_validate(&bidder)?)?),
         QueryMsg::AuctionsByStartTime {
             query_options
         } => to_binary(&query_auctions_by_start_time(
@@ -82,6 +93,51 @@ pub fn query_auction(deps: Deps, env: Env, token_id: TokenId) -> StdResult<Aucti
     Ok(AuctionResponse { auction, auction_status, is_reserve_price_met, next_bid_min })
 }
 
+pub fn query_min_outbid(deps: Deps, token_id: TokenId) -> StdResult<MinOutbidResponse> {
+    let auction = auctions().may_load(deps.storage, token_id)?;
+    let config = CONFIG.load(deps.storage)?;
+
+    let min_outbid = auction.map(|_auction| {
+        coin(_auction.get_next_bid_min(config.min_bid_increment).u128(), &config.denom)
+    });
+
+    Ok(MinOutbidResponse { min_outbid })
+}
+
+pub fn query_auction_time_remaining(
+    deps: Deps,
+    env: Env,
+    token_id: TokenId,
+) -> StdResult<AuctionTimeRemainingResponse> {
+    let auction = auctions().load(deps.storage, token_id)?;
+    let config = CONFIG.load(deps.storage)?;
+
+    let seconds_remaining = auction.end_time.seconds().saturating_sub(env.block.time.seconds());
+
+    Ok(AuctionTimeRemainingResponse {
+        seconds_remaining,
+        in_extension_window: seconds_remaining > 0 && seconds_remaining < config.buffer_duration,
+    })
+}
+
+pub fn query_would_win_auction(
+    deps: Deps,
+    token_id: TokenId,
+    bidder: Addr,
+) -> StdResult<WouldWinAuctionResponse> {
+    let auction = auctions().load(deps.storage, token_id)?;
+
+    let is_highest_bidder = auction
+        .highest_bid
+        .as_ref()
+        .map_or(false, |highest_bid| highest_bid.bidder == bidder);
+
+    Ok(WouldWinAuctionResponse {
+        is_highest_bidder,
+        meets_reserve: is_highest_bidder && auction.is_reserve_price_met(),
+    })
+}
+
 pub fn query_auctions_by_start_time(
     deps: Deps,
     query_options: &QueryOptions<TokenTimestampOffset>
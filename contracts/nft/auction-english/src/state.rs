@@ -1,6 +1,6 @@
 use std::fmt::{Display, Formatter, Result};
 use cosmwasm_std::{Addr, Decimal, Timestamp, Uint128, Coin};
-use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -28,12 +28,22 @@ pub struct Config {
     pub closed_duration: u64,
     /// The duration an Auction is extended by when a bid is placed in the final minutes
     pub buffer_duration: u64,
+    /// The furthest an auction end time may be from the current block time, in seconds
+    pub max_expiry_horizon_seconds: Option<u64>,
+    /// The minimum number of distinct bidders an auction needs before it can settle with a sale
+    pub min_bids_to_settle: Option<u32>,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
 pub type TokenId = String;
 
+/// The number of distinct bidders on each live auction
+pub const AUCTION_BID_COUNTS: Map<&str, u32> = Map::new("auction_bid_counts");
+
+/// The bidders counted in AUCTION_BID_COUNTS, so re-bids by the same bidder are not counted again
+pub const AUCTION_BIDDERS: Map<(&str, &Addr), bool> = Map::new("auction_bidders");
+
 /// Represents a bid (offer) on an auction in the marketplace
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AuctionBid {
@@ -51,6 +61,8 @@ pub struct Auction {
     pub starting_price: Coin,
     pub reserve_price: Option<Coin>,
     pub funds_recipient: Option<Addr>,
+    /// When set, only these addresses may bid on the auction
+    pub allowed_bidders: Option<Vec<Addr>>,
     pub highest_bid: Option<AuctionBid>
 }
 
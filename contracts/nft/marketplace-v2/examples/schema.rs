@@ -11,8 +11,12 @@ fn main() {
 
     export_schema(&schema_for!(MarketplaceContract), &out_dir);
     export_schema(&schema_for!(msg::InstantiateMsg), &out_dir);
+    export_schema(&schema_for!(msg::MigrateMsg), &out_dir);
     export_schema(&schema_for!(msg::ExecuteMsg), &out_dir);
     export_schema(&schema_for!(msg::QueryMsg), &out_dir);
+    export_schema(&schema_for!(msg::SudoMsg), &out_dir);
+    export_schema(&schema_for!(msg::SignedAskPayload), &out_dir);
+    export_schema(&schema_for!(msg::HookMsg), &out_dir);
 
     export_schema(&schema_for!(msg::QueryOptions<msg::TokenAddrOffset>), &out_dir);
     export_schema(&schema_for!(msg::QueryOptions<msg::TokenPriceOffset>), &out_dir);
@@ -20,11 +24,25 @@ fn main() {
     export_schema(&schema_for!(msg::QueryOptions<msg::CollectionBidPriceOffset>), &out_dir);
     export_schema(&schema_for!(msg::CollectionBidPriceOffset), &out_dir);
     export_schema(&schema_for!(msg::AskResponse), &out_dir);
+    export_schema(&schema_for!(msg::BundleAskResponse), &out_dir);
     export_schema(&schema_for!(msg::AsksResponse), &out_dir);
     export_schema(&schema_for!(msg::AskCountResponse), &out_dir);
+    export_schema(&schema_for!(msg::IsMatchableResponse), &out_dir);
     export_schema(&schema_for!(msg::BidResponse), &out_dir);
     export_schema(&schema_for!(msg::BidsResponse), &out_dir);
+    export_schema(&schema_for!(msg::BidPreviewResponse), &out_dir);
     export_schema(&schema_for!(msg::ConfigResponse), &out_dir);
+    export_schema(&schema_for!(msg::OperatorsResponse), &out_dir);
+    export_schema(&schema_for!(msg::FeeSplitsResponse), &out_dir);
+    export_schema(&schema_for!(msg::RolesResponse), &out_dir);
+    export_schema(&schema_for!(msg::HooksResponse), &out_dir);
+    export_schema(&schema_for!(msg::BootstrapResponse), &out_dir);
     export_schema(&schema_for!(msg::CollectionBidResponse), &out_dir);
     export_schema(&schema_for!(msg::CollectionBidsResponse), &out_dir);
+    export_schema(&schema_for!(msg::CanListResponse), &out_dir);
+    export_schema(&schema_for!(msg::ContractVersionResponse), &out_dir);
+    export_schema(&schema_for!(msg::HighestBidsResponse), &out_dir);
+    export_schema(&schema_for!(msg::LiquidationEstimateResponse), &out_dir);
+    export_schema(&schema_for!(msg::CreditsResponse), &out_dir);
+    export_schema(&schema_for!(msg::SettlementsResponse), &out_dir);
 }
\ No newline at end of file
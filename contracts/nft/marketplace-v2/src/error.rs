@@ -1,4 +1,4 @@
-use cosmwasm_std::{StdError, Uint128};
+use cosmwasm_std::{Coin, StdError, Timestamp, Uint128};
 use cw_utils::PaymentError;
 use thiserror::Error;
 
@@ -19,9 +19,111 @@ pub enum ContractError {
     #[error("Incorrect bid payment: expected {0}, actual {1}")]
     IncorrectBidPayment(Uint128, Uint128),
 
+    #[error("Trading is halted")]
+    Halted {},
+
     #[error("Invalid config: {0}")]
     InvalidConfig(String),
 
+    #[error("Invalid migration: {0}")]
+    InvalidMigration(String),
+
     #[error("Invalid collection bid")]
     InvalidCollectionBid {},
+
+    #[error("Collection bid units above the maximum of {0}")]
+    TooManyUnits(u32),
+
+    #[error("Ask already exists: token_id {0}")]
+    AskAlreadyExists(String),
+
+    #[error("Collection bid must be filled in full: expected {0} units, got {1}")]
+    PartialFill(u32, u32),
+
+    #[error("NFT not held in escrow: token_id {0}")]
+    NftNotEscrowed(String),
+
+    #[error("NFT transfer failed, sale {0} rolled back: {1}")]
+    NftTransferFailed(u64, String),
+
+    #[error("NFT not held by the seller with the marketplace approved: token_id {0}")]
+    NftNotDeliverable(String),
+
+    #[error("Ask custody cannot change while listed: token_id {0}")]
+    CustodyChange(String),
+
+    #[error("Invalid hook: {0}")]
+    InvalidHook(String),
+
+    #[error("Invalid received NFT: {0}")]
+    InvalidReceivedNft(String),
+
+    #[error("Ask is reserved for another buyer: token_id {0}")]
+    ReservedAsk(String),
+
+    #[error("No listings can be bought within the sweep budget")]
+    EmptySweep {},
+
+    #[error("Price mismatch: expected {0}, actual {1}")]
+    PriceMismatch(Coin, Coin),
+
+    #[error("Bid too low: expected at least {0}, actual {1}")]
+    BidTooLow(Uint128, Uint128),
+
+    #[error("No bids on token_id {0}")]
+    NoBids(String),
+
+    #[error("Deadline passed: not after {0}")]
+    DeadlinePassed(Timestamp),
+
+    #[error("Last sale price is not below the collection bid: token_id {0}")]
+    LastSaleNotBelowBid(String),
+
+    #[error("Token does not match the collection bid traits: token_id {0}")]
+    TraitMismatch(String),
+
+    #[error("Invalid fee distribution: {0}")]
+    InvalidFeeDistribution(String),
+
+    #[error("Token was sold too recently to be listed again: token_id {0}")]
+    RelistCooldown(String),
+
+    #[error("External id must be at most {0} characters")]
+    ExternalIdTooLong(usize),
+
+    #[error("Invalid on_remove_msg: {0}")]
+    InvalidOnRemoveMsg(String),
+
+    #[error("Invalid credit share: {0} bps")]
+    InvalidCreditShare(u64),
+
+    #[error("No fee credit to withdraw")]
+    NoCredit {},
+
+    #[error("Market fee and royalties exceed the sale price")]
+    FeesExceedPrice {},
+
+    #[error("Linked addresses cannot trade with each other: {0} and {1}")]
+    LinkedAddresses(String, String),
+
+    #[error("Invalid bundle: {0}")]
+    InvalidBundle(String),
+
+    #[error("Invalid batch size: expected 1 to {0} items")]
+    InvalidBatchSize(usize),
+
+    #[error("Invalid CW20 payment: {0}")]
+    InvalidCw20Payment(String),
+
+    #[error("Invalid signature")]
+    InvalidSignature {},
+
+    #[error("Signed ask nonce already used: {0}")]
+    NonceAlreadyUsed(u64),
+
+    #[error("Signed ask expired")]
+    SignedAskExpired {},
+
+    #[error("A party to the sale cannot be its referrer: {0}")]
+    SelfReferral(String),
 }
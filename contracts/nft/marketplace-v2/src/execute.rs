@@ -1,46 +1,96 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    coin, Addr, Decimal, DepsMut, Env, Event, MessageInfo, StdError,
-    Uint128, Response,
+    coin, from_binary, to_binary, Addr, Api, Binary, Coin, Decimal, DepsMut, Env, Event, MessageInfo, Order,
+    Reply, StdError, StdResult, Timestamp, Uint128, Response,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
+use cw20::Cw20ReceiveMsg;
+use cw721::Cw721ReceiveMsg;
 use cw_utils::{maybe_addr, must_pay, nonpayable};
 
 use crate::error::ContractError;
 use crate::helpers::{
-    map_validate, finalize_sale, price_validate, only_owner_or_seller, only_seller,
-    only_operator, transfer_nft, transfer_token, match_bid, match_ask, validate_config,
+    map_validate, record_sale, finalize_sale, price_validate, only_owner_or_seller, only_seller, only_lister,
+    only_operator, only_after_relist_cooldown, only_matching_traits, match_collection_bid, only_deliverable, is_deliverable, transfer_nft, return_nft, validate_on_remove_msg, dispatch_on_remove_msg, transfer_token, match_bid, match_ask, validate_config, validate_royalty,
+    with_price_display, highest_bid, market_fee, referral_fee, seller_net_proceeds, redeem_credit, prepare_hooks, HOOK_REPLY_ID, is_trade, only_role,
 };
-use crate::msg::{InstantiateMsg, ExecuteMsg};
+use crate::msg::{InstantiateMsg, MigrateMsg, ExecuteMsg, AskInput, HookMsg, SignedAsk, SignedAskPayload};
 use crate::state::{
-    Config, CONFIG, Ask, asks, TokenId, bid_key, bids, Recipient,
-    Bid, CollectionBid, collection_bids
+    Config, CONFIG, LEGACY_CONFIG, legacy_tokens, Role, ROLES, FeeSplits, FEE_SPLITS, Ask, asks, TokenId, bid_key, bids, Recipient,
+    Bid, CollectionBid, collection_bids, LAST_SALE_PRICES, COLLECTED_FEES,
+    BundleAsk, BUNDLE_ASKS, BUNDLE_ASK_SEQ, SIGNING_KEYS, SIGNED_ASK_NONCES, HOOKS, CREDITS, CollectionRoyalty, COLLECTION_ROYALTIES,
 };
 use cw721_base::helpers::Cw721Contract;
+use semver::Version;
+use sha2::{Digest, Sha256};
 
 // Version info for migration info
 const CONTRACT_NAME: &str = "crates.io:marketplace-v2";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+// cw2 name of the legacy marketplace, which can be migrated in place
+const LEGACY_CONTRACT_NAME: &str = "crates.io:cw721-marketplace";
+
+// Max length of an ask's external order id
+const MAX_EXTERNAL_ID_LENGTH: usize = 128;
+// Max bids refunded when an ask is removed
+const MAX_REFUNDS_ON_ASK_REMOVAL: usize = 30;
+// Max bids refunded by a single RefundAllBids call
+const MAX_REFUND_ALL_BIDS_LIMIT: u32 = 100;
+// Max NFTs sold together in a bundle ask
+const MAX_BUNDLE_SIZE: usize = 10;
+// Max listings bought by a single Sweep call
+const MAX_SWEEP_LIMIT: u32 = 30;
+// Max asks or bids set or removed by a single batch call
+const MAX_BATCH_SIZE: usize = 30;
+// Max bids accepted by a single AcceptBids call
+const MAX_ACCEPT_BIDS: usize = 30;
+// Max addresses linked or unlinked in a single call
+const MAX_LINKED_ADDRESSES: usize = 30;
+// Max legacy listings turned into asks by a single MigrateLegacy call
+const MAX_LEGACY_MIGRATION_LIMIT: u32 = 30;
+// Max hook contracts notified of marketplace activity
+const MAX_HOOKS: usize = 10;
+// Max recipients of the market fee split
+const MAX_FEE_SPLITS: usize = 10;
+// Basis points in a whole, the max share of proceeds kept as fee credit
+const MAX_CREDIT_BPS: u64 = 10_000;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     let api = deps.api;
+    let mut operators = map_validate(deps.api, &msg.operators)?;
+    if msg.include_sender_as_operator && !operators.contains(&info.sender) {
+        operators.push(info.sender);
+    }
     let config = Config {
         cw721_address: api.addr_validate(&msg.cw721_address)?,
         denom: msg.denom,
         collector_address: api.addr_validate(&msg.collector_address)?,
         trading_fee_percent: Decimal::percent(msg.trading_fee_bps),
-        operators: map_validate(deps.api, &msg.operators)?,
+        operators,
         min_price: msg.min_price,
+        min_bid_fraction_of_ask: msg.min_bid_fraction_of_ask,
+        accumulate_fees: msg.accumulate_fees,
+        relist_cooldown_seconds: msg.relist_cooldown_seconds,
+        refund_bids_on_ask_removal: msg.refund_bids_on_ask_removal,
+        display_decimals: msg.display_decimals,
+        max_collection_bid_units: msg.max_collection_bid_units,
+        cw20_payments: msg.cw20_payments,
+        accepted_denoms: msg.accepted_denoms,
+        halted: false,
+        referral_fee_share: msg.referral_fee_share,
     };
+    if config.cw20_payments {
+        api.addr_validate(&config.denom)?;
+    }
     validate_config(&config)?;
     CONFIG.save(deps.storage, &config)?;
 
@@ -48,6 +98,153 @@ pub fn instantiate(
 }
 
 
+/// Upgrades this contract to a newer version, or the legacy marketplace to this contract
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(mut deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    let prev = get_contract_version(deps.storage)?;
+
+    let event = Event::new("contract-migrated")
+        .add_attribute("prev-contract", prev.contract.clone())
+        .add_attribute("prev-version", prev.version.clone())
+        .add_attribute("next-version", CONTRACT_VERSION);
+
+    match prev.contract.as_str() {
+        CONTRACT_NAME => {
+            let prev_version = parse_version(&prev.version)?;
+            if prev_version > parse_version(CONTRACT_VERSION)? {
+                return Err(ContractError::InvalidMigration(format!("cannot downgrade from {} to {}", prev.version, CONTRACT_VERSION)));
+            }
+        }
+        LEGACY_CONTRACT_NAME => {
+            migrate_legacy(deps.branch(), msg)?;
+        }
+        _ => return Err(ContractError::InvalidMigration(format!("cannot migrate from {}", prev.contract))),
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_event(event))
+}
+
+fn parse_version(version: &str) -> Result<Version, ContractError> {
+    version
+        .parse()
+        .map_err(|_| ContractError::InvalidMigration(format!("invalid version {}", version)))
+}
+
+/// Replaces the legacy config. The legacy listings are turned into asks afterwards, in batches
+/// bounded by MigrateLegacy calls, so a large legacy book cannot make the migration run out of gas.
+fn migrate_legacy(deps: DepsMut, msg: MigrateMsg) -> Result<(), ContractError> {
+    let min_price = msg.min_price.ok_or_else(|| {
+        ContractError::InvalidMigration(String::from("min_price is required to migrate the legacy marketplace"))
+    })?;
+    let legacy_config = LEGACY_CONFIG.load(deps.storage)?;
+    let config = Config {
+        cw721_address: legacy_config.nft_contract_addr,
+        denom: legacy_config.allowed_native,
+        collector_address: legacy_config.collector_addr,
+        // The legacy fee is a fraction of the price, the trading fee is in percent
+        trading_fee_percent: legacy_config.fee_percentage * Decimal::percent(10_000),
+        operators: vec![legacy_config.admin],
+        min_price,
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: false,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: false,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        cw20_payments: false,
+        accepted_denoms: vec![],
+        halted: false,
+        referral_fee_share: None,
+    };
+    validate_config(&config)?;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(())
+}
+
+/// Turns up to `limit` legacy listings into asks, removing them from the legacy token map. Legacy
+/// listings leave the NFT with its owner, so they become non-custodial asks sold by the current
+/// owner. The legacy marketplace charged its fee to the buyer on top of the price, asks pay it
+/// from the seller's proceeds like any other. Anyone can call this.
+pub fn execute_migrate_legacy(
+    deps: DepsMut,
+    info: MessageInfo,
+    limit: u32,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    if limit == 0 || limit > MAX_LEGACY_MIGRATION_LIMIT {
+        return Err(ContractError::InvalidBatchSize(MAX_LEGACY_MIGRATION_LIMIT as usize));
+    }
+    let config = CONFIG.load(deps.storage)?;
+
+    // One more than the limit is loaded to tell whether listings remain after this batch
+    let mut tokens = legacy_tokens()
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(limit as usize + 1)
+        .map(|item| item.map(|(_, token)| token))
+        .collect::<StdResult<Vec<_>>>()?;
+    let remaining = tokens.len() > limit as usize;
+    tokens.truncate(limit as usize);
+
+    let mut migrated_asks = 0;
+    for token in tokens {
+        legacy_tokens().remove(deps.storage, token.id.clone())?;
+        if !token.on_sale || token.price.is_zero() {
+            continue;
+        }
+        // Tokens burned since they were listed are dropped
+        let owner = match Cw721Contract(config.cw721_address.clone()).owner_of(&deps.querier, token.id.clone(), false) {
+            Ok(res) => deps.api.addr_validate(&res.owner)?,
+            Err(_) => continue,
+        };
+        let ask = Ask {
+            token_id: token.id.clone(),
+            seller: owner,
+            price: coin(token.price.u128(), &config.denom),
+            funds_recipient: None,
+            external_id: None,
+            min_offer: None,
+            pre_authorized: None,
+            min_net_proceeds: None,
+            hidden_min: None,
+            on_remove_msg: None,
+            reserve_for: None,
+            non_custodial: true,
+        };
+        asks().save(deps.storage, token.id, &ask)?;
+        migrated_asks += 1;
+    }
+
+    let event = Event::new("migrate-legacy")
+        .add_attribute("migrated_asks", migrated_asks.to_string())
+        .add_attribute("remaining", remaining.to_string());
+
+    Ok(Response::new().add_event(event))
+}
+
+/// Confirms a settlement once all of its submessages have succeeded; the reply id is the sale_seq.
+/// A failed NFT transfer fails the whole execution, so the buyer's payment and the seller's
+/// proceeds are rolled back with it.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    // Hooks only reply on error, which is recorded without reverting the action that fired them
+    if msg.id == HOOK_REPLY_ID {
+        let event = Event::new("hook-failed")
+            .add_attribute("error", msg.result.into_result().err().unwrap_or_default());
+        return Ok(Response::new().add_event(event));
+    }
+
+    if let Err(err) = msg.result.into_result() {
+        return Err(ContractError::NftTransferFailed(msg.id, err));
+    }
+
+    let event = Event::new("sale-settled")
+        .add_attribute("sale_seq", msg.id.to_string());
+
+    Ok(Response::new().add_event(event))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
@@ -58,12 +255,26 @@ pub fn execute(
     let api = deps.api;
     let message_info = info.clone();
 
+    // Payments through Receive are checked when the wrapped message is executed
+    if is_trade(&msg) && CONFIG.load(deps.storage)?.halted {
+        return Err(ContractError::Halted {});
+    }
+
     match msg {
         ExecuteMsg::UpdateConfig {
             collector_address,
             trading_fee_bps,
             operators,
             min_price,
+            denom,
+            min_bid_fraction_of_ask,
+            accumulate_fees,
+            relist_cooldown_seconds,
+            refund_bids_on_ask_removal,
+            display_decimals,
+            max_collection_bid_units,
+            accepted_denoms,
+            referral_fee_share,
         } => execute_update_config(
             deps,
             info,
@@ -71,11 +282,28 @@ pub fn execute(
             trading_fee_bps,
             operators,
             min_price,
+            denom,
+            min_bid_fraction_of_ask,
+            accumulate_fees,
+            relist_cooldown_seconds,
+            refund_bids_on_ask_removal,
+            display_decimals,
+            max_collection_bid_units,
+            accepted_denoms,
+            referral_fee_share,
         ),
         ExecuteMsg::SetAsk {
             token_id,
             price,
             funds_recipient,
+            external_id,
+            min_offer,
+            pre_authorized,
+            min_net_proceeds,
+            hidden_min,
+            on_remove_msg,
+            reserve_for,
+            non_custodial,
         } => execute_set_ask(
             deps,
             env,
@@ -85,14 +313,104 @@ pub fn execute(
                 seller: message_info.sender,
                 price,
                 funds_recipient: maybe_addr(api, funds_recipient)?,
+                external_id,
+                min_offer,
+                pre_authorized: pre_authorized
+                    .map(|(bidder, price)| -> StdResult<_> { Ok((api.addr_validate(&bidder)?, price)) })
+                    .transpose()?,
+                min_net_proceeds,
+                hidden_min,
+                on_remove_msg,
+                reserve_for: maybe_addr(api, reserve_for)?,
+                non_custodial,
+            },
+        ),
+        ExecuteMsg::CreateAsk {
+            token_id,
+            price,
+            funds_recipient,
+            external_id,
+            min_offer,
+            pre_authorized,
+            min_net_proceeds,
+            hidden_min,
+            on_remove_msg,
+            reserve_for,
+            non_custodial,
+        } => execute_create_ask(
+            deps,
+            env,
+            info,
+            Ask {
+                token_id,
+                seller: message_info.sender,
+                price,
+                funds_recipient: maybe_addr(api, funds_recipient)?,
+                external_id,
+                min_offer,
+                pre_authorized: pre_authorized
+                    .map(|(bidder, price)| -> StdResult<_> { Ok((api.addr_validate(&bidder)?, price)) })
+                    .transpose()?,
+                min_net_proceeds,
+                hidden_min,
+                on_remove_msg,
+                reserve_for: maybe_addr(api, reserve_for)?,
+                non_custodial,
             },
         ),
+        ExecuteMsg::CounterWithAsk {
+            token_id,
+            price,
+        } => execute_counter_with_ask(deps, env, info, token_id, price),
+        ExecuteMsg::SetAsks {
+            asks,
+        } => execute_set_asks(
+            deps,
+            env,
+            info,
+            asks
+                .into_iter()
+                .map(|input| ask_from_input(api, &message_info.sender, input))
+                .collect::<StdResult<Vec<_>>>()?,
+        ),
+        ExecuteMsg::UpdateAsk {
+            token_id,
+            price,
+            reserve_for,
+            clear_reserve_for,
+        } => execute_update_ask(deps, info, token_id, price, maybe_addr(api, reserve_for)?, clear_reserve_for),
         ExecuteMsg::RemoveAsk {
             token_id,
         } => execute_remove_ask(deps, info, token_id),
+        ExecuteMsg::RemoveAsks {
+            token_ids,
+        } => execute_remove_asks(deps, info, token_ids),
+        ExecuteMsg::RemoveStaleAsk {
+            token_id,
+        } => execute_remove_stale_ask(deps, env, info, token_id),
+        ExecuteMsg::SetBundleAsk {
+            token_ids,
+            price,
+            funds_recipient,
+        } => execute_set_bundle_ask(
+            deps,
+            env,
+            info,
+            token_ids,
+            price,
+            maybe_addr(api, funds_recipient)?,
+        ),
+        ExecuteMsg::RemoveBundleAsk {
+            bundle_id,
+        } => execute_remove_bundle_ask(deps, info, bundle_id),
+        ExecuteMsg::BuyBundle {
+            bundle_id,
+        } => execute_buy_bundle(deps, env, info, bundle_id),
         ExecuteMsg::SetBid {
             token_id,
             price,
+            refund_to,
+            referrer,
         } => execute_set_bid(
             deps,
             env,
@@ -101,23 +419,62 @@ pub fn execute(
                 token_id,
                 bidder: message_info.sender,
                 price,
+                refund_to: maybe_addr(api, refund_to)?,
             },
+            maybe_addr(api, referrer)?,
         ),
+        ExecuteMsg::BuyNow {
+            token_id,
+            expected_price,
+            referrer,
+        } => execute_buy_now(deps, env, info, token_id, expected_price, maybe_addr(api, referrer)?),
+        ExecuteMsg::Sweep {
+            limit,
+            max_total_price,
+        } => execute_sweep(deps, env, info, limit, max_total_price),
         ExecuteMsg::RemoveBid {
             token_id,
         } => execute_remove_bid(deps, env, info, token_id),
+        ExecuteMsg::RemoveBids {
+            token_ids,
+        } => execute_remove_bids(deps, env, info, token_ids),
         ExecuteMsg::AcceptBid {
             token_id,
             bidder,
+            credit_bps,
+            min_price,
+            referrer,
         } => execute_accept_bid(
             deps,
+            env,
             info,
             token_id,
             api.addr_validate(&bidder)?,
+            credit_bps,
+            min_price,
+            maybe_addr(api, referrer)?,
+        ),
+        ExecuteMsg::WithdrawCredit {} => execute_withdraw_credit(deps, info),
+        ExecuteMsg::AcceptBids { items } => execute_accept_bids(
+            deps,
+            env,
+            info,
+            items
+                .into_iter()
+                .map(|(token_id, bidder)| -> StdResult<_> { Ok((token_id, api.addr_validate(&bidder)?)) })
+                .collect::<StdResult<Vec<_>>>()?,
         ),
+        ExecuteMsg::AcceptBestBidBefore {
+            token_id,
+            not_after,
+        } => execute_accept_best_bid_before(deps, env, info, token_id, not_after),
         ExecuteMsg::SetCollectionBid {
             units,
             price,
+            all_or_nothing,
+            only_below_last_sale,
+            traits,
+            budget,
         } => execute_set_collection_bid(
             deps,
             info,
@@ -125,24 +482,205 @@ pub fn execute(
                 units,
                 price,
                 bidder: message_info.sender,
+                all_or_nothing,
+                only_below_last_sale,
+                traits,
+                budget,
             }
         ),
         ExecuteMsg::RemoveCollectionBid { } => {
             execute_remove_collection_bid(deps, env, info)
         }
+        ExecuteMsg::ReduceCollectionBid { new_units } => {
+            execute_reduce_collection_bid(deps, info, new_units)
+        }
+        ExecuteMsg::IncreaseCollectionBidUnits { additional_units } => {
+            execute_increase_collection_bid_units(deps, info, additional_units)
+        }
         ExecuteMsg::AcceptCollectionBid {
             token_id,
             bidder,
+            min_price,
         } => execute_accept_collection_bid(
             deps,
+            env,
             info,
-            token_id,
+            vec![token_id],
+            api.addr_validate(&bidder)?,
+            min_price,
+        ),
+        ExecuteMsg::AcceptCollectionBidBulk {
+            token_ids,
+            bidder,
+        } => execute_accept_collection_bid(
+            deps,
+            env,
+            info,
+            token_ids,
             api.addr_validate(&bidder)?,
+            None,
+        ),
+        ExecuteMsg::DistributeFees { recipients } => {
+            execute_distribute_fees(deps, info, recipients)
+        }
+        ExecuteMsg::RefundAllBids { limit } => {
+            execute_refund_all_bids(deps, info, limit)
+        }
+        ExecuteMsg::SetFeeSplits {
+            recipients,
+            burn_share,
+        } => execute_set_fee_splits(
+            deps,
+            info,
+            recipients
+                .into_iter()
+                .map(|(recipient, share)| -> StdResult<_> { Ok((api.addr_validate(&recipient)?, share)) })
+                .collect::<StdResult<Vec<_>>>()?,
+            burn_share,
+        ),
+        ExecuteMsg::SetCollectionRoyalty {
+            collection,
+            recipient,
+            percent,
+        } => execute_set_collection_royalty(
+            deps,
+            info,
+            api.addr_validate(&collection)?,
+            vec![(api.addr_validate(&recipient)?, percent)],
+        ),
+        ExecuteMsg::SetCollectionRoyaltySplits {
+            collection,
+            splits,
+        } => execute_set_collection_royalty(
+            deps,
+            info,
+            api.addr_validate(&collection)?,
+            splits
+                .into_iter()
+                .map(|(recipient, share)| -> StdResult<_> { Ok((api.addr_validate(&recipient)?, share)) })
+                .collect::<StdResult<Vec<_>>>()?,
         ),
+        ExecuteMsg::LinkAddresses { set_id, addresses } => {
+            execute_link_addresses(deps, info, set_id, addresses)
+        }
+        ExecuteMsg::UnlinkAddresses { addresses } => {
+            execute_unlink_addresses(deps, info, addresses)
+        }
+        ExecuteMsg::GrantRole { address, role } => execute_grant_role(deps, info, api.addr_validate(&address)?, role),
+        ExecuteMsg::RevokeRole { address, role } => execute_revoke_role(deps, info, api.addr_validate(&address)?, role),
+        ExecuteMsg::SetHalted { halted } => execute_set_halted(deps, info, halted),
+        ExecuteMsg::AddHook { hook } => execute_add_hook(deps, info, api.addr_validate(&hook)?),
+        ExecuteMsg::RemoveHook { hook } => execute_remove_hook(deps, info, api.addr_validate(&hook)?),
+        ExecuteMsg::SetSigningKey { pubkey } => {
+            execute_set_signing_key(deps, info, pubkey)
+        }
+        ExecuteMsg::FillSignedAsk {
+            ask,
+            signature,
+        } => execute_fill_signed_ask(deps, env, info, ask, signature),
+        ExecuteMsg::CancelSignedAsk { nonce } => execute_cancel_signed_ask(deps, info, nonce),
+        ExecuteMsg::MigrateLegacy { limit } => execute_migrate_legacy(deps, info, limit),
+        ExecuteMsg::Receive(receive_msg) => execute_receive(deps, env, info, receive_msg),
+        ExecuteMsg::ReceiveNft(receive_msg) => execute_receive_nft(deps, env, info, receive_msg),
+    }
+}
+
+/// The CW20 token forwards a payment with the message to pay for, which runs as if the
+/// token sender had attached the amount as funds
+pub fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    receive_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.cw20_payments || info.sender != config.denom {
+        return Err(ContractError::InvalidCw20Payment(format!("token {} is not accepted", info.sender)));
+    }
+
+    let msg: ExecuteMsg = from_binary(&receive_msg.msg)?;
+    match msg {
+        ExecuteMsg::SetBid { .. }
+        | ExecuteMsg::BuyNow { .. }
+        | ExecuteMsg::Sweep { .. }
+        | ExecuteMsg::BuyBundle { .. }
+        | ExecuteMsg::SetCollectionBid { .. }
+        | ExecuteMsg::IncreaseCollectionBidUnits { .. }
+        | ExecuteMsg::FillSignedAsk { .. } => {}
+        _ => {
+            return Err(ContractError::InvalidCw20Payment(String::from("message does not take a payment")));
+        }
+    }
+
+    let payer = MessageInfo {
+        sender: deps.api.addr_validate(&receive_msg.sender)?,
+        funds: vec![coin(receive_msg.amount.u128(), &config.denom)],
+    };
+    execute(deps, env, payer, msg)
+}
+
+/// Lists an NFT sent to the marketplace with SendNft, which escrows it without an approval
+pub fn execute_receive_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    receive_msg: Cw721ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.cw721_address {
+        return Err(ContractError::InvalidReceivedNft(format!("collection {} is not traded here", info.sender)));
+    }
+
+    let api = deps.api;
+    let seller = api.addr_validate(&receive_msg.sender)?;
+    let ask = match from_binary(&receive_msg.msg)? {
+        ExecuteMsg::SetAsk {
+            token_id,
+            price,
+            funds_recipient,
+            external_id,
+            min_offer,
+            pre_authorized,
+            min_net_proceeds,
+            hidden_min,
+            on_remove_msg,
+            reserve_for,
+            non_custodial,
+        } => Ask {
+            token_id,
+            seller: seller.clone(),
+            price,
+            funds_recipient: maybe_addr(api, funds_recipient)?,
+            external_id,
+            min_offer,
+            pre_authorized: pre_authorized
+                .map(|(bidder, price)| -> StdResult<_> { Ok((api.addr_validate(&bidder)?, price)) })
+                .transpose()?,
+            min_net_proceeds,
+            hidden_min,
+            on_remove_msg,
+            reserve_for: maybe_addr(api, reserve_for)?,
+            non_custodial,
+        },
+        _ => {
+            return Err(ContractError::InvalidReceivedNft(String::from("message is not a SetAsk")));
+        }
+    };
+    if ask.non_custodial {
+        return Err(ContractError::InvalidReceivedNft(String::from("a sent NFT is held in escrow")));
+    }
+    if ask.token_id != receive_msg.token_id {
+        return Err(ContractError::InvalidReceivedNft(format!("token_id {} was not sent", ask.token_id)));
     }
+
+    let seller_info = MessageInfo {
+        sender: seller,
+        funds: vec![],
+    };
+    set_ask(deps, env, seller_info, ask, true)
 }
 
-/// An operator may update the marketplace config
+/// An operator or params admin may update the marketplace config
 pub fn execute_update_config(
     deps: DepsMut,
     info: MessageInfo,
@@ -150,9 +688,61 @@ pub fn execute_update_config(
     trading_fee_bps: Option<u64>,
     operators: Option<Vec<String>>,
     min_price: Option<Uint128>,
+    denom: Option<String>,
+    min_bid_fraction_of_ask: Option<Decimal>,
+    accumulate_fees: Option<bool>,
+    relist_cooldown_seconds: Option<u64>,
+    refund_bids_on_ask_removal: Option<bool>,
+    display_decimals: Option<u32>,
+    max_collection_bid_units: Option<u32>,
+    accepted_denoms: Option<Vec<Coin>>,
+    referral_fee_share: Option<Decimal>,
 ) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    // A params admin cannot change who the operators are
+    if operators.is_some() {
+        only_operator(&info, &config)?;
+    } else {
+        only_role(deps.as_ref(), &info, &config, Role::ParamsAdmin)?;
+    }
+
+    update_config(
+        deps,
+        collector_address,
+        trading_fee_bps,
+        operators,
+        min_price,
+        denom,
+        min_bid_fraction_of_ask,
+        accumulate_fees,
+        relist_cooldown_seconds,
+        refund_bids_on_ask_removal,
+        display_decimals,
+        max_collection_bid_units,
+        accepted_denoms,
+        referral_fee_share,
+    )?;
+    Ok(Response::new())
+}
+
+/// Applies a config update from an operator or from governance through sudo
+pub fn update_config(
+    deps: DepsMut,
+    collector_address: Option<String>,
+    trading_fee_bps: Option<u64>,
+    operators: Option<Vec<String>>,
+    min_price: Option<Uint128>,
+    denom: Option<String>,
+    min_bid_fraction_of_ask: Option<Decimal>,
+    accumulate_fees: Option<bool>,
+    relist_cooldown_seconds: Option<u64>,
+    refund_bids_on_ask_removal: Option<bool>,
+    display_decimals: Option<u32>,
+    max_collection_bid_units: Option<u32>,
+    accepted_denoms: Option<Vec<Coin>>,
+    referral_fee_share: Option<Decimal>,
+) -> Result<Config, ContractError> {
     let mut config = CONFIG.load(deps.storage)?;
-    only_operator(&info, &config)?;
 
     if let Some(_collector_address) = collector_address {
         config.collector_address = deps.api.addr_validate(&_collector_address)?;
@@ -166,9 +756,67 @@ pub fn execute_update_config(
     if let Some(_min_price) = min_price {
         config.min_price = _min_price;
     }
+    if let Some(_denom) = denom {
+        // Escrowed bids must be refunded in the denom they were paid in
+        if _denom != config.denom {
+            let has_bids = bids()
+                .keys_raw(deps.storage, None, None, Order::Ascending)
+                .next()
+                .is_some();
+            let has_collection_bids = collection_bids()
+                .keys_raw(deps.storage, None, None, Order::Ascending)
+                .next()
+                .is_some();
+            if has_bids || has_collection_bids {
+                return Err(ContractError::InvalidConfig(String::from("denom cannot be updated while bids are escrowed")));
+            }
+            if !COLLECTED_FEES.may_load(deps.storage)?.unwrap_or_default().is_zero() {
+                return Err(ContractError::InvalidConfig(String::from("denom cannot be updated while fees are collected")));
+            }
+            // Fee credit is held in the config denom
+            let has_credits = CREDITS
+                .keys_raw(deps.storage, None, None, Order::Ascending)
+                .next()
+                .is_some();
+            if has_credits {
+                return Err(ContractError::InvalidConfig(String::from("denom cannot be updated while fee credit is held")));
+            }
+        }
+        if config.cw20_payments {
+            deps.api.addr_validate(&_denom)?;
+        }
+        config.denom = _denom;
+    }
+    if let Some(_min_bid_fraction_of_ask) = min_bid_fraction_of_ask {
+        config.min_bid_fraction_of_ask = Some(_min_bid_fraction_of_ask);
+    }
+    if let Some(_accumulate_fees) = accumulate_fees {
+        config.accumulate_fees = _accumulate_fees;
+    }
+    if let Some(_relist_cooldown_seconds) = relist_cooldown_seconds {
+        config.relist_cooldown_seconds = Some(_relist_cooldown_seconds);
+    }
+    if let Some(_refund_bids_on_ask_removal) = refund_bids_on_ask_removal {
+        config.refund_bids_on_ask_removal = _refund_bids_on_ask_removal;
+    }
+    if let Some(_display_decimals) = display_decimals {
+        config.display_decimals = Some(_display_decimals);
+    }
+    if let Some(_max_collection_bid_units) = max_collection_bid_units {
+        config.max_collection_bid_units = Some(_max_collection_bid_units);
+    }
+    if let Some(_accepted_denoms) = accepted_denoms {
+        config.accepted_denoms = _accepted_denoms;
+    }
+    if let Some(_referral_fee_share) = referral_fee_share {
+        config.referral_fee_share = Some(_referral_fee_share);
+    }
     validate_config(&config)?;
+    if let Some(royalty) = COLLECTION_ROYALTIES.may_load(deps.storage, &config.cw721_address)? {
+        validate_royalty(&config, royalty.percent())?;
+    }
     CONFIG.save(deps.storage, &config)?;
-    Ok(Response::new())
+    Ok(config)
 }
 
 /// A seller may set an Ask on their NFT to list it on Marketplace
@@ -179,54 +827,151 @@ pub fn execute_set_ask(
     ask: Ask,
 ) -> Result<Response, ContractError> {
     nonpayable(&info)?;
-    
+    set_ask(deps, env, info, ask, false)
+}
+
+/// Lists an ask, `received` when the marketplace was just sent the unlisted NFT by the seller
+fn set_ask(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    ask: Ask,
+    received: bool,
+) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     price_validate(&ask.price, &config)?;
+    if let Some(min_offer) = ask.min_offer {
+        if min_offer > ask.price.amount {
+            return Err(ContractError::InvalidPrice {});
+        }
+    }
+    if let Some((_, agreed_price)) = &ask.pre_authorized {
+        if agreed_price > &ask.price.amount {
+            return Err(ContractError::InvalidPrice {});
+        }
+    }
+    // Bids above the price buy the NFT at the price, so a higher hidden min could never be met
+    if let Some(hidden_min) = ask.hidden_min {
+        if hidden_min > ask.price.amount {
+            return Err(ContractError::InvalidPrice {});
+        }
+    }
+    if let Some(external_id) = &ask.external_id {
+        if external_id.len() > MAX_EXTERNAL_ID_LENGTH {
+            return Err(ContractError::ExternalIdTooLong(MAX_EXTERNAL_ID_LENGTH));
+        }
+    }
+    if let Some(on_remove_msg) = &ask.on_remove_msg {
+        validate_on_remove_msg(&ask.token_id, on_remove_msg)?;
+        if ask.non_custodial {
+            return Err(ContractError::InvalidOnRemoveMsg("the NFT is not held in escrow".to_string()));
+        }
+    }
 
-    let existing_ask = asks().load(deps.storage, ask.token_id.clone()).ok();
-    only_owner_or_seller(
-        deps.as_ref(),
-        &info,
-        &config.cw721_address,
-        &ask.token_id,
-        &existing_ask.clone().map_or(None, |a| Some(a.seller)),
-    )?;
+    // The seller's non-custodial ask looks stale once its NFT is received, so check it first
+    if received {
+        if let Some(existing) = asks().may_load(deps.storage, ask.token_id.clone())? {
+            if existing.seller == ask.seller && existing.non_custodial != ask.non_custodial {
+                return Err(ContractError::CustodyChange(ask.token_id));
+            }
+        }
+    }
 
     let mut response = Response::new();
+    remove_stale_ask(deps.branch(), &env, &ask.token_id, &config, &mut response)?;
+
+    // A received NFT is already escrowed, and cannot have an ask
+    let existing_ask = if received {
+        only_after_relist_cooldown(deps.as_ref(), &env, &ask.token_id, &config)?;
+        None
+    } else {
+        only_lister(deps.as_ref(), &env, &info, &ask.token_id, &config)?
+    };
+    if existing_ask.as_ref().map_or(false, |existing| existing.non_custodial != ask.non_custodial) {
+        return Err(ContractError::CustodyChange(ask.token_id));
+    }
+    if ask.non_custodial && !is_deliverable(deps.as_ref(), &env, &ask, &config)? {
+        return Err(ContractError::NftNotDeliverable(ask.token_id));
+    }
+
     let matching_bid = match_ask(deps.as_ref(), &ask, &mut response)?;
+    let matching_collection_bid = match matching_bid {
+        Some(_) => None,
+        None => match_collection_bid(deps.as_ref(), &ask, &mut response)?,
+    };
+    let private_sale = ask.reserve_for.is_some() && (matching_bid.is_some() || matching_collection_bid.is_some());
 
-    match matching_bid {
+    match (matching_bid, matching_collection_bid) {
         // If matching bid found:
-        // * finalize sale
         // * remove bid
         // * if existing ask exists, remove it
-        Some(bid) => {
+        // * finalize sale
+        (Some(bid), _) => {
+            bids().remove(
+                deps.storage,
+                bid_key(&bid.bidder, bid.token_id.clone())
+            )?;
+            if let Some(_existing_ask) = existing_ask  {
+                asks().remove(
+                    deps.storage,
+                    _existing_ask.token_id
+                )?;
+            }
+            let sale_seq = record_sale(deps.branch(), &env, &ask.token_id, &bid.bidder, &ask.seller, &bid.price, &config, None)?;
             finalize_sale(
                 deps.as_ref(),
                 &bid.bidder,
                 &ask.token_id,
-                bid.price.amount,
+                sale_seq,
+                &bid.price,
                 &ask.get_recipient(),
                 Uint128::zero(),
+                Uint128::zero(),
                 &bid.bidder,
+                None,
                 &config,
                 &mut response,
             )?;
-            bids().remove(
-                deps.storage,
-                bid_key(&bid.bidder, bid.token_id.clone())
-            )?;
+        },
+        // If matching collection bid found:
+        // * take a unit (and its payment) off the collection bid
+        // * if existing ask exists, remove it
+        // * finalize sale
+        (None, Some((collection_bid, payment))) => {
+            let mut updated_collection_bid = collection_bid.clone();
+            updated_collection_bid.units -= 1;
+            updated_collection_bid.budget = collection_bid.budget.map(|b| b - payment.amount);
+            if updated_collection_bid.units == 0 || updated_collection_bid.budget == Some(Uint128::zero()) {
+                collection_bids().remove(deps.storage, collection_bid.bidder.clone())?;
+            } else {
+                collection_bids().save(deps.storage, collection_bid.bidder.clone(), &updated_collection_bid)?;
+            }
             if let Some(_existing_ask) = existing_ask  {
                 asks().remove(
                     deps.storage,
                     _existing_ask.token_id
                 )?;
             }
+            let sale_seq = record_sale(deps.branch(), &env, &ask.token_id, &collection_bid.bidder, &ask.seller, &payment, &config, None)?;
+            finalize_sale(
+                deps.as_ref(),
+                &collection_bid.bidder,
+                &ask.token_id,
+                sale_seq,
+                &payment,
+                &ask.get_recipient(),
+                Uint128::zero(),
+                Uint128::zero(),
+                &collection_bid.bidder,
+                None,
+                &config,
+                &mut response,
+            )?;
         },
-        // If matching bid not found:
+        // If no matching bid found:
         // * update ask
-        // * if contract is not the owner of the NFT, transfer NFT to contract
-        None => {
+        // * if contract is not the owner of the NFT and the ask is custodial, transfer NFT to contract
+        (None, None) => {
             asks().update(
                 deps.storage,
                 ask.token_id.clone(),
@@ -234,332 +979,1748 @@ pub fn execute_set_ask(
             )?;
             let res = Cw721Contract(config.cw721_address.clone())
                 .owner_of(&deps.querier, ask.token_id.clone(), false)?;
-            if res.owner != env.contract.address {
+            if !ask.non_custodial && res.owner != env.contract.address {
                 transfer_nft(&ask.token_id, &env.contract.address, &config.cw721_address, &mut response)?;
             }
+            prepare_hooks(deps.as_ref(), &HookMsg::AskCreatedHook { ask: ask.clone().public() }, &mut response)?;
         }
     }
 
-    let event = Event::new("set-ask")
+    let mut event = Event::new("set-ask")
         .add_attribute("collection", config.cw721_address.to_string())
         .add_attribute("token_id", ask.token_id.to_string())
         .add_attribute("seller", ask.seller)
-        .add_attribute("price", ask.price.to_string());
+        .add_attribute("price", ask.price.to_string())
+        .add_attribute("external_id", ask.external_id.unwrap_or_default());
+    if private_sale {
+        event = event.add_attribute("private_sale", "true");
+    }
+    let event = with_price_display(event, ask.price.amount, &config);
 
     Ok(response.add_event(event))
 }
 
-/// Removes the ask on a particular NFT
-pub fn execute_remove_ask(
-    deps: DepsMut,
+/// The ask a seller lists with an AskInput
+fn ask_from_input(api: &dyn Api, seller: &Addr, input: AskInput) -> StdResult<Ask> {
+    Ok(Ask {
+        token_id: input.token_id,
+        seller: seller.clone(),
+        price: input.price,
+        funds_recipient: maybe_addr(api, input.funds_recipient)?,
+        external_id: input.external_id,
+        min_offer: input.min_offer,
+        pre_authorized: input.pre_authorized
+            .map(|(bidder, price)| -> StdResult<_> { Ok((api.addr_validate(&bidder)?, price)) })
+            .transpose()?,
+        min_net_proceeds: input.min_net_proceeds,
+        hidden_min: input.hidden_min,
+        on_remove_msg: input.on_remove_msg,
+        reserve_for: maybe_addr(api, input.reserve_for)?,
+        non_custodial: input.non_custodial,
+    })
+}
+
+/// A seller may set several Asks at once, all or none
+pub fn execute_set_asks(
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    token_id: TokenId,
+    asks: Vec<Ask>,
 ) -> Result<Response, ContractError> {
-    nonpayable(&info)?;
+    if asks.is_empty() || asks.len() > MAX_BATCH_SIZE {
+        return Err(ContractError::InvalidBatchSize(MAX_BATCH_SIZE));
+    }
 
-    let ask = asks().load(deps.storage, token_id.clone())?;
-    only_seller(&info, &ask.seller)?;
+    let mut response = Response::new();
+    for ask in asks {
+        let res = execute_set_ask(deps.branch(), env.clone(), info.clone(), ask)?;
+        response.messages.extend(res.messages);
+        response.events.extend(res.events);
+    }
 
-    asks().remove(deps.storage, token_id.clone())?;
+    Ok(response)
+}
+
+/// A seller may create an Ask on their NFT, unlike SetAsk this never updates an existing Ask
+pub fn execute_create_ask(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    ask: Ask,
+) -> Result<Response, ContractError> {
+    if asks().may_load(deps.storage, ask.token_id.clone())?.is_some() {
+        return Err(ContractError::AskAlreadyExists(ask.token_id));
+    }
+    execute_set_ask(deps, env, info, ask)
+}
+
+/// The owner of an unlisted NFT with bids can counter them by listing it at an ask price
+pub fn execute_counter_with_ask(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: TokenId,
+    price: Coin,
+) -> Result<Response, ContractError> {
+    if highest_bid(deps.as_ref(), &token_id)?.is_none() {
+        return Err(ContractError::NoBids(token_id));
+    }
+
+    let ask = Ask {
+        token_id,
+        seller: info.sender.clone(),
+        price,
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
+    };
+    let event = Event::new("counter-with-ask")
+        .add_attribute("token_id", ask.token_id.to_string())
+        .add_attribute("price", ask.price.to_string());
+    let response = execute_create_ask(deps, env, info, ask)?;
+
+    Ok(response.add_event(event))
+}
+
+/// The seller may change the price and reserved buyer of their ask in place. Unlike SetAsk the
+/// update does not match standing bids, which the seller can still accept.
+pub fn execute_update_ask(
+    deps: DepsMut,
+    info: MessageInfo,
+    token_id: TokenId,
+    price: Coin,
+    reserve_for: Option<Addr>,
+    clear_reserve_for: bool,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    if clear_reserve_for && reserve_for.is_some() {
+        return Err(ContractError::Std(StdError::generic_err("reserve_for cannot be set and cleared at once")));
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let mut ask = asks().load(deps.storage, token_id.clone())?;
+    only_seller(&info, &ask.seller)?;
+
+    price_validate(&price, &config)?;
+    if ask.min_offer.map_or(false, |min_offer| min_offer > price.amount) {
+        return Err(ContractError::InvalidPrice {});
+    }
+    if ask.pre_authorized.as_ref().map_or(false, |(_, agreed_price)| agreed_price > &price.amount) {
+        return Err(ContractError::InvalidPrice {});
+    }
+    if ask.hidden_min.map_or(false, |hidden_min| hidden_min > price.amount) {
+        return Err(ContractError::InvalidPrice {});
+    }
+
+    let old_price = ask.price.clone();
+    ask.price = price;
+    if clear_reserve_for {
+        ask.reserve_for = None;
+    } else if reserve_for.is_some() {
+        ask.reserve_for = reserve_for;
+    }
+    asks().save(deps.storage, token_id.clone(), &ask)?;
+
+    let event = Event::new("update-ask")
+        .add_attribute("collection", config.cw721_address.to_string())
+        .add_attribute("token_id", token_id)
+        .add_attribute("seller", ask.seller.to_string())
+        .add_attribute("old_price", old_price.to_string())
+        .add_attribute("price", ask.price.to_string())
+        .add_attribute("reserve_for", ask.reserve_for.map_or(String::new(), |a| a.to_string()));
+    let event = with_price_display(event, ask.price.amount, &config);
+
+    Ok(Response::new().add_event(event))
+}
+
+/// Removes the ask on a particular NFT
+pub fn execute_remove_ask(
+    deps: DepsMut,
+    info: MessageInfo,
+    token_id: TokenId,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let ask = asks().load(deps.storage, token_id.clone())?;
+    only_seller(&info, &ask.seller)?;
+
+    asks().remove(deps.storage, token_id.clone())?;
+
+    let config = CONFIG.load(deps.storage)?;
+
+    // Remove standing bids on the delisted token
+    let mut refunded_bids = vec![];
+    if config.refund_bids_on_ask_removal {
+        refunded_bids = bids()
+            .idx
+            .token_price
+            .sub_prefix(token_id.clone())
+            .range(deps.storage, None, None, Order::Descending)
+            .take(MAX_REFUNDS_ON_ASK_REMOVAL)
+            .map(|item| item.map(|(_, b)| b))
+            .collect::<StdResult<Vec<_>>>()?;
+        for bid in &refunded_bids {
+            bids().remove(deps.storage, bid_key(&bid.bidder, bid.token_id.clone()))?;
+        }
+    }
+
+    let mut response = Response::new();
+
+    // A non-custodial ask leaves the NFT with the seller
+    match ask.on_remove_msg {
+        Some(on_remove_msg) => dispatch_on_remove_msg(&ask.token_id, on_remove_msg, &config.cw721_address, &mut response)?,
+        None if ask.non_custodial => {},
+        None => return_nft(&ask.token_id, &ask.seller, &config.cw721_address, &mut response)?,
+    }
+    for bid in refunded_bids {
+        transfer_token(bid.price.clone(), bid.get_recipient().to_string(), "refund-bidder", &config, &mut response)?;
+    }
+
+    let event = Event::new("remove-ask")
+        .add_attribute("collection", config.cw721_address.to_string())
+        .add_attribute("token_id", token_id.to_string());
+
+    Ok(response.add_event(event))
+}
+
+/// A seller may remove several Asks at once, all or none
+pub fn execute_remove_asks(
+    mut deps: DepsMut,
+    info: MessageInfo,
+    token_ids: Vec<TokenId>,
+) -> Result<Response, ContractError> {
+    if token_ids.is_empty() || token_ids.len() > MAX_BATCH_SIZE {
+        return Err(ContractError::InvalidBatchSize(MAX_BATCH_SIZE));
+    }
+
+    let mut response = Response::new();
+    for token_id in token_ids {
+        let res = execute_remove_ask(deps.branch(), info.clone(), token_id)?;
+        response.messages.extend(res.messages);
+        response.events.extend(res.events);
+    }
+
+    Ok(response)
+}
+
+/// Anyone may remove a non-custodial ask that can no longer be settled
+pub fn execute_remove_stale_ask(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: TokenId,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let mut response = Response::new();
+    if !remove_stale_ask(deps, &env, &token_id, &config, &mut response)? {
+        return Err(ContractError::Unauthorized(String::from("only stale asks can be removed by anyone")));
+    }
+
+    Ok(response)
+}
+
+/// Removes the ask on a token if it is non-custodial and can no longer be settled, returning
+/// whether it was removed
+fn remove_stale_ask(
+    deps: DepsMut,
+    env: &Env,
+    token_id: &TokenId,
+    config: &Config,
+    response: &mut Response,
+) -> Result<bool, ContractError> {
+    let ask = match asks().may_load(deps.storage, token_id.clone())? {
+        Some(ask) if ask.non_custodial => ask,
+        _ => return Ok(false),
+    };
+    if is_deliverable(deps.as_ref(), env, &ask, config)? {
+        return Ok(false);
+    }
+    asks().remove(deps.storage, token_id.clone())?;
+
+    let event = Event::new("remove-stale-ask")
+        .add_attribute("collection", config.cw721_address.to_string())
+        .add_attribute("token_id", token_id.to_string())
+        .add_attribute("seller", ask.seller.to_string());
+    response.events.push(event);
+
+    Ok(true)
+}
+
+/// Lists several unlisted NFTs of the seller to be sold together, escrowing all of them
+pub fn execute_set_bundle_ask(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_ids: Vec<TokenId>,
+    price: Coin,
+    funds_recipient: Option<Addr>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    price_validate(&price, &config)?;
+    if token_ids.len() < 2 || token_ids.len() > MAX_BUNDLE_SIZE {
+        return Err(ContractError::InvalidBundle(format!("expected 2 to {} tokens", MAX_BUNDLE_SIZE)));
+    }
+
+    let mut response = Response::new();
+    for (idx, token_id) in token_ids.iter().enumerate() {
+        if token_ids[..idx].contains(token_id) {
+            return Err(ContractError::InvalidBundle(format!("duplicate token_id {}", token_id)));
+        }
+        // Tokens already listed are escrowed under their ask, so they cannot be bundled
+        if only_lister(deps.as_ref(), &env, &info, token_id, &config)?.is_some() {
+            return Err(ContractError::AskAlreadyExists(token_id.clone()));
+        }
+        transfer_nft(token_id, &env.contract.address, &config.cw721_address, &mut response)?;
+    }
+
+    let id = BUNDLE_ASK_SEQ.may_load(deps.storage)?.unwrap_or_default() + 1;
+    BUNDLE_ASK_SEQ.save(deps.storage, &id)?;
+    let bundle_ask = BundleAsk {
+        id,
+        token_ids,
+        seller: info.sender,
+        price,
+        funds_recipient,
+    };
+    BUNDLE_ASKS.save(deps.storage, id, &bundle_ask)?;
+
+    let event = Event::new("set-bundle-ask")
+        .add_attribute("collection", config.cw721_address.to_string())
+        .add_attribute("bundle_id", id.to_string())
+        .add_attribute("token_ids", bundle_ask.token_ids.join(","))
+        .add_attribute("seller", bundle_ask.seller.to_string())
+        .add_attribute("price", bundle_ask.price.to_string());
+    let event = with_price_display(event, bundle_ask.price.amount, &config);
+
+    Ok(response.add_event(event))
+}
+
+/// Removes a bundle ask, returning its NFTs to the seller
+pub fn execute_remove_bundle_ask(
+    deps: DepsMut,
+    info: MessageInfo,
+    bundle_id: u64,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let bundle_ask = BUNDLE_ASKS.load(deps.storage, bundle_id)?;
+    only_seller(&info, &bundle_ask.seller)?;
+    BUNDLE_ASKS.remove(deps.storage, bundle_id);
+
+    let config = CONFIG.load(deps.storage)?;
+    let mut response = Response::new();
+    for token_id in &bundle_ask.token_ids {
+        return_nft(token_id, &bundle_ask.seller, &config.cw721_address, &mut response)?;
+    }
+
+    let event = Event::new("remove-bundle-ask")
+        .add_attribute("collection", config.cw721_address.to_string())
+        .add_attribute("bundle_id", bundle_id.to_string())
+        .add_attribute("token_ids", bundle_ask.token_ids.join(","));
+
+    Ok(response.add_event(event))
+}
+
+/// Buys every NFT of a bundle ask. The price is split evenly across the tokens, with any
+/// remainder on the last, so each token settles as its own sale.
+pub fn execute_buy_bundle(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    bundle_id: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let bundle_ask = BUNDLE_ASKS.load(deps.storage, bundle_id)?;
+    let buyer = info.sender.clone();
+
+    let received_amount = must_pay(&info, &bundle_ask.price.denom)?;
+    if received_amount != bundle_ask.price.amount {
+        return Err(ContractError::IncorrectBidPayment(bundle_ask.price.amount, received_amount));
+    }
+    BUNDLE_ASKS.remove(deps.storage, bundle_id);
+
+    let mut response = Response::new();
+    let num_tokens = bundle_ask.token_ids.len() as u128;
+    let token_price = bundle_ask.price.amount.multiply_ratio(1u128, num_tokens);
+    let last_token_price = bundle_ask.price.amount - token_price * Uint128::from(num_tokens - 1);
+    for (idx, token_id) in bundle_ask.token_ids.iter().enumerate() {
+        let amount = if idx as u128 == num_tokens - 1 { last_token_price } else { token_price };
+        let payment = coin(amount.u128(), &bundle_ask.price.denom);
+        let sale_seq = record_sale(deps.branch(), &env, token_id, &buyer, &bundle_ask.seller, &payment, &config, None)?;
+        finalize_sale(
+            deps.as_ref(),
+            &buyer,
+            token_id,
+            sale_seq,
+            &payment,
+            &bundle_ask.get_recipient(),
+            Uint128::zero(),
+            Uint128::zero(),
+            &buyer,
+            None,
+            &config,
+            &mut response,
+        )?;
+    }
+
+    let event = Event::new("buy-bundle")
+        .add_attribute("collection", config.cw721_address.to_string())
+        .add_attribute("bundle_id", bundle_id.to_string())
+        .add_attribute("token_ids", bundle_ask.token_ids.join(","))
+        .add_attribute("buyer", buyer)
+        .add_attribute("seller", bundle_ask.seller.to_string())
+        .add_attribute("price", bundle_ask.price.to_string());
+    let event = with_price_display(event, bundle_ask.price.amount, &config);
+
+    Ok(response.add_event(event))
+}
+
+/// Places a bid on a listed or unlisted NFT. The bid is escrowed in the contract.
+pub fn execute_set_bid(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    bid: Bid,
+    referrer: Option<Addr>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    price_validate(&bid.price, &config)?;
+    let received_amount = must_pay(&info, &bid.price.denom)?;
+    if bid.price.amount != received_amount  {
+        return Err(ContractError::IncorrectBidPayment(bid.price.amount, received_amount));
+    }
+
+    let mut response = Response::new();
+    let bid_key = bid_key(&bid.bidder, bid.token_id.clone());
+    let ask_key = &bid.token_id;
+    remove_stale_ask(deps.branch(), &env, ask_key, &config, &mut response)?;
+
+    // If bid exists, remove it so the escrowed tokens can be refunded
+    let existing_bid = bids().may_load(deps.storage, bid_key.clone())?;
+    if existing_bid.is_some() {
+        bids().remove(deps.storage, bid_key.clone())?;
+    }
+
+    let matching_ask = match_bid(deps.as_ref(), &env, &bid, &mut response)?;
+    let sale_seq = match &matching_ask {
+        // If matching ask found:
+        // * remove ask
+        // * record sale
+        Some(ask) => {
+            asks().remove(deps.storage, ask_key.clone())?;
+            Some(record_sale(
+                deps.branch(),
+                &env,
+                &ask.token_id,
+                &bid.bidder,
+                &ask.seller,
+                &coin(ask.sale_price_for(&bid.bidder).u128(), &ask.price.denom),
+                &config,
+                referrer.as_ref(),
+            )?)
+        },
+        // If matching ask not found:
+        // * reject lowball bids on listed NFTs
+        // * save bid
+        None => {
+            if let Some(ask) = asks().may_load(deps.storage, ask_key.clone())? {
+                // Bids on a listed token must be in the ask's denom
+                if bid.price.denom != ask.price.denom {
+                    return Err(ContractError::InvalidPrice {});
+                }
+                // Only the reserved buyer can place a bid that settles a reserved ask
+                if ask.reserve_for.is_some() && bid.price.amount >= ask.price.amount {
+                    return Err(ContractError::ReservedAsk(ask.token_id));
+                }
+                if let Some(min_bid_fraction_of_ask) = config.min_bid_fraction_of_ask {
+                    let min_bid_amount = ask.price.amount * min_bid_fraction_of_ask;
+                    if bid.price.amount < min_bid_amount {
+                        return Err(ContractError::BidTooLow(min_bid_amount, bid.price.amount));
+                    }
+                }
+                if let Some(min_offer) = ask.min_offer {
+                    if bid.price.amount < min_offer {
+                        return Err(ContractError::BidTooLow(min_offer, bid.price.amount));
+                    }
+                }
+            }
+            bids().save(deps.storage, bid_key, &bid)?;
+            prepare_hooks(deps.as_ref(), &HookMsg::BidCreatedHook { bid: bid.clone() }, &mut response)?;
+            None
+        }
+    };
+
+    // Refund the escrowed tokens of the replaced bid
+    if let Some(existing_bid) = existing_bid {
+        transfer_token(
+            existing_bid.price.clone(),
+            existing_bid.get_recipient().to_string(),
+            "refund-bidder",
+            &config,
+            &mut response,
+        )?;
+    }
+
+    let mut event = Event::new("set-bid")
+        .add_attribute("token_id", bid.token_id.to_string())
+        .add_attribute("bidder", bid.bidder.clone())
+        .add_attribute("price", bid.price.to_string())
+        .add_attribute("matched", matching_ask.is_some().to_string());
+    event = with_price_display(event, bid.price.amount, &config);
+
+    // If matching ask found:
+    // * calculate surplus
+    // * finalize sale
+    if let (Some(ask), Some(sale_seq)) = (matching_ask, sale_seq) {
+        let sale_amount = ask.sale_price_for(&bid.bidder);
+        let surplus_amount = received_amount - sale_amount;
+        finalize_sale(
+            deps.as_ref(),
+            &bid.bidder,
+            &ask.token_id,
+            sale_seq,
+            &coin(sale_amount.u128(), &ask.price.denom),
+            &ask.get_recipient(),
+            Uint128::zero(),
+            surplus_amount,
+            &bid.bidder,
+            referrer.as_ref(),
+            &config,
+            &mut response,
+        )?;
+        event = event
+            .add_attribute("seller", ask.seller.to_string())
+            .add_attribute("sale_price", coin(sale_amount.u128(), &ask.price.denom).to_string());
+        if ask.reserve_for.is_some() {
+            event = event.add_attribute("private_sale", "true");
+        }
+    }
+
+    response.events.push(event);
+
+    Ok(response)
+}
+
+/// Buys a listed NFT outright. The sale fails instead of falling back to a bid if the ask
+/// no longer sells at `expected_price`.
+pub fn execute_buy_now(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: TokenId,
+    expected_price: Coin,
+    referrer: Option<Addr>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let ask = asks().load(deps.storage, token_id.clone())?;
+    let buyer = info.sender.clone();
+    if ask.reserve_for.as_ref().map_or(false, |reserved| reserved != &buyer) {
+        return Err(ContractError::ReservedAsk(token_id));
+    }
+    only_deliverable(deps.as_ref(), &env, &ask, &config)?;
+
+    // Guard against the ask changing before the purchase lands
+    let sale_price = coin(ask.sale_price_for(&buyer).u128(), &ask.price.denom);
+    if expected_price != sale_price {
+        return Err(ContractError::PriceMismatch(expected_price, sale_price));
+    }
+    let received_amount = must_pay(&info, &sale_price.denom)?;
+    if received_amount != sale_price.amount {
+        return Err(ContractError::IncorrectBidPayment(sale_price.amount, received_amount));
+    }
+    if let Some(min_net_proceeds) = ask.min_net_proceeds {
+        let net_proceeds = seller_net_proceeds(deps.as_ref(), sale_price.amount, &config)?;
+        if net_proceeds < min_net_proceeds {
+            return Err(ContractError::BidTooLow(min_net_proceeds, net_proceeds));
+        }
+    }
+
+    let mut response = Response::new();
+
+    // The buyer's standing bid on the token is no longer needed
+    let bid_key = bid_key(&buyer, token_id.clone());
+    if let Some(existing_bid) = bids().may_load(deps.storage, bid_key.clone())? {
+        bids().remove(deps.storage, bid_key)?;
+        transfer_token(
+            existing_bid.price.clone(),
+            existing_bid.get_recipient().to_string(),
+            "refund-bidder",
+            &config,
+            &mut response,
+        )?;
+    }
+
+    asks().remove(deps.storage, token_id.clone())?;
+    let sale_seq = record_sale(deps.branch(), &env, &token_id, &buyer, &ask.seller, &sale_price, &config, referrer.as_ref())?;
+    finalize_sale(
+        deps.as_ref(),
+        &buyer,
+        &token_id,
+        sale_seq,
+        &sale_price,
+        &ask.get_recipient(),
+        Uint128::zero(),
+        Uint128::zero(),
+        &buyer,
+        referrer.as_ref(),
+        &config,
+        &mut response,
+    )?;
+
+    let mut event = Event::new("buy-now")
+        .add_attribute("token_id", token_id)
+        .add_attribute("buyer", buyer)
+        .add_attribute("seller", ask.seller.to_string())
+        .add_attribute("price", sale_price.to_string());
+    if ask.reserve_for.is_some() {
+        event = event.add_attribute("private_sale", "true");
+    }
+    response.events.push(with_price_display(event, sale_price.amount, &config));
+
+    Ok(response)
+}
+
+/// Buys the cheapest listings in the config denom, cheapest first, until `limit` are bought or
+/// the next one no longer fits the budget. Reserved, undeliverable and own listings are skipped.
+pub fn execute_sweep(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    limit: u32,
+    max_total_price: Uint128,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let buyer = info.sender.clone();
+    if limit == 0 || limit > MAX_SWEEP_LIMIT {
+        return Err(ContractError::InvalidBatchSize(MAX_SWEEP_LIMIT as usize));
+    }
+    let received_amount = must_pay(&info, &config.denom)?;
+    if received_amount != max_total_price {
+        return Err(ContractError::IncorrectBidPayment(max_total_price, received_amount));
+    }
+
+    // Pick the listings to buy before changing any state
+    let mut purchases: Vec<Ask> = vec![];
+    let mut remaining = max_total_price;
+    for item in asks().idx.price.range(deps.storage, None, None, Order::Ascending) {
+        let (_, ask) = item?;
+        // Asks are ordered by price, so no later ask fits the budget
+        if ask.price.amount > remaining || purchases.len() == limit as usize {
+            break;
+        }
+        if ask.price.denom != config.denom || ask.seller == buyer || ask.reserve_for.is_some() {
+            continue;
+        }
+        if let Some(min_net_proceeds) = ask.min_net_proceeds {
+            if seller_net_proceeds(deps.as_ref(), ask.price.amount, &config)? < min_net_proceeds {
+                continue;
+            }
+        }
+        if only_deliverable(deps.as_ref(), &env, &ask, &config).is_err() {
+            continue;
+        }
+        remaining -= ask.price.amount;
+        purchases.push(ask);
+    }
+    if purchases.is_empty() {
+        return Err(ContractError::EmptySweep {});
+    }
+
+    let mut response = Response::new();
+    for ask in purchases {
+        // The buyer's standing bid on the token is no longer needed
+        let bid_key = bid_key(&buyer, ask.token_id.clone());
+        if let Some(existing_bid) = bids().may_load(deps.storage, bid_key.clone())? {
+            bids().remove(deps.storage, bid_key)?;
+            transfer_token(
+                existing_bid.price.clone(),
+                existing_bid.get_recipient().to_string(),
+                "refund-bidder",
+                &config,
+                &mut response,
+            )?;
+        }
+
+        asks().remove(deps.storage, ask.token_id.clone())?;
+        let sale_seq = record_sale(deps.branch(), &env, &ask.token_id, &buyer, &ask.seller, &ask.price, &config, None)?;
+        finalize_sale(
+            deps.as_ref(),
+            &buyer,
+            &ask.token_id,
+            sale_seq,
+            &ask.price,
+            &ask.get_recipient(),
+            Uint128::zero(),
+            Uint128::zero(),
+            &buyer,
+            None,
+            &config,
+            &mut response,
+        )?;
+
+        let event = Event::new("sweep-purchase")
+            .add_attribute("token_id", ask.token_id.to_string())
+            .add_attribute("buyer", buyer.to_string())
+            .add_attribute("seller", ask.seller.to_string())
+            .add_attribute("price", ask.price.to_string());
+        response.events.push(with_price_display(event, ask.price.amount, &config));
+    }
+
+    if !remaining.is_zero() {
+        transfer_token(
+            coin(remaining.u128(), &config.denom),
+            buyer.to_string(),
+            "refund-sweep",
+            &config,
+            &mut response,
+        )?;
+    }
+
+    Ok(response)
+}
+
+/// Removes the bidder's bids on several tokens, all or none
+pub fn execute_remove_bids(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_ids: Vec<TokenId>,
+) -> Result<Response, ContractError> {
+    if token_ids.is_empty() || token_ids.len() > MAX_BATCH_SIZE {
+        return Err(ContractError::InvalidBatchSize(MAX_BATCH_SIZE));
+    }
+
+    let mut response = Response::new();
+    for token_id in token_ids {
+        let res = execute_remove_bid(deps.branch(), env.clone(), info.clone(), token_id)?;
+        response.messages.extend(res.messages);
+        response.events.extend(res.events);
+    }
+
+    Ok(response)
+}
+
+/// Removes a bid made by the bidder. Bidders can only remove their own bids
+pub fn execute_remove_bid(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    token_id: TokenId,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    let config = CONFIG.load(deps.storage)?;
+    let bidder = info.sender;
+
+    let key = bid_key(&bidder, token_id.clone());
+    let bid = bids().load(deps.storage, key.clone())?;
+    bids().remove(deps.storage, key)?;
+
+    let mut response = Response::new();
+    transfer_token(bid.price.clone(), bid.get_recipient().to_string(), "refund-bidder", &config, &mut response)?;
+
+    let event = Event::new("remove-bid")
+        .add_attribute("token_id", token_id.clone())
+        .add_attribute("bidder", bidder);
+    response.events.push(event);
+
+    Ok(response)
+}
+
+/// Seller can accept a bid which transfers funds as well as the token. The bid may or may not be associated with an ask.
+/// The seller's existing fee credit is redeemed against the market fee, and `credit_bps` of the proceeds
+/// can be kept as new fee credit.
+pub fn execute_accept_bid(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: TokenId,
+    bidder: Addr,
+    credit_bps: Option<u64>,
+    min_price: Option<Uint128>,
+    referrer: Option<Addr>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let credit_bps = credit_bps.unwrap_or_default();
+    if credit_bps > MAX_CREDIT_BPS {
+        return Err(ContractError::InvalidCreditShare(credit_bps));
+    }
+
+    let bid_key = bid_key(&bidder, token_id.clone());
+    let bid = bids().load(deps.storage, bid_key.clone())?;
+    // Guard against the bid being lowered before the accept lands
+    if let Some(min_price) = min_price {
+        if bid.price.amount < min_price {
+            return Err(ContractError::PriceMismatch(coin(min_price.u128(), &bid.price.denom), bid.price));
+        }
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    // Fee credit is held in the config denom
+    let in_config_denom = bid.price.denom == config.denom;
+    if credit_bps > 0 && !in_config_denom {
+        return Err(ContractError::InvalidCreditShare(credit_bps));
+    }
+    let existing_ask = asks().may_load(deps.storage, token_id.clone())?;
+
+    only_owner_or_seller(
+        deps.as_ref(),
+        &info,
+        &config.cw721_address,
+        &token_id,
+        &existing_ask.clone().map_or(None, |a| Some(a.seller)),
+    )?;
+
+    // Reject bids below the seller's hidden minimum
+    if let Some(hidden_min) = existing_ask.as_ref().and_then(|a| a.hidden_min) {
+        if bid.price.amount < hidden_min {
+            return Err(ContractError::BidTooLow(hidden_min, bid.price.amount));
+        }
+    }
+
+    // Remove ask if it exists, define seller and recipient
+    let seller = existing_ask.as_ref().map_or(info.sender.clone(), |a| a.seller.clone());
+    let payment_recipient = match existing_ask {
+        Some(ask) => {
+            only_deliverable(deps.as_ref(), &env, &ask, &config)?;
+            asks().remove(deps.storage, ask.token_id.clone())?;
+            ask.get_recipient()
+        },
+        None => info.sender,
+    };
+
+    // Remove accepted bid
+    bids().remove(deps.storage, bid_key)?;
+    let sale_seq = record_sale(deps.branch(), &env, &token_id, &bid.bidder, &seller, &bid.price, &config, referrer.as_ref())?;
+
+    let mut response = Response::new();
+
+    // Redeem existing credit before keeping part of these proceeds as new credit
+    let redeemed = match in_config_denom {
+        true => redeem_credit(
+            deps.branch(),
+            &seller,
+            market_fee(bid.price.amount, &config) - referral_fee(bid.price.amount, referrer.as_ref(), &config),
+        )?,
+        false => Uint128::zero(),
+    };
+    if !redeemed.is_zero() {
+        transfer_token(
+            coin(redeemed.u128(), &config.denom),
+            payment_recipient.to_string(),
+            "redeem-credit",
+            &config,
+            &mut response,
+        )?;
+    }
+    let credit_amount = seller_net_proceeds(deps.as_ref(), bid.price.amount, &config)?
+        .multiply_ratio(credit_bps, MAX_CREDIT_BPS);
+    if !credit_amount.is_zero() {
+        CREDITS.update(deps.storage, &seller, |credit| -> StdResult<_> {
+            Ok(credit.unwrap_or_default() + credit_amount)
+        })?;
+    }
+
+    // Transfer funds and NFT
+    finalize_sale(
+        deps.as_ref(),
+        &bid.bidder,
+        &token_id,
+        sale_seq,
+        &bid.price,
+        &payment_recipient,
+        credit_amount,
+        Uint128::zero(),
+        &bid.bidder,
+        referrer.as_ref(),
+        &config,
+        &mut response,
+    )?;
+
+    let event = Event::new("accept-bid")
+        .add_attribute("token_id", token_id.to_string())
+        .add_attribute("bidder", bidder)
+        .add_attribute("price", bid.price.to_string())
+        .add_attribute("credit", coin(credit_amount.u128(), &config.denom).to_string());
+    response.events.push(event);
+
+    Ok(response)
+}
+
+/// A seller may withdraw the fee credit they have not redeemed against market fees
+pub fn execute_withdraw_credit(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let credit = CREDITS.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+    if credit.is_zero() {
+        return Err(ContractError::NoCredit {});
+    }
+    CREDITS.remove(deps.storage, &info.sender);
+
+    let config = CONFIG.load(deps.storage)?;
+    let mut response = Response::new();
+    transfer_token(
+        coin(credit.u128(), &config.denom),
+        info.sender.to_string(),
+        "withdraw-credit",
+        &config,
+        &mut response,
+    )?;
+
+    Ok(response)
+}
+
+/// Seller can accept bids on several tokens in one call. Each bid goes through AcceptBid, and any failure rejects the batch.
+pub fn execute_accept_bids(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    items: Vec<(TokenId, Addr)>,
+) -> Result<Response, ContractError> {
+    if items.is_empty() || items.len() > MAX_ACCEPT_BIDS {
+        return Err(ContractError::InvalidBatchSize(MAX_ACCEPT_BIDS));
+    }
+
+    let mut response = Response::new();
+    for (token_id, bidder) in items {
+        let res = execute_accept_bid(deps.branch(), env.clone(), info.clone(), token_id, bidder, None, None, None)?;
+        response.messages.extend(res.messages);
+        response.events.extend(res.events);
+    }
+
+    Ok(response)
+}
+
+/// Seller can accept the highest bid on a token, as long as the accept lands at or before `not_after`
+pub fn execute_accept_best_bid_before(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: TokenId,
+    not_after: Timestamp,
+) -> Result<Response, ContractError> {
+    if env.block.time > not_after {
+        return Err(ContractError::DeadlinePassed(not_after));
+    }
+
+    let best_bid = highest_bid(deps.as_ref(), &token_id)?
+        .ok_or_else(|| ContractError::NoBids(token_id.clone()))?;
+
+    execute_accept_bid(deps, env, info, token_id, best_bid.bidder, None, None, None)
+}
+
+/// Place a collection bid (limit order) across an entire collection
+pub fn execute_set_collection_bid(
+    deps: DepsMut,
+    info: MessageInfo,
+    collection_bid: CollectionBid
+) -> Result<Response, ContractError> {
+    if collection_bid.units == 0 {
+        return Err(ContractError::InvalidCollectionBid {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    if let Some(max_collection_bid_units) = config.max_collection_bid_units {
+        if collection_bid.units > max_collection_bid_units {
+            return Err(ContractError::TooManyUnits(max_collection_bid_units));
+        }
+    }
+    
+    // A budget cannot exceed what the units can spend at the max price
+    if let Some(budget) = collection_bid.budget {
+        if budget.is_zero() || budget > collection_bid.price.amount * Uint128::from(collection_bid.units) {
+            return Err(ContractError::InvalidCollectionBid {});
+        }
+    }
+
+    // Escrows the amount (price * units, or the budget)
+    price_validate(&collection_bid.price, &config)?;
+    let received_amount = must_pay(&info, &collection_bid.price.denom)?;
+    if Uint128::from(collection_bid.total_cost()) != received_amount  {
+        return Err(ContractError::IncorrectBidPayment(
+            Uint128::from(collection_bid.total_cost()),
+            received_amount,
+        ));
+    }
+    let collection_bid_key = collection_bid.bidder.clone();
+    let mut response = Response::new();
+
+    // If collection bid exists, replace it and refund the escrowed tokens
+    let existing_bid = collection_bids().may_load(deps.storage, collection_bid_key.clone())?;
+    if existing_bid.is_some() {
+        collection_bids().remove(deps.storage, collection_bid_key.clone())?;
+    }
+    collection_bids().save(deps.storage, collection_bid_key, &collection_bid)?;
+
+    if let Some(existing_bid) = existing_bid {
+        transfer_token(
+            coin(existing_bid.total_cost(), existing_bid.price.denom),
+            existing_bid.bidder.to_string(),
+            "refund-collection-bidder",
+            &config,
+            &mut response,
+        )?;
+    }
+
+    let mut event = Event::new("set-collection-bid")
+        .add_attribute("bidder", collection_bid.bidder)
+        .add_attribute("price", collection_bid.price.to_string())
+        .add_attribute("units", collection_bid.units.to_string());
+    if let Some(budget) = collection_bid.budget {
+        event = event.add_attribute("budget", budget.to_string());
+    }
+    response.events.push(event);
+
+    Ok(response)
+}
+
+/// Remove an existing collection bid (limit order)
+pub fn execute_remove_collection_bid(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    let config = CONFIG.load(deps.storage)?;
+    let mut response = Response::new();
+    
+    let collection_bid_key = info.sender.clone();
+
+    let collection_bid = collection_bids().load(deps.storage, collection_bid_key.clone())?;
+
+    collection_bids().remove(deps.storage, collection_bid_key)?;
+    transfer_token(
+        coin(collection_bid.total_cost(), collection_bid.price.denom),
+        collection_bid.bidder.to_string(),
+        "refund-collection-bidder",
+        &config,
+        &mut response,
+    )?;
+
+    let event = Event::new("remove-collection-bid")
+        .add_attribute("bidder", collection_bid.bidder);
+    response.events.push(event);
+
+    Ok(response)
+}
+
+/// Collection bidder can lower the units of their collection bid, the escrow for the removed units is refunded.
+/// Units are raised with IncreaseCollectionBidUnits.
+pub fn execute_reduce_collection_bid(
+    deps: DepsMut,
+    info: MessageInfo,
+    new_units: u32,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    let config = CONFIG.load(deps.storage)?;
+    let mut response = Response::new();
+
+    let mut collection_bid = collection_bids().load(deps.storage, info.sender.clone())?;
+    if collection_bid.budget.is_some() || new_units < 1 || new_units >= collection_bid.units {
+        return Err(ContractError::InvalidCollectionBid {});
+    }
+
+    let removed_units = collection_bid.units - new_units;
+    collection_bid.units = new_units;
+    collection_bids().save(deps.storage, info.sender.clone(), &collection_bid)?;
+
+    transfer_token(
+        coin(
+            collection_bid.price.amount.u128() * u128::from(removed_units),
+            collection_bid.price.denom.clone(),
+        ),
+        collection_bid.bidder.to_string(),
+        "refund-collection-bidder",
+        &config,
+        &mut response,
+    )?;
+
+    let event = Event::new("reduce-collection-bid")
+        .add_attribute("bidder", collection_bid.bidder)
+        .add_attribute("units", new_units.to_string());
+    response.events.push(event);
+
+    Ok(response)
+}
+
+/// Collection bidder can raise the units of their collection bid by escrowing the price of the added units
+pub fn execute_increase_collection_bid_units(
+    deps: DepsMut,
+    info: MessageInfo,
+    additional_units: u32,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    let mut collection_bid = collection_bids().load(deps.storage, info.sender.clone())?;
+    if collection_bid.budget.is_some() || additional_units == 0 {
+        return Err(ContractError::InvalidCollectionBid {});
+    }
+
+    // Escrows the top-up (price * additional_units)
+    let received_amount = must_pay(&info, &collection_bid.price.denom)?;
+    let top_up_amount = collection_bid.price.amount * Uint128::from(additional_units);
+    if top_up_amount != received_amount {
+        return Err(ContractError::IncorrectBidPayment(top_up_amount, received_amount));
+    }
+
+    collection_bid.units = collection_bid.units
+        .checked_add(additional_units)
+        .ok_or(ContractError::InvalidCollectionBid {})?;
+    if let Some(max_collection_bid_units) = config.max_collection_bid_units {
+        if collection_bid.units > max_collection_bid_units {
+            return Err(ContractError::TooManyUnits(max_collection_bid_units));
+        }
+    }
+    collection_bids().save(deps.storage, info.sender.clone(), &collection_bid)?;
+
+    let event = Event::new("increase-collection-bid-units")
+        .add_attribute("bidder", collection_bid.bidder)
+        .add_attribute("units", collection_bid.units.to_string());
+
+    Ok(Response::new().add_event(event))
+}
+
+/// Owner/seller of items in a collection can accept a collection bid which transfers funds as well as the tokens.
+/// An all-or-nothing collection bid must be filled for all of its remaining units in a single call.
+/// A budget collection bid pays its price per token while the budget lasts, then whatever remains of it.
+pub fn execute_accept_collection_bid(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_ids: Vec<TokenId>,
+    bidder: Addr,
+    min_price: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let collection_bid_key = bidder.clone();
+    let collection_bid = collection_bids().load(deps.storage, collection_bid_key.clone())?;
+
+    // Each token can only be sold once, and at most once per unit
+    let mut unique_token_ids = token_ids.clone();
+    unique_token_ids.sort_unstable();
+    unique_token_ids.dedup();
+    let fill_units = token_ids.len() as u32;
+    if
+        token_ids.is_empty() ||
+        unique_token_ids.len() != token_ids.len() ||
+        fill_units > collection_bid.units
+    {
+        return Err(ContractError::InvalidCollectionBid {});
+    }
+    if collection_bid.all_or_nothing && fill_units != collection_bid.units {
+        return Err(ContractError::PartialFill(collection_bid.units, fill_units));
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let mut response = Response::new();
+
+    // Price each filled unit, drawing down the budget if there is one
+    let mut payments: Vec<Coin> = vec![];
+    let mut remaining_budget = collection_bid.budget;
+    for _ in 0..fill_units {
+        let amount = match remaining_budget {
+            Some(budget) => {
+                let amount = budget.min(collection_bid.price.amount);
+                remaining_budget = Some(budget - amount);
+                amount
+            },
+            None => collection_bid.price.amount,
+        };
+        let payment = coin(amount.u128(), &collection_bid.price.denom);
+        price_validate(&payment, &config)?;
+        // Guard against the bid being lowered before the accept lands
+        if let Some(min_price) = min_price {
+            if amount < min_price {
+                return Err(ContractError::PriceMismatch(coin(min_price.u128(), &payment.denom), payment));
+            }
+        }
+        payments.push(payment);
+    }
+
+    let remaining_units = collection_bid.units - fill_units;
+    if remaining_units == 0 || remaining_budget == Some(Uint128::zero()) {
+        // Remove accepted collection bid when no units or budget remain. The budget never
+        // exceeds price * units, so it is spent by the time the units run out.
+        collection_bids().remove(deps.storage, collection_bid_key)?;
+    } else {
+        // Decrement the number of units and the budget on the collection bid
+        let mut updated_collection_bid = collection_bid.clone();
+        updated_collection_bid.units = remaining_units;
+        updated_collection_bid.budget = remaining_budget;
+        collection_bids().save(deps.storage, collection_bid_key, &updated_collection_bid)?;
+    }
+
+    // Validate each token and record its sale before any messages are added
+    let mut sales: Vec<(TokenId, Addr, u64, Coin)> = vec![];
+    for (token_id, payment) in token_ids.into_iter().zip(payments) {
+        let existing_ask = asks().may_load(deps.storage, token_id.clone())?;
+        only_owner_or_seller(
+            deps.as_ref(),
+            &info,
+            &config.cw721_address,
+            &token_id,
+            &existing_ask.clone().map_or(None, |a| Some(a.seller)),
+        )?;
+
+        // Remove ask if it exists, define seller and recipient
+        let seller = existing_ask.as_ref().map_or(info.sender.clone(), |a| a.seller.clone());
+        let payment_recipient = match existing_ask {
+            Some(ask) => {
+                only_deliverable(deps.as_ref(), &env, &ask, &config)?;
+                asks().remove(deps.storage, ask.token_id.clone())?;
+                ask.get_recipient()
+            },
+            None => info.sender.clone(),
+        };
+
+        if collection_bid.only_below_last_sale {
+            if let Some(last_sale_price) = LAST_SALE_PRICES.may_load(deps.storage, &token_id)? {
+                if last_sale_price.amount >= collection_bid.price.amount {
+                    return Err(ContractError::LastSaleNotBelowBid(token_id));
+                }
+            }
+        }
+        only_matching_traits(deps.as_ref(), &config.cw721_address, &token_id, &collection_bid.traits)?;
+
+        let sale_seq = record_sale(deps.branch(), &env, &token_id, &bidder, &seller, &payment, &config, None)?;
+        sales.push((token_id, payment_recipient, sale_seq, payment));
+    }
+
+    for (idx, (token_id, payment_recipient, sale_seq, payment)) in sales.into_iter().enumerate() {
+        // Transfer funds and NFT
+        finalize_sale(
+            deps.as_ref(),
+            &collection_bid.bidder,
+            &token_id,
+            sale_seq,
+            &payment,
+            &payment_recipient,
+            Uint128::zero(),
+            Uint128::zero(),
+            &collection_bid.bidder,
+            None,
+            &config,
+            &mut response,
+        )?;
+
+        let remaining_units = collection_bid.units - (idx as u32) - 1;
+        let event = Event::new("accept-collection-bid")
+            .add_attribute("token_id", token_id.to_string())
+            .add_attribute("bidder", collection_bid.bidder.to_string())
+            .add_attribute("price", payment.to_string())
+            .add_attribute("units", remaining_units.to_string());
+        response.events.push(event);
+    }
+
+    Ok(response)
+}
+
+/// An operator may refund and remove outstanding bids and collection bids, in batches of `limit`,
+/// so the books can be emptied before a denom change
+pub fn execute_refund_all_bids(
+    deps: DepsMut,
+    info: MessageInfo,
+    limit: u32,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    let config = CONFIG.load(deps.storage)?;
+    only_operator(&info, &config)?;
+
+    let limit = limit.min(MAX_REFUND_ALL_BIDS_LIMIT) as usize;
+    let refunded_bids = bids()
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, b)| b))
+        .collect::<StdResult<Vec<_>>>()?;
+    let refunded_collection_bids = collection_bids()
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(limit - refunded_bids.len())
+        .map(|item| item.map(|(_, b)| b))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for bid in &refunded_bids {
+        bids().remove(deps.storage, bid_key(&bid.bidder, bid.token_id.clone()))?;
+    }
+    for collection_bid in &refunded_collection_bids {
+        collection_bids().remove(deps.storage, collection_bid.bidder.clone())?;
+    }
+
+    let mut response = Response::new();
+    for bid in &refunded_bids {
+        transfer_token(bid.price.clone(), bid.get_recipient().to_string(), "refund-bidder", &config, &mut response)?;
+    }
+    for collection_bid in &refunded_collection_bids {
+        transfer_token(
+            coin(collection_bid.total_cost(), collection_bid.price.denom.clone()),
+            collection_bid.bidder.to_string(),
+            "refund-collection-bidder",
+            &config,
+            &mut response,
+        )?;
+    }
+
+    let event = Event::new("refund-all-bids")
+        .add_attribute("operator", info.sender)
+        .add_attribute("bids", refunded_bids.len().to_string())
+        .add_attribute("collection_bids", refunded_collection_bids.len().to_string());
+    response.events.push(event);
+
+    Ok(response)
+}
+
+/// An operator or fee admin may split the collected marketplace fees between recipients by weight
+pub fn execute_distribute_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipients: Vec<(String, Decimal)>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    let config = CONFIG.load(deps.storage)?;
+    only_role(deps.as_ref(), &info, &config, Role::FeeAdmin)?;
+
+    if recipients.is_empty() {
+        return Err(ContractError::InvalidFeeDistribution(String::from("recipients must be non-empty")));
+    }
+    let total_weight = recipients
+        .iter()
+        .fold(Decimal::zero(), |total, (_, weight)| total + *weight);
+    if total_weight != Decimal::one() {
+        return Err(ContractError::InvalidFeeDistribution(String::from("weights must sum to 1")));
+    }
+
+    let collected_fees = COLLECTED_FEES.may_load(deps.storage)?.unwrap_or_default();
+    COLLECTED_FEES.save(deps.storage, &Uint128::zero())?;
 
+    let mut response = Response::new();
+    let mut remaining_fees = collected_fees;
+    for (idx, (recipient, weight)) in recipients.iter().enumerate() {
+        let recipient = deps.api.addr_validate(recipient)?;
+        // The last recipient receives any rounding remainder
+        let amount = if idx == recipients.len() - 1 {
+            remaining_fees
+        } else {
+            collected_fees * *weight
+        };
+        remaining_fees -= amount;
+        if amount > Uint128::zero() {
+            transfer_token(
+                coin(amount.u128(), &config.denom),
+                recipient.to_string(),
+                "payout-market",
+                &config,
+                &mut response,
+            )?;
+        }
+    }
+
+    let event = Event::new("distribute-fees")
+        .add_attribute("coin", coin(collected_fees.u128(), &config.denom).to_string());
+    response.events.push(event);
+
+    Ok(response)
+}
+
+/// An operator or fee admin may split the market fee of each sale between recipients and a burn
+pub fn execute_set_fee_splits(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipients: Vec<(Addr, Decimal)>,
+    burn_share: Decimal,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
     let config = CONFIG.load(deps.storage)?;
-    let mut response = Response::new();
+    only_role(deps.as_ref(), &info, &config, Role::FeeAdmin)?;
 
-    transfer_nft(&ask.token_id, &ask.seller, &config.cw721_address, &mut response)?;
+    // Without recipients or a burn the collector is paid the whole fee again
+    if recipients.is_empty() && burn_share.is_zero() {
+        FEE_SPLITS.remove(deps.storage);
+        return Ok(Response::new().add_event(Event::new("set-fee-splits")));
+    }
+    if recipients.len() > MAX_FEE_SPLITS {
+        return Err(ContractError::InvalidFeeDistribution(format!("at most {} recipients", MAX_FEE_SPLITS)));
+    }
+    for (idx, (recipient, share)) in recipients.iter().enumerate() {
+        if share.is_zero() {
+            return Err(ContractError::InvalidFeeDistribution(String::from("shares must be greater than zero")));
+        }
+        if recipients[..idx].iter().any(|(other, _)| other == recipient) {
+            return Err(ContractError::InvalidFeeDistribution(format!("recipient {} is duplicated", recipient)));
+        }
+    }
+    let total_share = recipients
+        .iter()
+        .fold(burn_share, |total, (_, share)| total + *share);
+    if total_share != Decimal::one() {
+        return Err(ContractError::InvalidFeeDistribution(String::from("shares must sum to 1")));
+    }
 
-    let event = Event::new("remove-ask")
-        .add_attribute("collection", config.cw721_address.to_string())
-        .add_attribute("token_id", token_id.to_string());
+    let mut event = Event::new("set-fee-splits");
+    for (recipient, share) in &recipients {
+        event = event.add_attribute(format!("share_{}", recipient), share.to_string());
+    }
+    event = event.add_attribute("burn_share", burn_share.to_string());
+    FEE_SPLITS.save(deps.storage, &FeeSplits { recipients, burn_share })?;
 
-    Ok(response.add_event(event))
+    Ok(Response::new().add_event(event))
 }
 
-/// Places a bid on a listed or unlisted NFT. The bid is escrowed in the contract.
-pub fn execute_set_bid(
+/// An operator or collection manager may override the royalty paid on sales of a collection, split between one or more creators
+pub fn execute_set_collection_royalty(
     deps: DepsMut,
-    env: Env,
     info: MessageInfo,
-    bid: Bid,
+    collection: Addr,
+    splits: Vec<(Addr, Decimal)>,
 ) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
     let config = CONFIG.load(deps.storage)?;
+    only_role(deps.as_ref(), &info, &config, Role::CollectionManager)?;
 
-    let received_amount = must_pay(&info, &config.denom)?;
-    if bid.price.amount != received_amount  {
-        return Err(ContractError::IncorrectBidPayment(bid.price.amount, received_amount));
+    if collection != config.cw721_address {
+        return Err(ContractError::InvalidConfig(String::from("collection is not traded on this marketplace")));
     }
-    price_validate(&bid.price, &config)?;
+    if splits.is_empty() {
+        return Err(ContractError::InvalidConfig(String::from("royalty splits must be non-empty")));
+    }
+    for (idx, (recipient, share)) in splits.iter().enumerate() {
+        if share.is_zero() {
+            return Err(ContractError::InvalidConfig(String::from("royalty shares must be greater than zero")));
+        }
+        if splits[..idx].iter().any(|(other, _)| other == recipient) {
+            return Err(ContractError::InvalidConfig(format!("royalty recipient {} is duplicated", recipient)));
+        }
+    }
+    let royalty = CollectionRoyalty { splits };
+    let percent = royalty.percent();
+    if percent > Decimal::one() {
+        return Err(ContractError::InvalidConfig(String::from("royalty percent must be less than or equal to 100")));
+    }
+    validate_royalty(&config, percent)?;
+
+    let recipients = royalty
+        .splits
+        .iter()
+        .map(|(recipient, _)| recipient.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    COLLECTION_ROYALTIES.save(deps.storage, &collection, &royalty)?;
+
+    let event = Event::new("set-collection-royalty")
+        .add_attribute("collection", collection.to_string())
+        .add_attribute("recipients", recipients)
+        .add_attribute("percent", percent.to_string());
+
+    Ok(Response::new().add_event(event))
+}
 
-    let mut response = Response::new();
-    let bid_key = bid_key(&bid.bidder, bid.token_id.clone());
-    let ask_key = &bid.token_id;
+/// An operator or collection manager may link addresses controlled by the same party, so they cannot accept each other's bids
+pub fn execute_link_addresses(
+    deps: DepsMut,
+    info: MessageInfo,
+    set_id: u64,
+    addresses: Vec<String>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    let config = CONFIG.load(deps.storage)?;
+    only_role(deps.as_ref(), &info, &config, Role::CollectionManager)?;
 
-    // If bid exists, refund the escrowed tokens
-    if let Some(existing_bid) = bids().may_load(deps.storage, bid_key.clone())? {
-        bids().remove(deps.storage, bid_key.clone())?;
-        transfer_token(
-            existing_bid.price,
-            existing_bid.bidder.to_string(),
-            "refund-bidder",
-            &mut response,
-        )?;
+    if addresses.len() > MAX_LINKED_ADDRESSES {
+        return Err(ContractError::InvalidConfig(format!("at most {} addresses per call", MAX_LINKED_ADDRESSES)));
+    }
+    for address in map_validate(deps.api, &addresses)? {
+        LINKED_ADDRESSES.save(deps.storage, &address, &set_id)?;
     }
 
-    // If existing ask found, finalize the sale
-    let matching_ask = match_bid(deps.as_ref(), &env, &bid, &mut response)?;
-    match matching_ask {
-        // If matching ask found:
-        // * calculate surplus
-        // * finalize sale
-        // * remove ask
-        Some(ask) => {
-            let surplus_amount = received_amount - ask.price.amount;
-            finalize_sale(
-                deps.as_ref(),
-                &bid.bidder,
-                &ask.token_id,
-                ask.price.amount,
-                &ask.get_recipient(),
-                surplus_amount,
-                &bid.bidder,
-                &config,
-                &mut response,
-            )?;
-            asks().remove(deps.storage, ask_key.clone())?;
-        },
-        // If matching ask not found:
-        // * save bid
-        None => { bids().save(deps.storage, bid_key, &bid)? }
-    };
-
-    let event = Event::new("set-bid")
-        .add_attribute("token_id", bid.token_id.to_string())
-        .add_attribute("bidder", bid.bidder)
-        .add_attribute("price", bid.price.to_string());
-    response.events.push(event);
+    let event = Event::new("link-addresses")
+        .add_attribute("set_id", set_id.to_string())
+        .add_attribute("addresses", addresses.join(","));
 
-    Ok(response)
+    Ok(Response::new().add_event(event))
 }
 
-/// Removes a bid made by the bidder. Bidders can only remove their own bids
-pub fn execute_remove_bid(
+/// An operator or collection manager may remove addresses from their linked address sets
+pub fn execute_unlink_addresses(
     deps: DepsMut,
-    _env: Env,
     info: MessageInfo,
-    token_id: TokenId,
+    addresses: Vec<String>,
 ) -> Result<Response, ContractError> {
     nonpayable(&info)?;
-    let bidder = info.sender;
-
-    let key = bid_key(&bidder, token_id.clone());
-    let bid = bids().load(deps.storage, key.clone())?;
-    bids().remove(deps.storage, key)?;
+    let config = CONFIG.load(deps.storage)?;
+    only_role(deps.as_ref(), &info, &config, Role::CollectionManager)?;
 
-    let mut response = Response::new();
-    transfer_token(bid.price, bid.bidder.to_string(), "refund-bidder", &mut response)?;
+    if addresses.len() > MAX_LINKED_ADDRESSES {
+        return Err(ContractError::InvalidConfig(format!("at most {} addresses per call", MAX_LINKED_ADDRESSES)));
+    }
+    for address in map_validate(deps.api, &addresses)? {
+        LINKED_ADDRESSES.remove(deps.storage, &address);
+    }
 
-    let event = Event::new("remove-bid")
-        .add_attribute("token_id", token_id.clone())
-        .add_attribute("bidder", bidder);
-    response.events.push(event);
+    let event = Event::new("unlink-addresses")
+        .add_attribute("addresses", addresses.join(","));
 
-    Ok(response)
+    Ok(Response::new().add_event(event))
 }
 
-/// Seller can accept a bid which transfers funds as well as the token. The bid may or may not be associated with an ask.
-pub fn execute_accept_bid(
+/// An operator may grant a role to an address
+pub fn execute_grant_role(
     deps: DepsMut,
     info: MessageInfo,
-    token_id: TokenId,
-    bidder: Addr,
+    address: Addr,
+    role: Role,
 ) -> Result<Response, ContractError> {
     nonpayable(&info)?;
+    let config = CONFIG.load(deps.storage)?;
+    only_operator(&info, &config)?;
 
-    let bid_key = bid_key(&bidder, token_id.clone());
-    let bid = bids().load(deps.storage, bid_key.clone())?;
+    let mut roles = ROLES.may_load(deps.storage, &address)?.unwrap_or_default();
+    if roles.contains(&role) {
+        return Err(ContractError::InvalidConfig(format!("{} already holds the {} role", address, role.as_str())));
+    }
+    roles.push(role);
+    ROLES.save(deps.storage, &address, &roles)?;
+
+    let event = Event::new("grant-role")
+        .add_attribute("address", address)
+        .add_attribute("role", role.as_str());
+
+    Ok(Response::new().add_event(event))
+}
 
+/// An operator may revoke a role from an address
+pub fn execute_revoke_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: Addr,
+    role: Role,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
     let config = CONFIG.load(deps.storage)?;
-    let existing_ask = asks().may_load(deps.storage, token_id.clone())?;
+    only_operator(&info, &config)?;
 
-    only_owner_or_seller(
-        deps.as_ref(),
-        &info,
-        &config.cw721_address,
-        &token_id,
-        &existing_ask.clone().map_or(None, |a| Some(a.seller)),
-    )?;
+    let mut roles = ROLES.may_load(deps.storage, &address)?.unwrap_or_default();
+    if !roles.contains(&role) {
+        return Err(ContractError::InvalidConfig(format!("{} does not hold the {} role", address, role.as_str())));
+    }
+    roles.retain(|r| r != &role);
+    if roles.is_empty() {
+        ROLES.remove(deps.storage, &address);
+    } else {
+        ROLES.save(deps.storage, &address, &roles)?;
+    }
 
-    // Remove ask if it exists, define recipient
-    let payment_recipient = match existing_ask {
-        Some(ask) => {
-            asks().remove(deps.storage, ask.token_id.clone())?;
-            ask.get_recipient()
-        },
-        None => info.sender,
-    };
+    let event = Event::new("revoke-role")
+        .add_attribute("address", address)
+        .add_attribute("role", role.as_str());
 
-    let mut response = Response::new();
+    Ok(Response::new().add_event(event))
+}
 
-    // Transfer funds and NFT
-    finalize_sale(
-        deps.as_ref(),
-        &bid.bidder,
-        &token_id,
-        bid.price.amount,
-        &payment_recipient,
-        Uint128::zero(),
-        &bid.bidder,
-        &config,
-        &mut response,
-    )?;
+/// An operator or pauser may pause or resume trading
+pub fn execute_set_halted(
+    deps: DepsMut,
+    info: MessageInfo,
+    halted: bool,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    let config = CONFIG.load(deps.storage)?;
+    only_role(deps.as_ref(), &info, &config, Role::Pauser)?;
 
-    // Remove accepted bid
-    bids().remove(deps.storage, bid_key)?;
+    set_halted(deps, halted)
+}
 
-    let event = Event::new("accept-bid")
-        .add_attribute("token_id", token_id.to_string())
-        .add_attribute("bidder", bidder)
-        .add_attribute("price", bid.price.to_string());
-    response.events.push(event);
+/// Pauses or resumes trading for an operator or for governance through sudo
+pub fn set_halted(deps: DepsMut, halted: bool) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    config.halted = halted;
+    CONFIG.save(deps.storage, &config)?;
 
-    Ok(response)
+    let event = Event::new("set-halted")
+        .add_attribute("halted", halted.to_string());
+
+    Ok(Response::new().add_event(event))
 }
 
-/// Place a collection bid (limit order) across an entire collection
-pub fn execute_set_collection_bid(
+/// An operator may register a contract to be notified of new asks, new bids and finalized sales
+pub fn execute_add_hook(
     deps: DepsMut,
     info: MessageInfo,
-    collection_bid: CollectionBid
+    hook: Addr,
 ) -> Result<Response, ContractError> {
-    if collection_bid.units == 0 {
-        return Err(ContractError::InvalidCollectionBid {});
-    }
-
+    nonpayable(&info)?;
     let config = CONFIG.load(deps.storage)?;
-    
-    // Escrows the amount (price * units)
-    let received_amount = must_pay(&info, &config.denom)?;
-    price_validate(&collection_bid.price, &config)?;
-    if Uint128::from(collection_bid.total_cost()) != received_amount  {
-        return Err(ContractError::IncorrectBidPayment(
-            Uint128::from(collection_bid.total_cost()),
-            received_amount,
-        ));
-    }
-    let collection_bid_key = collection_bid.bidder.clone();
-    let mut response = Response::new();
+    only_operator(&info, &config)?;
 
-    // If collection bid exists, refund the escrowed tokens
-    if let Some(existing_bid) = collection_bids().may_load(deps.storage, collection_bid_key.clone())? {
-        collection_bids().remove(deps.storage, collection_bid_key.clone())?;
-        transfer_token(
-            coin(existing_bid.total_cost(), existing_bid.price.denom),
-            existing_bid.bidder.to_string(),
-            "refund-collection-bidder",
-            &mut response,
-        )?;
+    let mut hooks = HOOKS.may_load(deps.storage)?.unwrap_or_default();
+    if hooks.contains(&hook) {
+        return Err(ContractError::InvalidHook(format!("{} is already registered", hook)));
     }
-    collection_bids().save(deps.storage, collection_bid_key, &collection_bid)?;
+    if hooks.len() >= MAX_HOOKS {
+        return Err(ContractError::InvalidHook(format!("at most {} hooks", MAX_HOOKS)));
+    }
+    hooks.push(hook.clone());
+    HOOKS.save(deps.storage, &hooks)?;
 
-    let event = Event::new("set-collection-bid")
-        .add_attribute("bidder", collection_bid.bidder)
-        .add_attribute("price", collection_bid.price.to_string())
-        .add_attribute("units", collection_bid.units.to_string());
-    response.events.push(event);
+    let event = Event::new("add-hook")
+        .add_attribute("hook", hook);
 
-    Ok(response)
+    Ok(Response::new().add_event(event))
 }
 
-/// Remove an existing collection bid (limit order)
-pub fn execute_remove_collection_bid(
+/// An operator may stop notifying a hook contract
+pub fn execute_remove_hook(
     deps: DepsMut,
-    _env: Env,
     info: MessageInfo,
+    hook: Addr,
 ) -> Result<Response, ContractError> {
     nonpayable(&info)?;
-    let mut response = Response::new();
-    
-    let collection_bid_key = info.sender.clone();
-
-    let collection_bid = collection_bids().load(deps.storage, collection_bid_key.clone())?;
+    let config = CONFIG.load(deps.storage)?;
+    only_operator(&info, &config)?;
 
-    collection_bids().remove(deps.storage, collection_bid_key)?;
-    transfer_token(
-        coin(collection_bid.total_cost(), collection_bid.price.denom),
-        collection_bid.bidder.to_string(),
-        "refund-collection-bidder",
-        &mut response,
-    )?;
+    let mut hooks = HOOKS.may_load(deps.storage)?.unwrap_or_default();
+    if !hooks.contains(&hook) {
+        return Err(ContractError::InvalidHook(format!("{} is not registered", hook)));
+    }
+    hooks.retain(|h| h != &hook);
+    HOOKS.save(deps.storage, &hooks)?;
 
-    let event = Event::new("remove-collection-bid")
-        .add_attribute("bidder", collection_bid.bidder);
-    response.events.push(event);
+    let event = Event::new("remove-hook")
+        .add_attribute("hook", hook);
 
-    Ok(response)
+    Ok(Response::new().add_event(event))
 }
 
-/// Owner/seller of an item in a collection can accept a collection bid which transfers funds as well as a token
-pub fn execute_accept_collection_bid(
+/// A seller may register the public key they sign off-chain asks with
+pub fn execute_set_signing_key(
     deps: DepsMut,
     info: MessageInfo,
-    token_id: TokenId,
-    bidder: Addr,
+    pubkey: Binary,
 ) -> Result<Response, ContractError> {
     nonpayable(&info)?;
+    SIGNING_KEYS.save(deps.storage, &info.sender, &pubkey)?;
 
-    let collection_bid_key = bidder.clone();
-    let mut collection_bid = collection_bids().load(deps.storage, collection_bid_key.clone())?;
+    let event = Event::new("set-signing-key")
+        .add_attribute("seller", info.sender)
+        .add_attribute("pubkey", pubkey.to_base64());
+
+    Ok(Response::new().add_event(event))
+}
 
+/// Buyer fills a seller-signed ask without the ask ever being stored. The NFT is
+/// pulled from the seller through their approval and the sale is finalized.
+pub fn execute_fill_signed_ask(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    ask: SignedAsk,
+    signature: Binary,
+) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
-    let existing_ask = asks().may_load(deps.storage, token_id.clone())?;
-    only_owner_or_seller(
-        deps.as_ref(),
-        &info,
-        &config.cw721_address,
-        &token_id,
-        &existing_ask.clone().map_or(None, |a| Some(a.seller)),
-    )?;
+    price_validate(&ask.price, &config)?;
+    if ask.expires_at <= env.block.time {
+        return Err(ContractError::SignedAskExpired {});
+    }
 
-    // Remove ask if it exists, define recipient
-    let payment_recipient = match existing_ask {
-        Some(ask) => {
-            asks().remove(deps.storage, ask.token_id.clone())?;
-            ask.get_recipient()
-        },
-        None => info.sender,
-    };
+    let received_amount = must_pay(&info, &ask.price.denom)?;
+    if ask.price.amount != received_amount {
+        return Err(ContractError::IncorrectBidPayment(ask.price.amount, received_amount));
+    }
 
-    match collection_bid.units {
-        1 => {
-            // Remove accepted collection bid when no units remain
-            collection_bids().remove(deps.storage, collection_bid_key)?;
-        },
-        _ => {
-            // Decrement the number of units on the collection bid by 1
-            collection_bid.units -= 1;
-            collection_bids().save(deps.storage, collection_bid_key, &collection_bid)?;
-        }
+    // Verify the seller signed this ask for this marketplace on this chain
+    let seller = deps.api.addr_validate(&ask.seller)?;
+    let pubkey = SIGNING_KEYS
+        .may_load(deps.storage, &seller)?
+        .ok_or_else(|| ContractError::Unauthorized(String::from("seller has no signing key")))?;
+    let payload = to_binary(&SignedAskPayload {
+        chain_id: env.block.chain_id.clone(),
+        marketplace: env.contract.address.to_string(),
+        ask: ask.clone(),
+    })?;
+    let hash = Sha256::digest(payload.as_slice());
+    let is_valid = deps
+        .api
+        .secp256k1_verify(&hash, &signature, &pubkey)
+        .map_err(|_| ContractError::InvalidSignature {})?;
+    if !is_valid {
+        return Err(ContractError::InvalidSignature {});
     }
 
-    let mut response = Response::new();
+    if SIGNED_ASK_NONCES.may_load(deps.storage, (&seller, ask.nonce))?.is_some() {
+        return Err(ContractError::NonceAlreadyUsed(ask.nonce));
+    }
+    SIGNED_ASK_NONCES.save(deps.storage, (&seller, ask.nonce), &true)?;
+
+    // The seller must still hold the NFT, and it cannot be listed on-chain. A custodial
+    // ask fails the owner check, a non-custodial one leaves the NFT with the seller.
+    let res = Cw721Contract(config.cw721_address.clone())
+        .owner_of(&deps.querier, ask.token_id.clone(), false)?;
+    if res.owner != seller {
+        return Err(ContractError::Unauthorized(String::from("seller does not own the NFT")));
+    }
+    if asks().may_load(deps.storage, ask.token_id.clone())?.is_some() {
+        return Err(ContractError::AskAlreadyExists(ask.token_id));
+    }
 
-    // Transfer funds and NFT
+    let payment_recipient = maybe_addr(deps.api, ask.funds_recipient.clone())?
+        .unwrap_or_else(|| seller.clone());
+
+    let sale_seq = record_sale(deps.branch(), &env, &ask.token_id, &info.sender, &seller, &ask.price, &config, None)?;
+
+    let mut response = Response::new();
     finalize_sale(
         deps.as_ref(),
-        &collection_bid.bidder,
-        &token_id,
-        collection_bid.price.amount,
+        &info.sender,
+        &ask.token_id,
+        sale_seq,
+        &ask.price,
         &payment_recipient,
         Uint128::zero(),
-        &collection_bid.bidder,
+        Uint128::zero(),
+        &info.sender,
+        None,
         &config,
         &mut response,
     )?;
 
-    let event = Event::new("accept-collection-bid")
-        .add_attribute("bidder", collection_bid.bidder)
-        .add_attribute("price", collection_bid.price.to_string())
-        .add_attribute("units", collection_bid.units.to_string());
+    let event = Event::new("fill-signed-ask")
+        .add_attribute("token_id", ask.token_id)
+        .add_attribute("seller", seller)
+        .add_attribute("buyer", info.sender)
+        .add_attribute("price", ask.price.to_string())
+        .add_attribute("nonce", ask.nonce.to_string());
     response.events.push(event);
 
     Ok(response)
 }
+
+/// Seller cancels a signed ask they handed out by burning its nonce
+pub fn execute_cancel_signed_ask(
+    deps: DepsMut,
+    info: MessageInfo,
+    nonce: u64,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    if SIGNED_ASK_NONCES.may_load(deps.storage, (&info.sender, nonce))?.is_some() {
+        return Err(ContractError::NonceAlreadyUsed(nonce));
+    }
+    SIGNED_ASK_NONCES.save(deps.storage, (&info.sender, nonce), &true)?;
+
+    let event = Event::new("cancel-signed-ask")
+        .add_attribute("seller", info.sender)
+        .add_attribute("nonce", nonce.to_string());
+
+    Ok(Response::new().add_event(event))
+}
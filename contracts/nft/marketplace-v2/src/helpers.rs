@@ -1,18 +1,31 @@
-use crate::msg::{ExecuteMsg};
+use crate::msg::{ExecuteMsg, HookMsg};
 use crate::error::ContractError;
 use crate::state::{
-    Config, TokenId, Bid, bids, Ask, asks
+    Config, CONFIG, TokenId, Bid, bids, Ask, asks, LAST_SALE_PRICES, LAST_SALE_TIMES, COLLECTED_FEES,
+    Settlement, settlements, SETTLEMENT_SEQ, CREDITS, CollectionRoyalty, COLLECTION_ROYALTIES,
+    LINKED_ADDRESSES, TraitFilter, CollectionBid, collection_bids, HOOKS, Role, ROLES, FEE_SPLITS,
 };
 use cosmwasm_std::{
-    to_binary, Addr, Api, StdResult, WasmMsg,CosmosMsg, Order,
+    from_binary, to_binary, Addr, Api, Binary, StdResult, WasmMsg,CosmosMsg, Order,
     Deps, Event, Coin, coin, Uint128, Response, MessageInfo, Attribute,
-    BankMsg, SubMsg, Env, Decimal
+    BankMsg, SubMsg, Env, Decimal, DepsMut, ReplyOn
 };
 use pg721::msg::{CollectionInfoResponse, QueryMsg as Pg721QueryMsg};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use cw20::Cw20ExecuteMsg;
 use cw721::{Cw721ExecuteMsg};
 use cw721_base::helpers::Cw721Contract;
+use std::collections::BTreeMap;
+
+// Max decimals for the price_display event attribute
+const MAX_DISPLAY_DECIMALS: u32 = 18;
+// Number of most recent settlements kept in state
+const MAX_SETTLEMENTS: u64 = 1000;
+// Max collection bids considered for a new ask, best priced first
+const MAX_COLLECTION_BID_MATCHES: usize = 30;
+/// Reply id of hook submessages, sale ids start at 1 so it never collides with a sale's NFT transfer
+pub const HOOK_REPLY_ID: u64 = 0;
 
 // MarketplaceContract is a wrapper around Addr that provides a lot of helpers
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -48,121 +61,362 @@ pub fn option_bool_to_order(descending: Option<bool>) -> Order {
     }
 }
 
-/// Transfers funds and NFT, updates bid
+/// The highest bid on a token
+pub fn highest_bid(deps: Deps, token_id: &TokenId) -> StdResult<Option<Bid>> {
+    bids()
+        .idx
+        .token_price
+        .sub_prefix(token_id.clone())
+        .range(deps.storage, None, None, Order::Descending)
+        .next()
+        .transpose()
+        .map(|item| item.map(|(_, bid)| bid))
+}
+
+/// Records a sale in state: the sale price and time, the settlement, and any collected market fee.
+/// Returns the sale sequence number. Must be called before the sale's messages are added with `finalize_sale`.
+pub fn record_sale(
+    deps: DepsMut,
+    env: &Env,
+    token_id: &TokenId,
+    buyer: &Addr,
+    seller: &Addr,
+    payment: &Coin,
+    config: &Config,
+    referrer: Option<&Addr>,
+) -> StdResult<u64> {
+    LAST_SALE_PRICES.save(deps.storage, token_id, payment)?;
+    LAST_SALE_TIMES.save(deps.storage, token_id, &env.block.time)?;
+
+    // Append the settlement, dropping the oldest once the retention limit is reached
+    let id = SETTLEMENT_SEQ.may_load(deps.storage)?.unwrap_or_default() + 1;
+    SETTLEMENT_SEQ.save(deps.storage, &id)?;
+    settlements().save(deps.storage, id, &Settlement {
+        id,
+        token_id: token_id.clone(),
+        buyer: buyer.clone(),
+        seller: seller.clone(),
+        price: payment.clone(),
+        time: env.block.time,
+    })?;
+    if id > MAX_SETTLEMENTS {
+        settlements().remove(deps.storage, id - MAX_SETTLEMENTS)?;
+    }
+
+    // The referral share is paid out with the sale, not collected
+    let retained_fee = market_fee(payment.amount, config) - referral_fee(payment.amount, referrer, config);
+    if accumulates_fees(&payment.denom, config) && retained_fee > Uint128::zero() {
+        let collected_fees = COLLECTED_FEES.may_load(deps.storage)?.unwrap_or_default();
+        COLLECTED_FEES.save(deps.storage, &(collected_fees + retained_fee))?;
+    }
+
+    Ok(id)
+}
+
+/// Transfers funds and NFT, updates bid. `credit_amount` is withheld from the seller's proceeds as fee credit.
 pub fn finalize_sale(
     deps: Deps,
     bidder: &Addr,
     token_id: &TokenId,
-    payment_amount: Uint128,
+    sale_seq: u64,
+    payment: &Coin,
     payment_recipient: &Addr,
+    credit_amount: Uint128,
     surplus_amount: Uint128,
     surplus_recipient: &Addr,
+    referrer: Option<&Addr>,
     config: &Config,
     res: &mut Response,
-) -> StdResult<()> {
+) -> Result<(), ContractError> {
+    // Every settlement path ends here, so linked addresses cannot trade through any of them
+    let settlement = settlements().load(deps.storage, sale_seq)?;
+    only_unlinked(deps, &settlement.seller, payment_recipient, bidder)?;
+    if let Some(referrer) = referrer {
+        only_third_party_referrer(referrer, &settlement.seller, payment_recipient, bidder)?;
+    }
+
     payout(
         deps,
-        payment_amount,
+        payment,
         payment_recipient,
+        credit_amount,
         surplus_amount,
         surplus_recipient,
+        referrer,
         &config,
         res,
     )?;
 
     transfer_nft(&token_id, bidder, &config.cw721_address, res)?;
+    // The NFT transfer is the sale's last submessage, so its reply confirms or rolls back the whole settlement
+    if let Some(transfer) = res.messages.last_mut() {
+        transfer.id = sale_seq;
+        transfer.reply_on = ReplyOn::Always;
+    }
+
+    prepare_hooks(
+        deps,
+        &HookMsg::SaleFinalizedHook {
+            token_id: token_id.clone(),
+            seller: settlement.seller,
+            buyer: bidder.clone(),
+            price: payment.clone(),
+            sale_seq,
+        },
+        res,
+    )?;
 
     let event = Event::new("finalize-sale")
         .add_attribute("collection", config.cw721_address.to_string())
         .add_attribute("buyer", bidder.to_string())
         .add_attribute("token_id", token_id.to_string())
-        .add_attribute("payment_amount", payment_amount.to_string())
+        .add_attribute("sale_seq", sale_seq.to_string())
+        .add_attribute("payment_amount", payment.amount.to_string())
         .add_attribute("payment_recipient", payment_recipient.to_string());
-    res.events.push(event);
+    res.events.push(with_price_display(event, payment.amount, config));
 
     Ok(())
 }
 
+/// Notifies every registered hook contract. Hooks only reply on error, so a failing hook
+/// never reverts the action that fired it.
+pub fn prepare_hooks(deps: Deps, hook_msg: &HookMsg, res: &mut Response) -> StdResult<()> {
+    let hooks = HOOKS.may_load(deps.storage)?.unwrap_or_default();
+    for hook in hooks {
+        let execute = WasmMsg::Execute {
+            contract_addr: hook.to_string(),
+            msg: to_binary(hook_msg)?,
+            funds: vec![],
+        };
+        res.messages.push(SubMsg::reply_on_error(execute, HOOK_REPLY_ID));
+    }
+
+    Ok(())
+}
+
+/// Formats a base unit amount with the given number of decimals, e.g. 1250000 with 6 decimals is "1.25"
+pub fn format_price(amount: Uint128, decimals: u32) -> String {
+    let digits = format!("{:0>width$}", amount.to_string(), width = decimals as usize + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals as usize);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
+
+/// Adds a `price_display` attribute to the event when display decimals are configured
+pub fn with_price_display(event: Event, amount: Uint128, config: &Config) -> Event {
+    match config.display_decimals {
+        Some(decimals) => event.add_attribute("price_display", format_price(amount, decimals)),
+        None => event,
+    }
+}
+
+/// The marketplace fee charged on a sale
+pub fn market_fee(payment_amount: Uint128, config: &Config) -> Uint128 {
+    payment_amount * config.trading_fee_percent / Uint128::from(100u128)
+}
+
+/// The share of the market fee paid to the referrer of a sale, zero without a referrer
+pub fn referral_fee(payment_amount: Uint128, referrer: Option<&Addr>, config: &Config) -> Uint128 {
+    match (referrer, config.referral_fee_share) {
+        (Some(_), Some(share)) => market_fee(payment_amount, config) * share,
+        _ => Uint128::zero(),
+    }
+}
+
+/// Fees are only held in the config denom, fees in other accepted denoms are paid out with each sale
+pub fn accumulates_fees(denom: &str, config: &Config) -> bool {
+    config.accumulate_fees && denom == config.denom
+}
+
+/// Redeems the seller's fee credit against the market fee of a sale, returning the amount redeemed
+pub fn redeem_credit(deps: DepsMut, seller: &Addr, market_fee: Uint128) -> StdResult<Uint128> {
+    let credit = CREDITS.may_load(deps.storage, seller)?.unwrap_or_default();
+    let redeemed = credit.min(market_fee);
+    if redeemed == credit {
+        CREDITS.remove(deps.storage, seller);
+    } else if !redeemed.is_zero() {
+        CREDITS.save(deps.storage, seller, &(credit - redeemed))?;
+    }
+    Ok(redeemed)
+}
+
+/// The royalty paid on sales of the collection. A royalty set by operators takes precedence over
+/// the royalty info of the collection itself.
+pub fn collection_royalty(deps: Deps, config: &Config) -> StdResult<Option<CollectionRoyalty>> {
+    if let Some(royalty) = COLLECTION_ROYALTIES.may_load(deps.storage, &config.cw721_address)? {
+        return Ok(Some(royalty));
+    }
+
+    let collection_info: CollectionInfoResponse = deps
+        .querier
+        .query_wasm_smart(&config.cw721_address, &Pg721QueryMsg::CollectionInfo {})?;
+
+    collection_info
+        .royalty_info
+        .map(|royalty| -> StdResult<_> {
+            Ok(CollectionRoyalty {
+                splits: vec![(deps.api.addr_validate(&royalty.payment_address)?, royalty.share)],
+            })
+        })
+        .transpose()
+}
+
+/// The collection royalty charged on a sale
+pub fn royalty_amount(deps: Deps, payment_amount: Uint128, config: &Config) -> StdResult<Uint128> {
+    Ok(collection_royalty(deps, config)?.map_or(Uint128::zero(), |royalty| {
+        royalty
+            .payouts(payment_amount)
+            .into_iter()
+            .fold(Uint128::zero(), |total, (_, amount)| total + amount)
+    }))
+}
+
+/// The seller's proceeds from a sale, after the market fee and royalties
+pub fn seller_net_proceeds(deps: Deps, payment_amount: Uint128, config: &Config) -> StdResult<Uint128> {
+    Ok(payment_amount
+        .checked_sub(market_fee(payment_amount, config))?
+        .checked_sub(royalty_amount(deps, payment_amount, config)?)?)
+}
+
+/// The market fee and a collection royalty together cannot take more than the sale price.
+/// Fee credit is a share of what is left, so it always fits.
+pub fn validate_royalty(config: &Config, royalty_percent: Decimal) -> Result<(), ContractError> {
+    if config.trading_fee_percent * Decimal::percent(1) + royalty_percent > Decimal::one() {
+        return Err(ContractError::InvalidConfig(String::from("trading fee and royalty percent must be less than or equal to 100")));
+    }
+    Ok(())
+}
+
 /// Payout a bid
 pub fn payout(
     deps: Deps,
-    payment_amount: Uint128,
+    payment: &Coin,
     payment_recipient: &Addr,
+    credit_amount: Uint128,
     surplus_amount: Uint128,
     surplus_recipient: &Addr,
+    referrer: Option<&Addr>,
     config: &Config,
     response: &mut Response,
-) -> StdResult<()> {
+) -> Result<(), ContractError> {
+    let payment_amount = payment.amount;
+    let denom = &payment.denom;
+
     if surplus_amount > Uint128::zero() {
         transfer_token(
-            coin(surplus_amount.u128(), &config.denom),
+            coin(surplus_amount.u128(), denom),
             surplus_recipient.to_string(),
             "payout-surplus",
+            config,
             response
         )?;
     }
 
-    let cw721_address = config.cw721_address.to_string();
-
-    // Charge market fee
-    let market_fee = payment_amount * config.trading_fee_percent / Uint128::from(100u128);
-    if market_fee > Uint128::zero() {
-        transfer_token(
-            coin(market_fee.u128(), &config.denom),
-            config.collector_address.to_string(),
-            "payout-market",
-            response
-        )?;
+    // Charge market fee, paying the referral share to the referrer. Collected fees are recorded by `record_sale`
+    let market_fee = market_fee(payment_amount, config);
+    let referral_fee = referral_fee(payment_amount, referrer, config);
+    if let Some(referrer) = referrer {
+        if referral_fee > Uint128::zero() {
+            transfer_token(
+                coin(referral_fee.u128(), denom),
+                referrer.to_string(),
+                "payout-referral",
+                config,
+                response
+            )?;
+        }
+    }
+    let retained_fee = market_fee - referral_fee;
+    if retained_fee > Uint128::zero() {
+        if accumulates_fees(denom, config) {
+            let event = Event::new("collect-market-fee")
+                .add_attribute("coin", coin(retained_fee.u128(), denom).to_string());
+            response.events.push(event);
+        } else if let Some(fee_splits) = FEE_SPLITS.may_load(deps.storage)? {
+            let (recipients, burn_amount) = fee_splits.payouts(retained_fee);
+            for (recipient, amount) in recipients {
+                if amount > Uint128::zero() {
+                    transfer_token(
+                        coin(amount.u128(), denom),
+                        recipient.to_string(),
+                        "payout-market",
+                        config,
+                        response
+                    )?;
+                }
+            }
+            if burn_amount > Uint128::zero() {
+                burn_token(coin(burn_amount.u128(), denom), config, response)?;
+            }
+        } else {
+            transfer_token(
+                coin(retained_fee.u128(), denom),
+                config.collector_address.to_string(),
+                "payout-market",
+                config,
+                response
+            )?;
+        }
     }
 
-    // Query royalties
-    let collection_info: CollectionInfoResponse = deps
-        .querier
-        .query_wasm_smart(&cw721_address, &Pg721QueryMsg::CollectionInfo {})?;
-
-    // Charge royalties if they exist
-    let royalties = match &collection_info.royalty_info {
-        Some(royalty) => Some((payment_amount * royalty.share, &royalty.payment_address)),
-        None => None
-    };
-    if let Some(_royalties) = &royalties {
-        if _royalties.0 > Uint128::zero() {
+    // Charge royalties if they exist, paying each creator their split
+    let royalties = collection_royalty(deps, config)?
+        .map_or(vec![], |royalty| royalty.payouts(payment_amount));
+    for (recipient, amount) in &royalties {
+        if *amount > Uint128::zero() {
             transfer_token(
-                coin(_royalties.0.u128(), &config.denom),
-                _royalties.1.to_string(),
+                coin(amount.u128(), denom),
+                recipient.to_string(),
                 "payout-royalty",
+                config,
                 response
             )?;
         }
-    };
+    }
 
-    // Pay seller
-    let mut seller_amount = payment_amount - market_fee;
-    if let Some(_royalties) = &royalties {
-        seller_amount -= _royalties.0;
-    };
+    // Pay seller, less any proceeds kept as fee credit
+    let royalty_total = royalties
+        .iter()
+        .fold(Uint128::zero(), |total, (_, amount)| total + *amount);
+    let seller_amount = payment_amount
+        .checked_sub(market_fee + credit_amount + royalty_total)
+        .map_err(|_| ContractError::FeesExceedPrice {})?;
 
-    transfer_token(
-        coin(seller_amount.u128(), &config.denom),
-        payment_recipient.to_string(),
-        "payout-seller",
-        response
-    )?;
+    if seller_amount > Uint128::zero() {
+        transfer_token(
+            coin(seller_amount.u128(), denom),
+            payment_recipient.to_string(),
+            "payout-seller",
+            config,
+            response
+        )?;
+    }
 
     Ok(())
 }
 
+/// The min price of an accepted denom, none if the denom is not accepted
+pub fn denom_min_price(denom: &str, config: &Config) -> Option<Uint128> {
+    if denom == config.denom {
+        return Some(config.min_price);
+    }
+    config
+        .accepted_denoms
+        .iter()
+        .find(|accepted| accepted.denom == denom)
+        .map(|accepted| accepted.amount)
+}
+
 // Validate Bid or Ask price
 pub fn price_validate(price: &Coin, config: &Config) -> Result<(), ContractError> {
-    if
-        price.amount.is_zero() ||
-        price.denom != config.denom ||
-        price.amount < config.min_price
-    {
-        return Err(ContractError::InvalidPrice {});
+    match denom_min_price(&price.denom, config) {
+        Some(min_price) if !price.amount.is_zero() && price.amount >= min_price => Ok(()),
+        _ => Err(ContractError::InvalidPrice {}),
     }
-
-    Ok(())
 }
 
 /// Checks to enforce only NFT owner can call
@@ -179,6 +433,79 @@ pub fn only_owner_or_seller(
     }
 }
 
+/// Checks that a recently sold token is past the relist cooldown
+pub fn only_after_relist_cooldown(
+    deps: Deps,
+    env: &Env,
+    token_id: &TokenId,
+    config: &Config,
+) -> Result<(), ContractError> {
+    if let Some(relist_cooldown_seconds) = config.relist_cooldown_seconds {
+        if let Some(last_sale_time) = LAST_SALE_TIMES.may_load(deps.storage, token_id)? {
+            if env.block.time < last_sale_time.plus_seconds(relist_cooldown_seconds) {
+                return Err(ContractError::RelistCooldown(token_id.clone()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks the gates a seller must pass to list a token, shared by SetAsk and the CanList query.
+/// Returns the token's existing ask.
+pub fn only_lister(
+    deps: Deps,
+    env: &Env,
+    info: &MessageInfo,
+    token_id: &TokenId,
+    config: &Config,
+) -> Result<Option<Ask>, ContractError> {
+    only_after_relist_cooldown(deps, env, token_id, config)?;
+
+    let existing_ask = asks().may_load(deps.storage, token_id.clone())?;
+    only_owner_or_seller(
+        deps,
+        info,
+        &config.cw721_address,
+        token_id,
+        &existing_ask.clone().map_or(None, |a| Some(a.seller)),
+    )?;
+
+    Ok(existing_ask)
+}
+
+/// Checks that the seller and the recipient of a sale are not linked to the bidder
+pub fn only_unlinked(
+    deps: Deps,
+    seller: &Addr,
+    payment_recipient: &Addr,
+    bidder: &Addr,
+) -> Result<(), ContractError> {
+    let bidder_set = match LINKED_ADDRESSES.may_load(deps.storage, bidder)? {
+        Some(set_id) => set_id,
+        None => return Ok(()),
+    };
+    for address in [seller, payment_recipient] {
+        if LINKED_ADDRESSES.may_load(deps.storage, address)? == Some(bidder_set) {
+            return Err(ContractError::LinkedAddresses(address.to_string(), bidder.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the referrer of a sale is neither its buyer nor its seller, who would otherwise
+/// rebate part of the market fee to themselves
+pub fn only_third_party_referrer(
+    referrer: &Addr,
+    seller: &Addr,
+    payment_recipient: &Addr,
+    bidder: &Addr,
+) -> Result<(), ContractError> {
+    if [seller, payment_recipient, bidder].contains(&referrer) {
+        return Err(ContractError::SelfReferral(referrer.to_string()));
+    }
+    Ok(())
+}
+
 /// Checks to enforce only NFT owner can call
 pub fn only_owner(
     deps: Deps,
@@ -193,6 +520,86 @@ pub fn only_owner(
     Ok(())
 }
 
+/// Metadata attributes of a token, as stored by collections with onchain metadata
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenMetadata {
+    pub attributes: Option<Vec<TokenTrait>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenTrait {
+    pub trait_type: String,
+    pub value: String,
+}
+
+/// Checks that a token carries every trait required by a collection bid
+pub fn only_matching_traits(
+    deps: Deps,
+    collection: &Addr,
+    token_id: &str,
+    traits: &[TraitFilter],
+) -> Result<(), ContractError> {
+    if traits.is_empty() {
+        return Ok(());
+    }
+    let res = Cw721Contract(collection.clone())
+        .nft_info::<_, Option<TokenMetadata>>(&deps.querier, token_id)?;
+    let attributes = res.extension.and_then(|m| m.attributes).unwrap_or_default();
+    for required in traits {
+        if !attributes.iter().any(|a| a.trait_type == required.trait_type && a.value == required.value) {
+            return Err(ContractError::TraitMismatch(token_id.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the marketplace still holds the NFT in escrow
+pub fn only_escrowed(
+    deps: Deps,
+    env: &Env,
+    collection: &Addr,
+    token_id: &str,
+) -> Result<(), ContractError> {
+    let res = Cw721Contract(collection.clone()).owner_of(&deps.querier, token_id, false)?;
+    if res.owner != env.contract.address {
+        return Err(ContractError::NftNotEscrowed(token_id.to_string()));
+    }
+    Ok(())
+}
+
+/// Whether a non-custodial ask can still be settled: the seller holds the NFT and the marketplace
+/// is approved to transfer it
+pub fn is_deliverable(deps: Deps, env: &Env, ask: &Ask, config: &Config) -> StdResult<bool> {
+    let collection = Cw721Contract(config.cw721_address.clone());
+    let res = collection.owner_of(&deps.querier, ask.token_id.clone(), false)?;
+    if res.owner != ask.seller {
+        return Ok(false);
+    }
+    let marketplace = env.contract.address.to_string();
+    if res.approvals.iter().any(|a| a.spender == marketplace) {
+        return Ok(true);
+    }
+    let operators = collection.all_operators(&deps.querier, ask.seller.to_string(), false, None, None)?;
+    Ok(operators.iter().any(|a| a.spender == marketplace))
+}
+
+/// Checks that the NFT of an ask can be delivered to a buyer: escrowed by the marketplace, or
+/// for a non-custodial ask held by the seller with the marketplace approved
+pub fn only_deliverable(
+    deps: Deps,
+    env: &Env,
+    ask: &Ask,
+    config: &Config,
+) -> Result<(), ContractError> {
+    if !ask.non_custodial {
+        return only_escrowed(deps, env, &config.cw721_address, &ask.token_id);
+    }
+    if !is_deliverable(deps, env, ask, config)? {
+        return Err(ContractError::NftNotDeliverable(ask.token_id.clone()));
+    }
+    Ok(())
+}
+
 /// Checks to enforce only Ask seller can call
 pub fn only_seller(
     info: &MessageInfo,
@@ -204,6 +611,32 @@ pub fn only_seller(
     Ok(())
 }
 
+/// Messages that trade, which are rejected while the marketplace is halted
+pub fn is_trade(msg: &ExecuteMsg) -> bool {
+    matches!(
+        msg,
+        ExecuteMsg::SetAsk { .. }
+            | ExecuteMsg::CreateAsk { .. }
+            | ExecuteMsg::CounterWithAsk { .. }
+            | ExecuteMsg::SetAsks { .. }
+            | ExecuteMsg::UpdateAsk { .. }
+            | ExecuteMsg::SetBundleAsk { .. }
+            | ExecuteMsg::BuyBundle { .. }
+            | ExecuteMsg::SetBid { .. }
+            | ExecuteMsg::BuyNow { .. }
+            | ExecuteMsg::Sweep { .. }
+            | ExecuteMsg::AcceptBid { .. }
+            | ExecuteMsg::AcceptBids { .. }
+            | ExecuteMsg::AcceptBestBidBefore { .. }
+            | ExecuteMsg::SetCollectionBid { .. }
+            | ExecuteMsg::IncreaseCollectionBidUnits { .. }
+            | ExecuteMsg::AcceptCollectionBid { .. }
+            | ExecuteMsg::AcceptCollectionBidBulk { .. }
+            | ExecuteMsg::FillSignedAsk { .. }
+            | ExecuteMsg::ReceiveNft(_)
+    )
+}
+
 /// Checks to enforce only privileged operators
 pub fn only_operator(info: &MessageInfo, config: &Config) -> Result<Addr, ContractError> {
     if !config
@@ -217,6 +650,19 @@ pub fn only_operator(info: &MessageInfo, config: &Config) -> Result<Addr, Contra
     Ok(info.sender.clone())
 }
 
+/// Operators hold every role, other addresses only the roles granted to them
+pub fn only_role(deps: Deps, info: &MessageInfo, config: &Config, role: Role) -> Result<Addr, ContractError> {
+    if config.operators.contains(&info.sender) {
+        return Ok(info.sender.clone());
+    }
+    let roles = ROLES.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+    if !roles.contains(&role) {
+        return Err(ContractError::Unauthorized(format!("only an operator or {} can call this function", role.as_str())));
+    }
+
+    Ok(info.sender.clone())
+}
+
 pub fn transfer_nft(token_id: &TokenId, recipient: &Addr, collection: &Addr, response: &mut Response,) -> StdResult<()> {
     let cw721_transfer_msg = Cw721ExecuteMsg::TransferNft {
         token_id: token_id.to_string(),
@@ -239,10 +685,107 @@ pub fn transfer_nft(token_id: &TokenId, recipient: &Addr, collection: &Addr, res
     Ok(())
 }
 
-pub fn transfer_token(coin_send: Coin, recipient: String, event_label: &str, response: &mut Response) -> StdResult<()> {
-    let token_transfer_msg = BankMsg::Send {
-        to_address: recipient.clone(),
-        amount: vec![coin_send.clone()]
+/// Transfer an escrowed NFT back to its seller, emitting a consistent `nft-returned` event
+pub fn return_nft(token_id: &TokenId, seller: &Addr, collection: &Addr, response: &mut Response) -> StdResult<()> {
+    transfer_nft(token_id, seller, collection, response)?;
+
+    let event = Event::new("nft-returned")
+        .add_attribute("collection", collection.to_string())
+        .add_attribute("token_id", token_id.to_string())
+        .add_attribute("recipient", seller.to_string());
+    response.events.push(event);
+
+    Ok(())
+}
+
+/// The only shape an on_remove_msg may take: a single collection action, such as a burn or
+/// a redeem, whose sole argument is the token id
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OnRemoveAction {
+    token_id: TokenId,
+}
+
+/// The marketplace sends an on_remove_msg under its own identity, so it may only act on the
+/// ask's own NFT, never on other escrowed NFTs or on the marketplace's approvals
+pub fn validate_on_remove_msg(token_id: &TokenId, msg: &Binary) -> Result<(), ContractError> {
+    let actions: BTreeMap<String, OnRemoveAction> = from_binary(msg).map_err(|_| {
+        ContractError::InvalidOnRemoveMsg(String::from("message must be a single action taking only a token_id"))
+    })?;
+    if actions.len() != 1 {
+        return Err(ContractError::InvalidOnRemoveMsg(String::from("message must be a single action taking only a token_id")));
+    }
+    if actions.values().any(|action| &action.token_id != token_id) {
+        return Err(ContractError::InvalidOnRemoveMsg(format!("message must target token_id {}", token_id)));
+    }
+    Ok(())
+}
+
+/// Send an ask's on_remove_msg to the collection in place of returning the escrowed NFT
+pub fn dispatch_on_remove_msg(token_id: &TokenId, msg: Binary, collection: &Addr, response: &mut Response) -> StdResult<()> {
+    response.messages.push(SubMsg::new(WasmMsg::Execute {
+        contract_addr: collection.to_string(),
+        msg,
+        funds: vec![],
+    }));
+
+    let event = Event::new("on-remove-msg")
+        .add_attribute("collection", collection.to_string())
+        .add_attribute("token_id", token_id.to_string());
+    response.events.push(event);
+
+    Ok(())
+}
+
+/// Burn the burn share of the market fee, through the CW20 token when cw20_payments is set
+pub fn burn_token(coin_burn: Coin, config: &Config, response: &mut Response) -> StdResult<()> {
+    let burn_msg: CosmosMsg = if config.cw20_payments {
+        WasmMsg::Execute {
+            contract_addr: coin_burn.denom.clone(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: coin_burn.amount,
+            })?,
+            funds: vec![],
+        }
+        .into()
+    } else {
+        BankMsg::Burn {
+            amount: vec![coin_burn.clone()]
+        }
+        .into()
+    };
+    response.messages.push(SubMsg::new(burn_msg));
+
+    let event = Event::new("burn-market-fee")
+        .add_attribute("coin", coin_burn.to_string());
+    response.events.push(event);
+
+    Ok(())
+}
+
+pub fn transfer_token(
+    coin_send: Coin,
+    recipient: String,
+    event_label: &str,
+    config: &Config,
+    response: &mut Response,
+) -> StdResult<()> {
+    let token_transfer_msg: CosmosMsg = if config.cw20_payments {
+        WasmMsg::Execute {
+            contract_addr: coin_send.denom.clone(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.clone(),
+                amount: coin_send.amount,
+            })?,
+            funds: vec![],
+        }
+        .into()
+    } else {
+        BankMsg::Send {
+            to_address: recipient.clone(),
+            amount: vec![coin_send.clone()]
+        }
+        .into()
     };
     response.messages.push(SubMsg::new(token_transfer_msg));
 
@@ -260,6 +803,10 @@ pub fn match_ask(deps: Deps, ask: &Ask, response: &mut Response) -> StdResult<Op
         .token_price
         .sub_prefix(ask.token_id.clone())
         .range(deps.storage, None, None, Order::Descending)
+        .filter(|item| item.as_ref().map_or(true, |(_, b)| {
+            b.price.denom == ask.price.denom &&
+                ask.reserve_for.as_ref().map_or(true, |reserved| reserved == &b.bidder)
+        }))
         .take(1usize)
         .map(|item| item.map(|(_, b)| b))
         .collect::<StdResult<Vec<_>>>()?;
@@ -279,6 +826,14 @@ pub fn match_ask(deps: Deps, ask: &Ask, response: &mut Response) -> StdResult<Op
         response.events.push(event);
         return Ok(None)
     }
+    if let Some(min_net_proceeds) = ask.min_net_proceeds {
+        let config = CONFIG.load(deps.storage)?;
+        if seller_net_proceeds(deps, highest_bid.price.amount, &config)? < min_net_proceeds {
+            set_match_outcome(&mut event, "net-too-low");
+            response.events.push(event);
+            return Ok(None)
+        }
+    }
 
     response.events.push(event);
     return Ok(Some(highest_bid))
@@ -296,16 +851,94 @@ pub fn match_bid(deps: Deps, _env: &Env, bid: &Bid, response: &mut Response) ->
         .add_attribute("token-id", bid.token_id.clone())
         .add_attribute("outcome", "match");
     
-    if existing_ask.price.amount > bid.price.amount {
+    if existing_ask.price.denom != bid.price.denom {
+        set_match_outcome(&mut event, "denom-mismatch");
+        response.events.push(event);
+        return Ok(None)
+    }
+    if existing_ask.reserve_for.as_ref().map_or(false, |reserved| reserved != &bid.bidder) {
+        set_match_outcome(&mut event, "reserved");
+        response.events.push(event);
+        return Ok(None)
+    }
+    if existing_ask.sale_price_for(&bid.bidder) > bid.price.amount {
         set_match_outcome(&mut event, "bid-too-low");
         response.events.push(event);
         return Ok(None)
     }
+    if let Some(min_net_proceeds) = existing_ask.min_net_proceeds {
+        let config = CONFIG.load(deps.storage)?;
+        let sale_amount = existing_ask.sale_price_for(&bid.bidder);
+        if seller_net_proceeds(deps, sale_amount, &config)? < min_net_proceeds {
+            set_match_outcome(&mut event, "net-too-low");
+            response.events.push(event);
+            return Ok(None)
+        }
+    }
+    if existing_ask.price.amount > bid.price.amount {
+        set_match_outcome(&mut event, "pre-authorized");
+    }
 
     response.events.push(event);
     return Ok(Some(existing_ask))
 }
 
+/// Finds the best collection bid that would buy a newly listed token at or above its ask price,
+/// returning it with the payment one unit of it makes
+pub fn match_collection_bid(
+    deps: Deps,
+    ask: &Ask,
+    response: &mut Response,
+) -> StdResult<Option<(CollectionBid, Coin)>> {
+    if ask.min_net_proceeds.is_some() {
+        return Ok(None)
+    }
+    let last_sale_price = LAST_SALE_PRICES.may_load(deps.storage, &ask.token_id)?;
+
+    for item in collection_bids()
+        .idx
+        .price
+        .range(deps.storage, None, None, Order::Descending)
+        .take(MAX_COLLECTION_BID_MATCHES)
+    {
+        let (_, collection_bid) = item?;
+        // Bids are ordered by price, so no later bid can reach the ask
+        if collection_bid.price.amount < ask.price.amount {
+            break;
+        }
+        let amount = collection_bid.budget.map_or(collection_bid.price.amount, |b| b.min(collection_bid.price.amount));
+        if
+            amount < ask.price.amount ||
+            collection_bid.price.denom != ask.price.denom ||
+            collection_bid.bidder == ask.seller ||
+            ask.reserve_for.as_ref().map_or(false, |reserved| reserved != &collection_bid.bidder) ||
+            (collection_bid.all_or_nothing && collection_bid.units > 1) ||
+            // Trait bids need an NftInfo query each, they are only filled by AcceptCollectionBid
+            !collection_bid.traits.is_empty()
+        {
+            continue;
+        }
+        if collection_bid.only_below_last_sale &&
+            last_sale_price.as_ref().map_or(false, |p| p.amount >= collection_bid.price.amount)
+        {
+            continue;
+        }
+        if only_unlinked(deps, &ask.seller, &ask.get_recipient(), &collection_bid.bidder).is_err() {
+            continue;
+        }
+
+        let event = Event::new("match-collection-bid")
+            .add_attribute("token-id", ask.token_id.clone())
+            .add_attribute("bidder", collection_bid.bidder.to_string())
+            .add_attribute("price", amount.to_string());
+        response.events.push(event);
+        let payment = coin(amount.u128(), &collection_bid.price.denom);
+        return Ok(Some((collection_bid, payment)))
+    }
+
+    Ok(None)
+}
+
 fn set_match_outcome(event: &mut Event, outcome: &str) -> () {
     event.attributes = event.attributes.iter_mut().map(|attr| {
         if attr.key == "outcome" {
@@ -328,5 +961,37 @@ pub fn validate_config(config: &Config) -> Result<(), ContractError> {
     if config.min_price.is_zero() {
         return Err(ContractError::InvalidConfig(String::from("min_price must be greater than zero")));
     }
+    if config.denom.is_empty() {
+        return Err(ContractError::InvalidConfig(String::from("denom must be non-empty")));
+    }
+    for (idx, accepted) in config.accepted_denoms.iter().enumerate() {
+        if accepted.denom.is_empty() || accepted.denom == config.denom {
+            return Err(ContractError::InvalidConfig(String::from("accepted denoms must be non-empty and differ from denom")));
+        }
+        if config.accepted_denoms[..idx].iter().any(|other| other.denom == accepted.denom) {
+            return Err(ContractError::InvalidConfig(format!("accepted denom {} is duplicated", accepted.denom)));
+        }
+        if accepted.amount.is_zero() {
+            return Err(ContractError::InvalidConfig(String::from("accepted denom min prices must be greater than zero")));
+        }
+    }
+    if config.cw20_payments && !config.accepted_denoms.is_empty() {
+        return Err(ContractError::InvalidConfig(String::from("cw20 payments only accept the denom token")));
+    }
+    if let Some(min_bid_fraction_of_ask) = config.min_bid_fraction_of_ask {
+        if min_bid_fraction_of_ask > Decimal::one() {
+            return Err(ContractError::InvalidConfig(String::from("min_bid_fraction_of_ask must be less than or equal to 1")));
+        }
+    }
+    if let Some(referral_fee_share) = config.referral_fee_share {
+        if referral_fee_share > Decimal::one() {
+            return Err(ContractError::InvalidConfig(String::from("referral_fee_share must be less than or equal to 1")));
+        }
+    }
+    if let Some(display_decimals) = config.display_decimals {
+        if display_decimals > MAX_DISPLAY_DECIMALS {
+            return Err(ContractError::InvalidConfig(String::from("display_decimals must be less than or equal to 18")));
+        }
+    }
     Ok(())
 }
\ No newline at end of file
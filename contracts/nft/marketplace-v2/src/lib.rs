@@ -8,6 +8,7 @@ mod multitest;
 
 pub mod query;
 pub mod state;
+pub mod sudo;
 
 pub use error::ContractError;
 pub use helpers::{MarketplaceContract};
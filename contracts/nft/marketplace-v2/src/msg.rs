@@ -1,5 +1,7 @@
-use crate::state::{Ask, TokenId, Bid, Config, CollectionBid};
-use cosmwasm_std::{Addr, Coin, Uint128};
+use crate::state::{Ask, BundleAsk, TokenId, Bid, Config, CollectionBid, FeeSplits, Role, Settlement, TraitFilter};
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Timestamp, Uint128};
+use cw20::Cw20ReceiveMsg;
+use cw721::Cw721ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -17,8 +19,34 @@ pub struct InstantiateMsg {
     /// Operators are entites that are responsible for maintaining the active state of Asks.
     /// They listen to NFT transfer events, and update the active state of Asks.
     pub operators: Vec<String>,
+    /// Add the instantiating address to the operators
+    pub include_sender_as_operator: bool,
     /// Min value for bids and asks
     pub min_price: Uint128,
+    /// Minimum bid on a listed NFT, as a fraction of the ask price
+    pub min_bid_fraction_of_ask: Option<Decimal>,
+    /// Hold marketplace fees in the contract until they are distributed
+    pub accumulate_fees: bool,
+    /// Seconds after a sale before the token can be listed again
+    pub relist_cooldown_seconds: Option<u64>,
+    /// Refund and remove bids on a token when its ask is removed
+    pub refund_bids_on_ask_removal: bool,
+    /// Decimals used to format the price_display attribute on events
+    pub display_decimals: Option<u32>,
+    /// Max units of a single collection bid
+    pub max_collection_bid_units: Option<u32>,
+    /// Prices are in the CW20 token at the denom address, paid in through Receive
+    pub cw20_payments: bool,
+    /// Denoms accepted besides denom, each with its own min price
+    pub accepted_denoms: Vec<Coin>,
+    /// Share of the market fee paid to the referrer of a sale
+    pub referral_fee_share: Option<Decimal>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {
+    /// Min value for a bid, required when migrating from the legacy marketplace
+    pub min_price: Option<Uint128>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -30,42 +58,320 @@ pub enum ExecuteMsg {
         trading_fee_bps: Option<u64>,
         operators: Option<Vec<String>>,
         min_price: Option<Uint128>,
+        denom: Option<String>,
+        min_bid_fraction_of_ask: Option<Decimal>,
+        accumulate_fees: Option<bool>,
+        relist_cooldown_seconds: Option<u64>,
+        refund_bids_on_ask_removal: Option<bool>,
+        display_decimals: Option<u32>,
+        max_collection_bid_units: Option<u32>,
+        accepted_denoms: Option<Vec<Coin>>,
+        referral_fee_share: Option<Decimal>,
     },
     /// List an NFT on the marketplace by creating a new ask
     SetAsk {
         token_id: TokenId,
         price: Coin,
         funds_recipient: Option<String>,
+        external_id: Option<String>,
+        min_offer: Option<Uint128>,
+        pre_authorized: Option<(String, Uint128)>,
+        min_net_proceeds: Option<Uint128>,
+        hidden_min: Option<Uint128>,
+        on_remove_msg: Option<Binary>,
+        reserve_for: Option<String>,
+        /// Keep the NFT with the seller, escrowed by the marketplace when omitted
+        #[serde(default)]
+        non_custodial: bool,
+    },
+    /// List an NFT on the marketplace, failing if an ask already exists
+    CreateAsk {
+        token_id: TokenId,
+        price: Coin,
+        funds_recipient: Option<String>,
+        external_id: Option<String>,
+        min_offer: Option<Uint128>,
+        pre_authorized: Option<(String, Uint128)>,
+        min_net_proceeds: Option<Uint128>,
+        hidden_min: Option<Uint128>,
+        on_remove_msg: Option<Binary>,
+        reserve_for: Option<String>,
+        /// Keep the NFT with the seller, escrowed by the marketplace when omitted
+        #[serde(default)]
+        non_custodial: bool,
+    },
+    /// List an unlisted NFT that has bids, countering them with an ask price
+    CounterWithAsk {
+        token_id: TokenId,
+        price: Coin,
+    },
+    /// Set several asks at once, all or none
+    SetAsks {
+        asks: Vec<AskInput>,
+    },
+    /// Change the price and reserved buyer of an existing ask, keeping the NFT in escrow.
+    /// The reserved buyer is kept when `reserve_for` is omitted, `clear_reserve_for` removes it.
+    UpdateAsk {
+        token_id: TokenId,
+        price: Coin,
+        reserve_for: Option<String>,
+        #[serde(default)]
+        clear_reserve_for: bool,
     },
     /// Remove an existing ask from the marketplace
     RemoveAsk {
         token_id: TokenId,
     },
+    /// Remove several asks at once, all or none
+    RemoveAsks {
+        token_ids: Vec<TokenId>,
+    },
+    /// Remove a non-custodial ask whose NFT left the seller or lost its marketplace approval.
+    /// Anyone can call this.
+    RemoveStaleAsk {
+        token_id: TokenId,
+    },
+    /// List several NFTs to be sold together at a single price, escrowing all of them
+    SetBundleAsk {
+        token_ids: Vec<TokenId>,
+        price: Coin,
+        funds_recipient: Option<String>,
+    },
+    /// Remove a bundle ask, returning its NFTs to the seller
+    RemoveBundleAsk {
+        bundle_id: u64,
+    },
+    /// Buy every NFT of a bundle ask at its price
+    BuyBundle {
+        bundle_id: u64,
+    },
     /// Place a bid on an existing ask
     SetBid {
         token_id: TokenId,
         price: Coin,
+        refund_to: Option<String>,
+        /// Paid the referral share of the market fee if the bid settles immediately
+        referrer: Option<String>,
+    },
+    /// Buy a listed NFT at its ask price, failing if the price is not `expected_price`
+    BuyNow {
+        token_id: TokenId,
+        expected_price: Coin,
+        /// Paid the referral share of the market fee
+        referrer: Option<String>,
+    },
+    /// Buy up to `limit` of the cheapest listings in the config denom for at most `max_total_price`,
+    /// which must be attached. The unspent funds are refunded.
+    Sweep {
+        limit: u32,
+        max_total_price: Uint128,
     },
     /// Remove an existing bid from an ask
     RemoveBid {
         token_id: TokenId,
     },
-    /// Accept a bid on an existing ask
+    /// Remove the sender's bids on several tokens at once, all or none
+    RemoveBids {
+        token_ids: Vec<TokenId>,
+    },
+    /// Accept a bid on an existing ask, optionally keeping `credit_bps` of the seller's
+    /// proceeds as marketplace fee credit
     AcceptBid {
         token_id: TokenId,
         bidder: String,
+        credit_bps: Option<u64>,
+        /// Fail instead of selling if the bid is below this amount
+        min_price: Option<Uint128>,
+        /// Paid the referral share of the market fee
+        referrer: Option<String>,
+    },
+    /// Withdraw the sender's whole marketplace fee credit
+    WithdrawCredit {},
+    /// Accept bids on several tokens at once, all or none
+    AcceptBids {
+        items: Vec<(TokenId, String)>,
+    },
+    /// Accept the highest bid on a token, failing if the block time is after `not_after`
+    AcceptBestBidBefore {
+        token_id: TokenId,
+        not_after: Timestamp,
     },
     /// Place a bid (limit order) across an entire collection
     SetCollectionBid {
         units: u32,
         price: Coin,
+        #[serde(default)]
+        all_or_nothing: bool,
+        #[serde(default)]
+        only_below_last_sale: bool,
+        /// Attributes a token must all carry to fill the bid, any token when omitted
+        #[serde(default)]
+        traits: Vec<TraitFilter>,
+        /// Escrow this total instead of `price * units`, filling at up to `price` per unit
+        budget: Option<Uint128>,
     },
     /// Remove a bid (limit order) across an entire collection
     RemoveCollectionBid { },
+    /// Lower the units of a collection bid, refunding the escrow for the removed units
+    ReduceCollectionBid {
+        new_units: u32,
+    },
+    /// Raise the units of a collection bid, escrowing the price of each added unit
+    IncreaseCollectionBidUnits {
+        additional_units: u32,
+    },
     /// Accept a collection bid
     AcceptCollectionBid {
         token_id: TokenId,
         bidder: String,
+        /// Fail instead of selling if the bid pays less than this amount
+        min_price: Option<Uint128>,
+    },
+    /// Accept a collection bid for several tokens at once
+    AcceptCollectionBidBulk {
+        token_ids: Vec<TokenId>,
+        bidder: String,
+    },
+    /// Split the collected marketplace fees between recipients by weight
+    DistributeFees {
+        recipients: Vec<(String, Decimal)>,
+    },
+    /// Operator or fee admin only, split the market fee of each sale between recipients and a burn.
+    /// The shares must sum to 1, no recipients and no burn share pays the collector again.
+    SetFeeSplits {
+        recipients: Vec<(String, Decimal)>,
+        burn_share: Decimal,
+    },
+    /// Refund and remove up to `limit` bids and collection bids, e.g. ahead of a denom change
+    RefundAllBids {
+        limit: u32,
+    },
+    /// Operator or collection manager only, override the royalty paid on sales of a collection
+    SetCollectionRoyalty {
+        collection: String,
+        recipient: String,
+        /// Share of the sale price, e.g. 0.05 for 5%
+        percent: Decimal,
+    },
+    /// Operator or collection manager only, override the royalty paid on sales of a collection with a split between creators.
+    /// Each share is of the sale price, and together they make up the total royalty.
+    SetCollectionRoyaltySplits {
+        collection: String,
+        splits: Vec<(String, Decimal)>,
+    },
+    /// Operator or collection manager only, add addresses to a set of linked addresses that cannot trade with each other
+    LinkAddresses {
+        set_id: u64,
+        addresses: Vec<String>,
+    },
+    /// Operator or collection manager only, remove addresses from their linked address sets
+    UnlinkAddresses {
+        addresses: Vec<String>,
+    },
+    /// Operator only, grant a role to an address
+    GrantRole {
+        address: String,
+        role: Role,
+    },
+    /// Operator only, revoke a role from an address
+    RevokeRole {
+        address: String,
+        role: Role,
+    },
+    /// Operator or pauser only, pause or resume trading. Asks and bids can still be removed while halted.
+    SetHalted {
+        halted: bool,
+    },
+    /// Operator only, register a contract notified of new asks, new bids and finalized sales
+    AddHook {
+        hook: String,
+    },
+    /// Operator only, stop notifying a hook contract
+    RemoveHook {
+        hook: String,
+    },
+    /// Register the public key used to sign off-chain asks
+    SetSigningKey {
+        pubkey: Binary,
+    },
+    /// Buy an NFT from an ask the seller signed off-chain
+    FillSignedAsk {
+        ask: SignedAsk,
+        signature: Binary,
+    },
+    /// Seller only, burn the nonce of a signed ask so it can no longer be filled
+    CancelSignedAsk {
+        nonce: u64,
+    },
+    /// After migrating from the legacy marketplace, turn up to `limit` of its remaining
+    /// listings into asks. Anyone can call this until no listing remains.
+    MigrateLegacy {
+        limit: u32,
+    },
+    /// Pay with the CW20 token when cw20_payments is set. The wrapped message is one of
+    /// SetBid, BuyNow, Sweep, BuyBundle, SetCollectionBid, IncreaseCollectionBidUnits or FillSignedAsk, run for the token sender.
+    Receive(Cw20ReceiveMsg),
+    /// List an NFT sent with the collection's SendNft, without a prior approval. The wrapped
+    /// message is a SetAsk for the sent token, listed for the NFT sender.
+    ReceiveNft(Cw721ReceiveMsg),
+}
+
+/// An ask signed off-chain by the seller, never stored by the marketplace
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SignedAsk {
+    pub token_id: TokenId,
+    pub seller: String,
+    pub price: Coin,
+    pub funds_recipient: Option<String>,
+    /// Unique per seller, a signed ask can only be filled once
+    pub nonce: u64,
+    /// The signed ask cannot be filled from this block time on
+    pub expires_at: Timestamp,
+}
+
+/// The fields of a SetAsk, for setting several asks at once
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AskInput {
+    pub token_id: TokenId,
+    pub price: Coin,
+    pub funds_recipient: Option<String>,
+    pub external_id: Option<String>,
+    pub min_offer: Option<Uint128>,
+    pub pre_authorized: Option<(String, Uint128)>,
+    pub min_net_proceeds: Option<Uint128>,
+    pub hidden_min: Option<Uint128>,
+    pub on_remove_msg: Option<Binary>,
+    pub reserve_for: Option<String>,
+    #[serde(default)]
+    pub non_custodial: bool,
+}
+
+/// The message a seller signs: the SHA-256 hash of this payload's JSON encoding. The chain id
+/// keeps a signature from being replayed on a marketplace at the same address on another chain.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SignedAskPayload {
+    pub chain_id: String,
+    pub marketplace: String,
+    pub ask: SignedAsk,
+}
+
+/// Message sent to every registered hook contract. A failing hook does not revert the
+/// marketplace action that fired it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HookMsg {
+    AskCreatedHook {
+        ask: Ask,
+    },
+    BidCreatedHook {
+        bid: Bid,
+    },
+    SaleFinalizedHook {
+        token_id: TokenId,
+        seller: Addr,
+        buyer: Addr,
+        price: Coin,
+        sale_seq: u64,
     },
 }
 
@@ -104,17 +410,81 @@ pub struct CollectionBidPriceOffset {
 }
 
 
+/// Messages only the chain's governance module can send
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SudoMsg {
+    /// Update the marketplace parameters, the operators are managed with AddOperator and RemoveOperator
+    UpdateParams {
+        collector_address: Option<String>,
+        trading_fee_bps: Option<u64>,
+        min_price: Option<Uint128>,
+        denom: Option<String>,
+        min_bid_fraction_of_ask: Option<Decimal>,
+        accumulate_fees: Option<bool>,
+        relist_cooldown_seconds: Option<u64>,
+        refund_bids_on_ask_removal: Option<bool>,
+        display_decimals: Option<u32>,
+        max_collection_bid_units: Option<u32>,
+        accepted_denoms: Option<Vec<Coin>>,
+        referral_fee_share: Option<Decimal>,
+    },
+    /// Pause or resume trading
+    SetHalted {
+        halted: bool,
+    },
+    /// Add an operator
+    AddOperator {
+        operator: String,
+    },
+    /// Remove an operator, at least one must remain
+    RemoveOperator {
+        operator: String,
+    },
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     /// Get the config for the contract
     /// Return type: `ConfigResponse`
     Config {},
+    /// Get the operators of the contract
+    /// Return type: `OperatorsResponse`
+    Operators {},
+    /// Get how the market fee of each sale is split, none when it is paid to the collector
+    /// Return type: `FeeSplitsResponse`
+    FeeSplits {},
+    /// Get the addresses granted roles, with their roles. Offset is the address.
+    /// Return type: `RolesResponse`
+    Roles {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Get the registered hook contracts
+    /// Return type: `HooksResponse`
+    Hooks {},
+    /// Get the cw2 contract name and version stored on instantiate and migrate
+    /// Return type: `ContractVersionResponse`
+    ContractVersion {},
+    /// Get the config and live counts in a single call
+    /// Return type: `BootstrapResponse`
+    Bootstrap {},
     /// Get the current ask for specific NFT
     /// Return type: `AskResponse`
     Ask {
         token_id: TokenId,
     },
+    /// Get a bundle ask by id
+    /// Return type: `BundleAskResponse`
+    BundleAsk {
+        bundle_id: u64,
+    },
+    /// Whether an ask for a specific NFT can currently be matched by a bid
+    /// Return type: `IsMatchableResponse`
+    IsMatchable {
+        token_id: TokenId,
+    },
     /// Get all asks sorted by price
     /// Return type: `AsksResponse`
     AsksSortedByPrice {
@@ -125,6 +495,12 @@ pub enum QueryMsg {
     AsksBySeller {
         query_options: QueryOptions<TokenAddrOffset>
     },
+    /// Whether an address can currently list a token, and if not, why
+    /// Return type: `CanListResponse`
+    CanList {
+        address: String,
+        token_id: TokenId,
+    },
     /// Count of all asks
     /// Return type: `AskCountResponse`
     AskCount {},
@@ -134,12 +510,35 @@ pub enum QueryMsg {
         token_id: TokenId,
         bidder: String,
     },
+    /// Preview whether a bid would match the ask on a token, and how the sale would be split
+    /// Return type: `BidPreviewResponse`
+    BidPreview {
+        token_id: TokenId,
+        bidder: String,
+        price: Coin,
+    },
     /// Get all bids for a token sorted by price
     /// Return type: `BidsResponse`
     BidsByTokenPrice {
         token_id: TokenId,
         query_options: QueryOptions<BidTokenPriceOffset>
     },
+    /// Get the top bids for a token, highest first
+    /// Return type: `BidsResponse`
+    BidLeaderboard {
+        token_id: TokenId,
+        top_n: u32,
+    },
+    /// Get the highest bid on each of several tokens
+    /// Return type: `HighestBidsResponse`
+    HighestBids {
+        token_ids: Vec<TokenId>,
+    },
+    /// Estimate a seller's net proceeds from accepting the highest bid on each of several tokens
+    /// Return type: `LiquidationEstimateResponse`
+    LiquidationEstimate {
+        token_ids: Vec<TokenId>,
+    },
     /// Get all bids by bidders sorted by expiry
     /// Return type: `BidsResponse`
     BidsByBidder {
@@ -155,6 +554,19 @@ pub enum QueryMsg {
     CollectionBidsByPrice {
         query_options: QueryOptions<CollectionBidPriceOffset>
     },
+    /// Get the marketplace fee credit held for an address
+    /// Return type: `CreditsResponse`
+    Credits {
+        address: String,
+    },
+    /// Get recent settlements across all tokens, newest first unless `descending` is false,
+    /// optionally filtered by buyer and seller. Offset is the settlement id.
+    /// Return type: `SettlementsResponse`
+    Settlements {
+        buyer: Option<String>,
+        seller: Option<String>,
+        query_options: QueryOptions<u64>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -162,11 +574,27 @@ pub struct AskResponse {
     pub ask: Option<Ask>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BundleAskResponse {
+    pub bundle_ask: Option<BundleAsk>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IsMatchableResponse {
+    pub is_matchable: bool,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AsksResponse {
     pub asks: Vec<Ask>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CanListResponse {
+    pub can_list: bool,
+    pub reason: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AskCountResponse {
     pub count: u32,
@@ -177,16 +605,95 @@ pub struct BidResponse {
     pub bid: Option<Bid>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BidPreviewResponse {
+    pub matched: bool,
+    pub ask_price: Option<Coin>,
+    /// The following are zero unless the bid matches
+    pub sale_price: Uint128,
+    pub surplus: Uint128,
+    pub market_fee: Uint128,
+    pub royalty: Uint128,
+    pub seller_proceeds: Uint128,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct BidsResponse {
     pub bids: Vec<Bid>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenHighestBid {
+    pub token_id: TokenId,
+    pub bid: Option<Bid>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HighestBidsResponse {
+    pub highest_bids: Vec<TokenHighestBid>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenProceeds {
+    pub token_id: TokenId,
+    /// The highest bid, none if the token is unsellable
+    pub bid: Option<Bid>,
+    /// The seller's proceeds after fees and royalties, zero if the token is unsellable
+    pub net_proceeds: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LiquidationEstimateResponse {
+    /// Total proceeds from bids in the config denom
+    pub total: Coin,
+    pub tokens: Vec<TokenProceeds>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ConfigResponse {
     pub config: Config,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BootstrapResponse {
+    pub config: Config,
+    pub ask_count: u32,
+    pub bid_count: u32,
+    pub collection_bid_count: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContractVersionResponse {
+    pub contract: String,
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OperatorsResponse {
+    pub operators: Vec<Addr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeSplitsResponse {
+    pub fee_splits: Option<FeeSplits>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AddressRoles {
+    pub address: Addr,
+    pub roles: Vec<Role>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RolesResponse {
+    pub roles: Vec<AddressRoles>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HooksResponse {
+    pub hooks: Vec<Addr>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct CollectionBidResponse {
     pub collection_bid: Option<CollectionBid>,
@@ -195,4 +702,14 @@ pub struct CollectionBidResponse {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct CollectionBidsResponse {
     pub collection_bids: Vec<CollectionBid>,
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CreditsResponse {
+    pub credits: Coin,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SettlementsResponse {
+    pub settlements: Vec<Settlement>,
+}
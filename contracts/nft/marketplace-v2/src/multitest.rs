@@ -1,16 +1,21 @@
 #![cfg(test)]
 use crate::error::ContractError;
 use crate::msg::{
-    ExecuteMsg, QueryMsg, AskResponse, AsksResponse, QueryOptions, TokenPriceOffset, AskCountResponse,
-    BidResponse, BidsResponse, ConfigResponse, CollectionBidResponse, CollectionBidsResponse, TokenAddrOffset,
+    ExecuteMsg, QueryMsg, SudoMsg, MigrateMsg, AskInput, SignedAsk, SignedAskPayload, AskResponse, AsksResponse, QueryOptions, TokenPriceOffset, AskCountResponse,
+    IsMatchableResponse, BidResponse, BidsResponse, ConfigResponse, OperatorsResponse, BootstrapResponse, CollectionBidResponse, CollectionBidsResponse, TokenAddrOffset,
+    SettlementsResponse, BidPreviewResponse, CreditsResponse, CanListResponse, HighestBidsResponse,
+    ContractVersionResponse, LiquidationEstimateResponse, BundleAskResponse, HooksResponse, AddressRoles, RolesResponse, FeeSplitsResponse,
 };
-use crate::state::{Ask, Bid, Config, CollectionBid};
-use cosmwasm_std::{Addr, Empty, Attribute, coin, coins, Coin, Decimal, Uint128};
+use crate::state::{Ask, Bid, Config, CollectionBid, Role, TraitFilter};
+use cosmwasm_std::{from_slice, to_binary, Addr, Binary, Empty, Attribute, coin, coins, Coin, Decimal, Uint128};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg};
 use cw721::{Cw721QueryMsg, OwnerOfResponse};
 use cw721_base::msg::{ExecuteMsg as Cw721ExecuteMsg, MintMsg};
 use cw_multi_test::{App, AppBuilder, BankSudo, Contract, ContractWrapper, Executor, SudoMsg as CwSudoMsg};
+use k256::ecdsa::{signature::Signer, Signature, SigningKey};
 use pg721::msg::{InstantiateMsg as Pg721InstantiateMsg, RoyaltyInfoResponse};
 use pg721::state::CollectionInfo;
+use pg721_metadata_onchain::msg::{ExecuteMsg as Pg721MetadataExecuteMsg, Metadata, Trait};
 
 const TOKEN_ID: &str = "123";
 const CREATION_FEE: u128 = 1_000_000_000;
@@ -42,12 +47,234 @@ pub fn contract_marketplace() -> Box<dyn Contract<Empty>> {
         crate::execute::execute,
         crate::execute::instantiate,
         crate::query::query,
+    )
+    .with_reply(crate::execute::reply)
+    .with_sudo(crate::sudo::sudo)
+    .with_migrate(crate::execute::migrate);
+    Box::new(contract)
+}
+
+pub fn contract_marketplace_legacy() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        marketplace_legacy::execute::execute,
+        marketplace_legacy::execute::instantiate,
+        marketplace_legacy::query::query,
+    );
+    Box::new(contract)
+}
+
+pub fn contract_cw20() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        mock_cw20::execute,
+        mock_cw20::instantiate,
+        mock_cw20::query,
+    );
+    Box::new(contract)
+}
+
+/// Just enough of a cw20 token to pay the marketplace with: balances, Transfer, Send, Burn
+/// and the Balance query
+mod mock_cw20 {
+    use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Uint128};
+    use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg};
+    use cw_storage_plus::Map;
+    use serde::{Deserialize, Serialize};
+
+    const BALANCES: Map<&str, Uint128> = Map::new("balance");
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct InstantiateMsg {
+        pub name: String,
+        pub symbol: String,
+        pub decimals: u8,
+        pub initial_balances: Vec<Cw20Coin>,
+    }
+
+    pub fn instantiate(deps: DepsMut, _env: Env, _info: MessageInfo, msg: InstantiateMsg) -> StdResult<Response> {
+        for balance in msg.initial_balances {
+            BALANCES.save(deps.storage, &balance.address, &balance.amount)?;
+        }
+        Ok(Response::new())
+    }
+
+    pub fn execute(deps: DepsMut, _env: Env, info: MessageInfo, msg: Cw20ExecuteMsg) -> StdResult<Response> {
+        match msg {
+            Cw20ExecuteMsg::Transfer { recipient, amount } => {
+                move_tokens(deps, info.sender.as_str(), Some(&recipient), amount)?;
+                Ok(Response::new())
+            }
+            Cw20ExecuteMsg::Burn { amount } => {
+                move_tokens(deps, info.sender.as_str(), None, amount)?;
+                Ok(Response::new())
+            }
+            Cw20ExecuteMsg::Send { contract, amount, msg } => {
+                move_tokens(deps, info.sender.as_str(), Some(&contract), amount)?;
+                let receive = Cw20ReceiveMsg { sender: info.sender.to_string(), amount, msg };
+                Ok(Response::new().add_message(receive.into_cosmos_msg(contract)?))
+            }
+            _ => Err(StdError::generic_err("Unsupported by the mock token")),
+        }
+    }
+
+    pub fn query(deps: Deps, _env: Env, msg: Cw20QueryMsg) -> StdResult<Binary> {
+        match msg {
+            Cw20QueryMsg::Balance { address } => to_binary(&BalanceResponse {
+                balance: BALANCES.may_load(deps.storage, &address)?.unwrap_or_default(),
+            }),
+            _ => Err(StdError::generic_err("Unsupported by the mock token")),
+        }
+    }
+
+    fn move_tokens(deps: DepsMut, from: &str, to: Option<&str>, amount: Uint128) -> StdResult<()> {
+        BALANCES.update(deps.storage, from, |balance| -> StdResult<_> {
+            Ok(balance.unwrap_or_default().checked_sub(amount)?)
+        })?;
+        if let Some(to) = to {
+            BALANCES.update(deps.storage, to, |balance| -> StdResult<_> {
+                Ok(balance.unwrap_or_default() + amount)
+            })?;
+        }
+        Ok(())
+    }
+}
+
+pub fn contract_baseline_marketplace() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        baseline_marketplace::execute,
+        baseline_marketplace::instantiate,
+        baseline_marketplace::query,
     );
-    // .with_sudo(crate::sudo::sudo)
-    // .with_reply(crate::execute::reply);
     Box::new(contract)
 }
 
+/// The marketplace as first deployed, storing its config, asks and bids in their original shape
+mod baseline_marketplace {
+    use cosmwasm_std::{Addr, Binary, Coin, Decimal, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdError, StdResult, Uint128};
+    use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct Config {
+        pub cw721_address: Addr,
+        pub denom: String,
+        pub collector_address: Addr,
+        pub trading_fee_percent: Decimal,
+        pub operators: Vec<Addr>,
+        pub min_price: Uint128,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct Ask {
+        pub token_id: String,
+        pub seller: Addr,
+        pub price: Coin,
+        pub funds_recipient: Option<Addr>,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct Bid {
+        pub token_id: String,
+        pub bidder: Addr,
+        pub price: Coin,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct CollectionBid {
+        pub bidder: Addr,
+        pub units: u32,
+        pub price: Coin,
+    }
+
+    const CONFIG: Item<Config> = Item::new("config");
+
+    struct AskIndices<'a> {
+        price: MultiIndex<'a, u128, Ask, String>,
+        seller: MultiIndex<'a, Addr, Ask, String>,
+    }
+
+    impl<'a> IndexList<Ask> for AskIndices<'a> {
+        fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Ask>> + '_> {
+            let v: Vec<&dyn Index<Ask>> = vec![&self.price, &self.seller];
+            Box::new(v.into_iter())
+        }
+    }
+
+    fn asks<'a>() -> IndexedMap<'a, String, Ask, AskIndices<'a>> {
+        let indexes = AskIndices {
+            price: MultiIndex::new(|d: &Ask| d.price.amount.u128(), "asks", "asks__price"),
+            seller: MultiIndex::new(|d: &Ask| d.seller.clone(), "asks", "asks__seller"),
+        };
+        IndexedMap::new("asks", indexes)
+    }
+
+    struct BidIndices<'a> {
+        token_price: MultiIndex<'a, (String, u128), Bid, (Addr, String)>,
+    }
+
+    impl<'a> IndexList<Bid> for BidIndices<'a> {
+        fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Bid>> + '_> {
+            let v: Vec<&dyn Index<Bid>> = vec![&self.token_price];
+            Box::new(v.into_iter())
+        }
+    }
+
+    fn bids<'a>() -> IndexedMap<'a, (Addr, String), Bid, BidIndices<'a>> {
+        let indexes = BidIndices {
+            token_price: MultiIndex::new(|d: &Bid| (d.token_id.clone(), d.price.amount.u128()), "bids", "bids__token_price"),
+        };
+        IndexedMap::new("bids", indexes)
+    }
+
+    struct CollectionBidIndices<'a> {
+        price: MultiIndex<'a, u128, CollectionBid, Addr>,
+    }
+
+    impl<'a> IndexList<CollectionBid> for CollectionBidIndices<'a> {
+        fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<CollectionBid>> + '_> {
+            let v: Vec<&dyn Index<CollectionBid>> = vec![&self.price];
+            Box::new(v.into_iter())
+        }
+    }
+
+    fn collection_bids<'a>() -> IndexedMap<'a, Addr, CollectionBid, CollectionBidIndices<'a>> {
+        let indexes = CollectionBidIndices {
+            price: MultiIndex::new(|d: &CollectionBid| d.price.amount.u128(), "col_bids", "col_bids__price"),
+        };
+        IndexedMap::new("col_bids", indexes)
+    }
+
+    /// Stores the config, an ask by the seller and a bid and collection bid paid by the sender
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct InstantiateMsg {
+        pub config: Config,
+        pub ask: Ask,
+        pub bid_price: Coin,
+        pub collection_bid: CollectionBid,
+    }
+
+    pub fn instantiate(deps: DepsMut, _env: Env, info: MessageInfo, msg: InstantiateMsg) -> StdResult<Response> {
+        cw2::set_contract_version(deps.storage, "crates.io:marketplace-v2", "0.1.0")?;
+        CONFIG.save(deps.storage, &msg.config)?;
+        asks().save(deps.storage, msg.ask.token_id.clone(), &msg.ask)?;
+        let bid = Bid {
+            token_id: msg.ask.token_id,
+            bidder: info.sender.clone(),
+            price: msg.bid_price,
+        };
+        bids().save(deps.storage, (info.sender, bid.token_id.clone()), &bid)?;
+        collection_bids().save(deps.storage, msg.collection_bid.bidder.clone(), &msg.collection_bid)?;
+        Ok(Response::new())
+    }
+
+    pub fn execute(_deps: DepsMut, _env: Env, _info: MessageInfo, _msg: Empty) -> StdResult<Response> {
+        Err(StdError::generic_err("Unsupported by the baseline marketplace"))
+    }
+
+    pub fn query(_deps: Deps, _env: Env, _msg: Empty) -> StdResult<Binary> {
+        Err(StdError::generic_err("Unsupported by the baseline marketplace"))
+    }
+}
+
 pub fn contract_pg721() -> Box<dyn Contract<Empty>> {
     let contract = ContractWrapper::new(
         pg721::contract::execute,
@@ -57,6 +284,15 @@ pub fn contract_pg721() -> Box<dyn Contract<Empty>> {
     Box::new(contract)
 }
 
+pub fn contract_pg721_metadata_onchain() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        pg721_metadata_onchain::contract::execute,
+        pg721_metadata_onchain::contract::instantiate,
+        pg721_metadata_onchain::contract::query,
+    );
+    Box::new(contract)
+}
+
 // Instantiates all needed contracts for testing
 fn setup_contracts(
     router: &mut App,
@@ -100,7 +336,17 @@ fn setup_contracts(
         collector_address: creator.to_string(),
         trading_fee_bps: TRADING_FEE_BPS,
         operators: vec!["operator".to_string()],
+        include_sender_as_operator: false,
         min_price: Uint128::from(5u128),
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: false,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: false,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        cw20_payments: false,
+        accepted_denoms: vec![],
+        referral_fee_share: None,
     };
     let marketplace = router
         .instantiate_contract(
@@ -218,6 +464,14 @@ fn ask(
         token_id: token_id,
         price: coin(price, NATIVE_DENOM),
         funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
     };
     let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
     assert!(res.is_ok());
@@ -234,6 +488,8 @@ fn bid(
     let set_bid = ExecuteMsg::SetBid {
         token_id: token_id,
         price: coin_send.clone(),
+        refund_to: None,
+        referrer: None,
     };
     let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_bid, &[coin_send]);
     assert!(res.is_ok());
@@ -258,6 +514,14 @@ fn try_add_update_remove_ask() {
         token_id: TOKEN_ID.to_string(),
         price: coin(110, "ujuno"),
         funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
     };
     let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
     assert!(res.is_err());
@@ -267,6 +531,14 @@ fn try_add_update_remove_ask() {
         token_id: TOKEN_ID.to_string(),
         price: coin(1, "ujuno"),
         funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
     };
     let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
     assert!(res.is_err());
@@ -276,6 +548,14 @@ fn try_add_update_remove_ask() {
         token_id: TOKEN_ID.to_string(),
         price: coin(110, NATIVE_DENOM),
         funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
     };
     let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
     assert!(res.is_ok());
@@ -298,6 +578,14 @@ fn try_add_update_remove_ask() {
         price: coin(110, NATIVE_DENOM),
         seller: creator.clone(),
         funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
     }, res_ask);
 
     // Check NFT is transferred to marketplace contract
@@ -316,6 +604,14 @@ fn try_add_update_remove_ask() {
         token_id: TOKEN_ID.to_string(),
         price: coin(200, NATIVE_DENOM),
         funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
     };
     let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
     assert!(res.is_ok());
@@ -338,6 +634,14 @@ fn try_add_update_remove_ask() {
         price: coin(200, NATIVE_DENOM),
         seller: creator.clone(),
         funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
     }, res_ask);
 
     // Remove an ask
@@ -346,6 +650,15 @@ fn try_add_update_remove_ask() {
     };
     let res = router.execute_contract(creator.clone(), marketplace.clone(), &remove_ask, &[]);
     assert!(res.is_ok());
+    // Check the NFT return is reported
+    let returned_event = res
+        .unwrap()
+        .events
+        .into_iter()
+        .find(|e| e.ty == "wasm-nft-returned")
+        .unwrap();
+    assert_eq!(returned_event.attributes[2].value, TOKEN_ID.to_string());
+    assert_eq!(returned_event.attributes[3].value, creator.to_string());
 
     // Validate Ask is removed
     let query_ask = QueryMsg::Ask {
@@ -383,6 +696,14 @@ fn try_add_update_remove_ask() {
         token_id: token_id.clone(),
         price: coin(sale_amount, NATIVE_DENOM),
         funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
     };
     let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
     assert!(res.is_ok());
@@ -434,6 +755,16 @@ fn try_ask_queries() {
         trading_fee_percent: Decimal::percent(TRADING_FEE_BPS),
         operators: vec![Addr::unchecked("operator")],
         min_price: Uint128::from(5u128),
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: false,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: false,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        cw20_payments: false,
+        accepted_denoms: vec![],
+        halted: false,
+        referral_fee_share: None,
     }, res.config);
 
     // Mint NFT for creator
@@ -465,6 +796,14 @@ fn try_ask_queries() {
             price: coin(100 + n, NATIVE_DENOM),
             seller: creator.clone(),
             funds_recipient: None,
+            external_id: None,
+            min_offer: None,
+            pre_authorized: None,
+            min_net_proceeds: None,
+            hidden_min: None,
+            on_remove_msg: None,
+            reserve_for: None,
+            non_custodial: false,
         }, res.asks[(n as usize) - 3]);
     }
 
@@ -489,6 +828,14 @@ fn try_ask_queries() {
             price: coin(100 + n, NATIVE_DENOM),
             seller: creator.clone(),
             funds_recipient: None,
+            external_id: None,
+            min_offer: None,
+            pre_authorized: None,
+            min_net_proceeds: None,
+            hidden_min: None,
+            on_remove_msg: None,
+            reserve_for: None,
+            non_custodial: false,
         }, res.asks[(n as usize) - 1]);
     }
 
@@ -519,6 +866,8 @@ fn try_set_bid() {
     let set_bid = ExecuteMsg::SetBid {
         token_id: n.to_string(),
         price: coin_send.clone(),
+        refund_to: None,
+        referrer: None,
     };
     let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &[coin_send.clone()]).unwrap();
 
@@ -558,8 +907,31 @@ fn try_set_bid() {
         value: String::from("creator")
     });
 
+    let set_bid_event = res.events.iter().find(|e| e.ty == "wasm-set-bid").unwrap();
+    assert!(set_bid_event.attributes.contains(&Attribute {
+        key: String::from("matched"),
+        value: String::from("true")
+    }));
+    assert!(set_bid_event.attributes.contains(&Attribute {
+        key: String::from("sale_price"),
+        value: String::from("100ujunox")
+    }));
+
     let n = 2;
-    bid(&mut router, &bidder, &marketplace, n.to_string(), 100 + n);
+    let coin_send = coin(100 + n, NATIVE_DENOM);
+    let set_bid = ExecuteMsg::SetBid {
+        token_id: n.to_string(),
+        price: coin_send.clone(),
+        refund_to: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &[coin_send]).unwrap();
+
+    let set_bid_event = res.events.iter().find(|e| e.ty == "wasm-set-bid").unwrap();
+    assert!(set_bid_event.attributes.contains(&Attribute {
+        key: String::from("matched"),
+        value: String::from("false")
+    }));
 
     let query_bid_msg = QueryMsg::Bid {
         token_id: n.to_string(),
@@ -573,6 +945,7 @@ fn try_set_bid() {
         token_id: n.to_string(),
         bidder: bidder.clone(),
         price: coin(100 + n, NATIVE_DENOM),
+        refund_to: None,
     }), res.bid);
 
     // Remove bid
@@ -625,6 +998,7 @@ fn try_bid_queries() {
         token_id: String::from("3"),
         price: coin(103, NATIVE_DENOM),
         bidder: bidder.clone(),
+        refund_to: None,
     }, res.bids[0]);
 
     let query_bids = QueryMsg::BidsByBidder {
@@ -648,6 +1022,7 @@ fn try_bid_queries() {
             token_id: idx.to_string(),
             price: coin(100 + (idx as u128), NATIVE_DENOM),
             bidder: bidder.clone(),
+            refund_to: None,
         }, res.bids[n - 1]);
     }
 }
@@ -666,6 +1041,10 @@ fn try_collection_bid_flow() {
     let set_collection_bid = ExecuteMsg::SetCollectionBid {
         units: 0,
         price: collection_bid_price.clone(),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
     };
     let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_collection_bid, &[collection_bid_price.clone()]);
     assert!(res.is_err());
@@ -674,6 +1053,10 @@ fn try_collection_bid_flow() {
     let set_collection_bid = ExecuteMsg::SetCollectionBid {
         units: 1,
         price: collection_bid_price.clone(),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
     };
     let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_collection_bid, &[collection_bid_price.clone()]);
     assert!(res.is_ok());
@@ -689,6 +1072,10 @@ fn try_collection_bid_flow() {
         units: 1,
         bidder: bidder.clone(),
         price: collection_bid_price.clone(),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
     }), res.collection_bid);
 
     let remove_collection_bid = ExecuteMsg::RemoveCollectionBid { };
@@ -708,6 +1095,10 @@ fn try_collection_bid_flow() {
     let set_collection_bid = ExecuteMsg::SetCollectionBid {
         units: 2,
         price: collection_bid_price.clone(),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
     };
     let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_collection_bid, &[
         coin(collection_bid_price.amount.u128() * 2u128, NATIVE_DENOM)
@@ -721,7 +1112,8 @@ fn try_collection_bid_flow() {
 
     let accept_collection_bid = ExecuteMsg::AcceptCollectionBid {
         token_id: token_id.clone(),
-        bidder: bidder.to_string()
+        bidder: bidder.to_string(),
+        min_price: None,
     };
     let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_collection_bid, &[]);
     assert!(res.is_ok());
@@ -742,6 +1134,10 @@ fn try_collection_bid_flow() {
         units: 1,
         bidder: bidder.clone(),
         price: collection_bid_price.clone(),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
     });
 
     // Sell to collection bid with Ask
@@ -752,7 +1148,8 @@ fn try_collection_bid_flow() {
 
     let accept_collection_bid = ExecuteMsg::AcceptCollectionBid {
         token_id: token_id.clone(),
-        bidder: bidder.to_string()
+        bidder: bidder.to_string(),
+        min_price: None,
     };
     let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_collection_bid, &[]);
     assert!(res.is_ok());
@@ -786,6 +1183,10 @@ fn test_refund_collection_bid() {
     let set_collection_bid = ExecuteMsg::SetCollectionBid {
         units: ten_units.clone(),
         price: collection_bid_price.clone().clone(),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
     };
     let sent_coin = coin(
         collection_bid_price.clone().amount.u128() * ten_units as u128,
@@ -805,6 +1206,10 @@ fn test_refund_collection_bid() {
     let set_collection_bid = ExecuteMsg::SetCollectionBid {
         units: one_unit.clone(),
         price: collection_bid_price.clone(),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
     };
     let sent_coin = coin(
         collection_bid_price.clone().amount.u128() * one_unit as u128,
@@ -833,4 +1238,4814 @@ fn test_refund_collection_bid() {
             collection_bid_price.amount.u128() * ten_units as u128
         )
     );
-}
\ No newline at end of file
+}
+#[test]
+fn try_accept_bid_nft_not_escrowed() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Bid below the asking price so the ask is stored, not matched
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 100);
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 200);
+
+    // Move the NFT out of escrow behind the marketplace's back
+    let transfer_nft = Cw721ExecuteMsg::<Empty>::TransferNft {
+        recipient: owner.to_string(),
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res = router.execute_contract(marketplace.clone(), collection.clone(), &transfer_nft, &[]);
+    assert!(res.is_ok());
+
+    // Accepting the bid fails cleanly and leaves the bid and ask in place
+    let accept_bid = ExecuteMsg::AcceptBid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.to_string(),
+        credit_bps: None,
+        min_price: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_bid, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::NftNotEscrowed(TOKEN_ID.to_string()).to_string(),
+    );
+
+    let query_bid_msg = QueryMsg::Bid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.to_string(),
+    };
+    let res: BidResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_bid_msg)
+        .unwrap();
+    assert!(res.bid.is_some());
+
+    let query_ask = QueryMsg::Ask {
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res: AskResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_ask)
+        .unwrap();
+    assert!(res.ask.is_some());
+}
+
+#[test]
+fn try_update_collector_address() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+    let collector = Addr::unchecked("collector");
+
+    // Only operators can update the collector
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: Some(collector.to_string()),
+        trading_fee_bps: None,
+        operators: None,
+        min_price: None,
+        denom: None,
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: None,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: None,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        accepted_denoms: None,
+        referral_fee_share: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_err());
+
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    let res: ConfigResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Config {})
+        .unwrap();
+    assert_eq!(res.config.collector_address, collector);
+
+    // Fees from subsequent sales are routed to the new collector
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 100);
+
+    let collector_balances = router.wrap().query_all_balances(collector.clone()).unwrap();
+    assert_eq!(collector_balances, coins(2, NATIVE_DENOM));
+}
+
+#[test]
+fn try_update_denom() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, _collection) = setup_contracts(&mut router, &creator).unwrap();
+    let operator = Addr::unchecked("operator");
+
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: None,
+        min_price: None,
+        denom: Some(String::from("upasg")),
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: None,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: None,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        accepted_denoms: None,
+        referral_fee_share: None,
+    };
+
+    // Cannot change the denom while a bid is escrowed
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 100);
+    let res = router.execute_contract(operator.clone(), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_err());
+
+    // Cannot change the denom while a collection bid is escrowed
+    let remove_bid = ExecuteMsg::RemoveBid {
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &remove_bid, &[]);
+    assert!(res.is_ok());
+
+    let collection_bid_price = coin(100u128, NATIVE_DENOM);
+    let set_collection_bid = ExecuteMsg::SetCollectionBid {
+        units: 1,
+        price: collection_bid_price.clone(),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_collection_bid, &[collection_bid_price]);
+    assert!(res.is_ok());
+
+    let res = router.execute_contract(operator.clone(), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_err());
+
+    // Can change the denom once escrow is empty
+    let remove_collection_bid = ExecuteMsg::RemoveCollectionBid { };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &remove_collection_bid, &[]);
+    assert!(res.is_ok());
+
+    let res = router.execute_contract(operator.clone(), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    let res: ConfigResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Config {})
+        .unwrap();
+    assert_eq!(res.config.denom, String::from("upasg"));
+}
+
+#[test]
+fn try_all_or_nothing_collection_bid() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Place an all-or-nothing collection bid for 2 units
+    let collection_bid_price = coin(100u128, NATIVE_DENOM);
+    let set_collection_bid = ExecuteMsg::SetCollectionBid {
+        units: 2,
+        price: collection_bid_price.clone(),
+        all_or_nothing: true,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_collection_bid, &[
+        coin(collection_bid_price.amount.u128() * 2u128, NATIVE_DENOM)
+    ]);
+    assert!(res.is_ok());
+
+    let token_ids = vec![String::from("1"), String::from("2")];
+    for token_id in &token_ids {
+        mint(&mut router, &creator, &collection, token_id.clone());
+        approve(&mut router, &creator, &collection, &marketplace, token_id.clone());
+    }
+
+    // A partial fill is rejected
+    let accept_collection_bid = ExecuteMsg::AcceptCollectionBid {
+        token_id: token_ids[0].clone(),
+        bidder: bidder.to_string(),
+        min_price: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_collection_bid, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::PartialFill(2, 1).to_string(),
+    );
+
+    // The same token cannot be used to fill two units
+    let accept_collection_bid_bulk = ExecuteMsg::AcceptCollectionBidBulk {
+        token_ids: vec![token_ids[0].clone(), token_ids[0].clone()],
+        bidder: bidder.to_string()
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_collection_bid_bulk, &[]);
+    assert!(res.is_err());
+
+    // Filling all units at once succeeds
+    let accept_collection_bid_bulk = ExecuteMsg::AcceptCollectionBidBulk {
+        token_ids: token_ids.clone(),
+        bidder: bidder.to_string()
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_collection_bid_bulk, &[]);
+    assert!(res.is_ok());
+
+    for token_id in &token_ids {
+        let query_owner_msg = Cw721QueryMsg::OwnerOf {
+            token_id: token_id.clone(),
+            include_expired: None,
+        };
+        let res: OwnerOfResponse = router
+            .wrap()
+            .query_wasm_smart(collection.clone(), &query_owner_msg)
+            .unwrap();
+        assert_eq!(res.owner, bidder.to_string());
+    }
+
+    let query_collection_bid_msg = QueryMsg::CollectionBid {
+        bidder: bidder.to_string(),
+    };
+    let res: CollectionBidResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_collection_bid_msg)
+        .unwrap();
+    assert_eq!(None, res.collection_bid);
+}
+
+#[test]
+fn try_bid_leaderboard() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+    let bidder3 = Addr::unchecked("bidder3");
+    router
+        .sudo(CwSudoMsg::Bank({
+            BankSudo::Mint {
+                to_address: bidder3.to_string(),
+                amount: coins(INITIAL_BALANCE, NATIVE_DENOM),
+            }
+        }))
+        .unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, _collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Seed four bids on the same token
+    bid(&mut router, &owner, &marketplace, TOKEN_ID.to_string(), 110);
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 140);
+    bid(&mut router, &bidder2, &marketplace, TOKEN_ID.to_string(), 120);
+    bid(&mut router, &bidder3, &marketplace, TOKEN_ID.to_string(), 130);
+
+    let query_leaderboard = QueryMsg::BidLeaderboard {
+        token_id: TOKEN_ID.to_string(),
+        top_n: 2,
+    };
+    let res: BidsResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_leaderboard)
+        .unwrap();
+    assert_eq!(res.bids, vec![
+        Bid {
+            token_id: TOKEN_ID.to_string(),
+            bidder: bidder.clone(),
+            price: coin(140, NATIVE_DENOM),
+            refund_to: None,
+        },
+        Bid {
+            token_id: TOKEN_ID.to_string(),
+            bidder: bidder3.clone(),
+            price: coin(130, NATIVE_DENOM),
+            refund_to: None,
+        },
+    ]);
+}
+
+#[test]
+fn try_is_matchable() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    let query_is_matchable = QueryMsg::IsMatchable {
+        token_id: TOKEN_ID.to_string(),
+    };
+
+    // Not matchable without an ask
+    let res: IsMatchableResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_is_matchable)
+        .unwrap();
+    assert!(!res.is_matchable);
+
+    // Matchable once listed
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+    let res: IsMatchableResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_is_matchable)
+        .unwrap();
+    assert!(res.is_matchable);
+
+    // Not matchable once the ask is removed
+    let remove_ask = ExecuteMsg::RemoveAsk {
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &remove_ask, &[]);
+    assert!(res.is_ok());
+    let res: IsMatchableResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_is_matchable)
+        .unwrap();
+    assert!(!res.is_matchable);
+
+    let set_ask = |reserve_for: Option<String>, non_custodial: bool| ExecuteMsg::SetAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(100, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for,
+        non_custodial,
+    };
+
+    // Not matchable while reserved for one buyer
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask(Some(bidder.to_string()), false), &[]);
+    assert!(res.is_ok());
+    let res: IsMatchableResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_is_matchable)
+        .unwrap();
+    assert!(!res.is_matchable);
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &remove_ask, &[]);
+    assert!(res.is_ok());
+
+    // Not matchable while trading is halted
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &ExecuteMsg::SetHalted { halted: true }, &[]);
+    assert!(res.is_ok());
+    let res: IsMatchableResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_is_matchable)
+        .unwrap();
+    assert!(!res.is_matchable);
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &ExecuteMsg::SetHalted { halted: false }, &[]);
+    assert!(res.is_ok());
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &remove_ask, &[]);
+    assert!(res.is_ok());
+
+    // Not matchable once a non-custodial ask can no longer deliver the NFT
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask(None, true), &[]);
+    assert!(res.is_ok());
+    let res: IsMatchableResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_is_matchable)
+        .unwrap();
+    assert!(res.is_matchable);
+    let revoke = Cw721ExecuteMsg::<Empty>::Revoke {
+        spender: marketplace.to_string(),
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res = router.execute_contract(creator.clone(), collection.clone(), &revoke, &[]);
+    assert!(res.is_ok());
+    let res: IsMatchableResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_is_matchable)
+        .unwrap();
+    assert!(!res.is_matchable);
+}
+
+#[test]
+fn try_create_ask() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, _bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+
+    // The first CreateAsk succeeds
+    let create_ask = ExecuteMsg::CreateAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(110, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &create_ask, &[]);
+    assert!(res.is_ok());
+
+    // A second CreateAsk for the same token fails
+    let create_ask = ExecuteMsg::CreateAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(120, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &create_ask, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::AskAlreadyExists(TOKEN_ID.to_string()).to_string(),
+    );
+
+    // SetAsk still upserts
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 120);
+    let query_ask = QueryMsg::Ask {
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res: AskResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_ask)
+        .unwrap();
+    assert_eq!(res.ask.unwrap().price, coin(120, NATIVE_DENOM));
+}
+
+#[test]
+fn try_min_bid_fraction_of_ask() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Require bids on listed NFTs to be at least half of the ask price
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: None,
+        min_price: None,
+        denom: None,
+        min_bid_fraction_of_ask: Some(Decimal::percent(50)),
+        accumulate_fees: None,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: None,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        accepted_denoms: None,
+        referral_fee_share: None,
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+
+    // A lowball bid is rejected
+    let set_bid = ExecuteMsg::SetBid {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(40, NATIVE_DENOM),
+        refund_to: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &[coin(40, NATIVE_DENOM)]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::BidTooLow(Uint128::from(50u128), Uint128::from(40u128)).to_string(),
+    );
+
+    // A reasonable bid below the ask is stored
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 60);
+    let query_bid = QueryMsg::Bid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.to_string(),
+    };
+    let res: BidResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_bid)
+        .unwrap();
+    assert_eq!(res.bid.unwrap().price, coin(60, NATIVE_DENOM));
+
+    // A bid meeting the ask still matches
+    bid(&mut router, &bidder2, &marketplace, TOKEN_ID.to_string(), 100);
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder2.to_string());
+}
+
+#[test]
+fn try_collection_bid_only_below_last_sale() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Sell the token to bidder for 100
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 100);
+
+    // A collection bid below the last sale price cannot be accepted
+    let collection_bid_price = coin(80u128, NATIVE_DENOM);
+    let set_collection_bid = ExecuteMsg::SetCollectionBid {
+        units: 1,
+        price: collection_bid_price.clone(),
+        all_or_nothing: false,
+        only_below_last_sale: true,
+        traits: vec![],
+        budget: None,
+    };
+    let res = router.execute_contract(bidder2.clone(), marketplace.clone(), &set_collection_bid, &[collection_bid_price]);
+    assert!(res.is_ok());
+
+    approve(&mut router, &bidder, &collection, &marketplace, TOKEN_ID.to_string());
+    let accept_collection_bid = ExecuteMsg::AcceptCollectionBid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder2.to_string(),
+        min_price: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &accept_collection_bid, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::LastSaleNotBelowBid(TOKEN_ID.to_string()).to_string(),
+    );
+
+    // A collection bid above the last sale price can be accepted
+    let collection_bid_price = coin(120u128, NATIVE_DENOM);
+    let set_collection_bid = ExecuteMsg::SetCollectionBid {
+        units: 1,
+        price: collection_bid_price.clone(),
+        all_or_nothing: false,
+        only_below_last_sale: true,
+        traits: vec![],
+        budget: None,
+    };
+    let res = router.execute_contract(bidder2.clone(), marketplace.clone(), &set_collection_bid, &[collection_bid_price]);
+    assert!(res.is_ok());
+
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &accept_collection_bid, &[]);
+    assert!(res.is_ok());
+
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder2.to_string());
+}
+
+#[test]
+fn try_collection_bid_traits() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate a collection with onchain metadata and a marketplace for it
+    let collection_id = router.store_code(contract_pg721_metadata_onchain());
+    let msg = Pg721InstantiateMsg {
+        name: String::from("Test Coin"),
+        symbol: String::from("TEST"),
+        minter: creator.to_string(),
+        collection_info: CollectionInfo {
+            creator: creator.to_string(),
+            description: String::from("Passage Monkeys"),
+            image:
+                "ipfs://bafybeigi3bwpvyvsmnbj46ra4hyffcxdeaj6ntfk5jpic5mx27x6ih2qvq/images/1.png"
+                    .to_string(),
+            external_link: None,
+            royalty_info: None,
+        },
+    };
+    let collection = router
+        .instantiate_contract(collection_id, creator.clone(), &msg, &[], "NFT", None)
+        .unwrap();
+
+    let marketplace_id = router.store_code(contract_marketplace());
+    let msg = crate::msg::InstantiateMsg {
+        cw721_address: collection.to_string(),
+        denom: String::from(NATIVE_DENOM),
+        collector_address: creator.to_string(),
+        trading_fee_bps: TRADING_FEE_BPS,
+        operators: vec!["operator".to_string()],
+        include_sender_as_operator: false,
+        min_price: Uint128::from(5u128),
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: false,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: false,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        cw20_payments: false,
+        accepted_denoms: vec![],
+        referral_fee_share: None,
+    };
+    let marketplace = router
+        .instantiate_contract(marketplace_id, creator.clone(), &msg, &[], "Marketplace", None)
+        .unwrap();
+
+    for (token_id, background) in vec![("1", "Silver"), ("2", "Gold")] {
+        let mint_msg = Pg721MetadataExecuteMsg::Mint(MintMsg {
+            token_id: token_id.to_string(),
+            owner: creator.to_string(),
+            token_uri: None,
+            extension: Some(Metadata {
+                attributes: Some(vec![Trait {
+                    display_type: None,
+                    trait_type: "Background".to_string(),
+                    value: background.to_string(),
+                }]),
+                ..Metadata::default()
+            }),
+        });
+        let res = router.execute_contract(creator.clone(), collection.clone(), &mint_msg, &[]);
+        assert!(res.is_ok());
+        approve(&mut router, &creator, &collection, &marketplace, token_id.to_string());
+    }
+
+    let collection_bid_price = coin(100u128, NATIVE_DENOM);
+    let set_collection_bid = ExecuteMsg::SetCollectionBid {
+        units: 1,
+        price: collection_bid_price.clone(),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![TraitFilter {
+            trait_type: "Background".to_string(),
+            value: "Gold".to_string(),
+        }],
+        budget: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_collection_bid, &[collection_bid_price]);
+    assert!(res.is_ok());
+
+    // A token without the trait cannot fill the bid
+    let accept_collection_bid = ExecuteMsg::AcceptCollectionBid {
+        token_id: "1".to_string(),
+        bidder: bidder.to_string(),
+        min_price: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_collection_bid, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::TraitMismatch("1".to_string()).to_string(),
+    );
+
+    // A token with the trait fills the bid
+    let accept_collection_bid = ExecuteMsg::AcceptCollectionBid {
+        token_id: "2".to_string(),
+        bidder: bidder.to_string(),
+        min_price: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_collection_bid, &[]);
+    assert!(res.is_ok());
+
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: "2".to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder.to_string());
+
+    // A SetCollectionBid from a client unaware of the newer fields bids on any token
+    let set_collection_bid: ExecuteMsg = from_slice(
+        br#"{"set_collection_bid":{"units":1,"price":{"denom":"ujunox","amount":"50"}}}"#
+    ).unwrap();
+    let res = router.execute_contract(bidder2.clone(), marketplace.clone(), &set_collection_bid, &coins(50, NATIVE_DENOM));
+    assert!(res.is_ok());
+    let res: CollectionBidResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::CollectionBid { bidder: bidder2.to_string() })
+        .unwrap();
+    let collection_bid = res.collection_bid.unwrap();
+    assert!(!collection_bid.all_or_nothing);
+    assert!(!collection_bid.only_below_last_sale);
+    assert!(collection_bid.traits.is_empty());
+}
+
+#[test]
+fn try_collection_bid_budget() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    for token_id in vec!["1", "2", "3"] {
+        mint(&mut router, &creator, &collection, token_id.to_string());
+        approve(&mut router, &creator, &collection, &marketplace, token_id.to_string());
+    }
+
+    // The budget cannot exceed price * units
+    let set_collection_bid = ExecuteMsg::SetCollectionBid {
+        units: 3,
+        price: coin(100u128, NATIVE_DENOM),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: Some(Uint128::from(301u128)),
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_collection_bid, &coins(301, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidCollectionBid {}.to_string(),
+    );
+
+    // Escrows the budget rather than price * units
+    let set_collection_bid = ExecuteMsg::SetCollectionBid {
+        units: 3,
+        price: coin(100u128, NATIVE_DENOM),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: Some(Uint128::from(150u128)),
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_collection_bid, &coins(150, NATIVE_DENOM));
+    assert!(res.is_ok());
+
+    // Units cannot be changed on a budget bid
+    let reduce_collection_bid = ExecuteMsg::ReduceCollectionBid { new_units: 2 };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &reduce_collection_bid, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidCollectionBid {}.to_string(),
+    );
+
+    // The first fill pays the max price
+    let accept_collection_bid = ExecuteMsg::AcceptCollectionBid {
+        token_id: "1".to_string(),
+        bidder: bidder.to_string(),
+        min_price: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_collection_bid, &[]);
+    assert!(res.is_ok());
+    let res: CollectionBidResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::CollectionBid { bidder: bidder.to_string() })
+        .unwrap();
+    let collection_bid = res.collection_bid.unwrap();
+    assert_eq!(collection_bid.units, 2);
+    assert_eq!(collection_bid.budget, Some(Uint128::from(50u128)));
+
+    // The second fill pays what is left of the budget, which removes the bid
+    let accept_collection_bid = ExecuteMsg::AcceptCollectionBid {
+        token_id: "2".to_string(),
+        bidder: bidder.to_string(),
+        min_price: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_collection_bid, &[]);
+    assert!(res.is_ok());
+    let event = res.unwrap().events.into_iter().find(|e| e.ty == "wasm-accept-collection-bid").unwrap();
+    assert!(event.attributes.contains(&Attribute::new("price", coin(50u128, NATIVE_DENOM).to_string())));
+
+    let res: CollectionBidResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::CollectionBid { bidder: bidder.to_string() })
+        .unwrap();
+    assert_eq!(res.collection_bid, None);
+
+    let bidder_balance = router.wrap().query_balance(bidder.to_string(), NATIVE_DENOM).unwrap();
+    assert_eq!(bidder_balance.amount, Uint128::from(INITIAL_BALANCE - 150));
+    // The creator is seller, royalty recipient and fee collector
+    let creator_balance = router.wrap().query_balance(creator.to_string(), NATIVE_DENOM).unwrap();
+    assert_eq!(creator_balance.amount, Uint128::from(150u128));
+}
+
+#[test]
+fn try_collection_bid_matches_new_ask() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    for token_id in vec!["1", "2"] {
+        mint(&mut router, &creator, &collection, token_id.to_string());
+        approve(&mut router, &creator, &collection, &marketplace, token_id.to_string());
+    }
+
+    let set_collection_bid = ExecuteMsg::SetCollectionBid {
+        units: 2,
+        price: coin(100u128, NATIVE_DENOM),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_collection_bid, &coins(200, NATIVE_DENOM));
+    assert!(res.is_ok());
+
+    // A higher trait bid is only filled through AcceptCollectionBid, never by a new ask
+    let set_trait_collection_bid = ExecuteMsg::SetCollectionBid {
+        units: 1,
+        price: coin(120u128, NATIVE_DENOM),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![TraitFilter {
+            trait_type: "Background".to_string(),
+            value: "Gold".to_string(),
+        }],
+        budget: None,
+    };
+    let res = router.execute_contract(bidder2.clone(), marketplace.clone(), &set_trait_collection_bid, &coins(120, NATIVE_DENOM));
+    assert!(res.is_ok());
+
+    // An ask above the collection bid is listed
+    ask(&mut router, &creator, &marketplace, "1".to_string(), 150);
+    let res: AskResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Ask { token_id: "1".to_string() })
+        .unwrap();
+    assert!(res.ask.is_some());
+
+    // An ask at or below the collection bid fills it at the bid price
+    let set_ask = ExecuteMsg::SetAsk {
+        token_id: "2".to_string(),
+        price: coin(90, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
+    assert!(res.is_ok());
+    let event = res.unwrap().events.into_iter().find(|e| e.ty == "wasm-match-collection-bid").unwrap();
+    assert!(event.attributes.contains(&Attribute::new("bidder", bidder.to_string())));
+
+    let res: AskResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Ask { token_id: "2".to_string() })
+        .unwrap();
+    assert_eq!(res.ask, None);
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: "2".to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder.to_string());
+
+    let res: CollectionBidResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::CollectionBid { bidder: bidder.to_string() })
+        .unwrap();
+    assert_eq!(res.collection_bid.unwrap().units, 1);
+
+    // The creator is seller, royalty recipient and fee collector
+    let creator_balance = router.wrap().query_balance(creator.to_string(), NATIVE_DENOM).unwrap();
+    assert_eq!(creator_balance.amount, Uint128::from(100u128));
+}
+
+#[test]
+fn try_reserve_for() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    let set_ask = ExecuteMsg::SetAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(100, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: Some(bidder.to_string()),
+        non_custodial: false,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
+    assert!(res.is_ok());
+
+    // Other bidders cannot settle the ask with a bid or a purchase
+    let set_bid = ExecuteMsg::SetBid {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(100, NATIVE_DENOM),
+        refund_to: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder2.clone(), marketplace.clone(), &set_bid, &coins(100, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::ReservedAsk(TOKEN_ID.to_string()).to_string()
+    );
+    let buy_now = ExecuteMsg::BuyNow {
+        token_id: TOKEN_ID.to_string(),
+        expected_price: coin(100, NATIVE_DENOM),
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder2.clone(), marketplace.clone(), &buy_now, &coins(100, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::ReservedAsk(TOKEN_ID.to_string()).to_string()
+    );
+
+    // A lower offer from another bidder is still stored
+    bid(&mut router, &bidder2, &marketplace, TOKEN_ID.to_string(), 50);
+
+    // The reserved buyer's bid settles the ask as a private sale
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &coins(100, NATIVE_DENOM));
+    assert!(res.is_ok());
+    let event = res.unwrap().events.into_iter().find(|e| e.ty == "wasm-set-bid").unwrap();
+    assert!(event.attributes.contains(&Attribute::new("private_sale", "true")));
+
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder.to_string());
+}
+
+#[test]
+fn try_update_ask() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+
+    // Only the seller can update the ask
+    let update_ask = ExecuteMsg::UpdateAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(80, NATIVE_DENOM),
+        reserve_for: Some(bidder.to_string()),
+        clear_reserve_for: false,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &update_ask, &[]);
+    assert!(res.is_err());
+
+    // The price is validated
+    let invalid_update_ask = ExecuteMsg::UpdateAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(1, NATIVE_DENOM),
+        reserve_for: None,
+        clear_reserve_for: false,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &invalid_update_ask, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidPrice {}.to_string()
+    );
+
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &update_ask, &[]);
+    assert!(res.is_ok());
+    let event = res.unwrap().events.into_iter().find(|e| e.ty == "wasm-update-ask").unwrap();
+    assert!(event.attributes.contains(&Attribute::new("old_price", coin(100, NATIVE_DENOM).to_string())));
+
+    let res: AskResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Ask { token_id: TOKEN_ID.to_string() })
+        .unwrap();
+    let ask = res.ask.unwrap();
+    assert_eq!(ask.price, coin(80, NATIVE_DENOM));
+    assert_eq!(ask.reserve_for, Some(bidder.clone()));
+
+    // The NFT stays in escrow
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, marketplace.to_string());
+
+    // A price-only update keeps the reserved buyer
+    let update_price = ExecuteMsg::UpdateAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(90, NATIVE_DENOM),
+        reserve_for: None,
+        clear_reserve_for: false,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &update_price, &[]);
+    assert!(res.is_ok());
+    let res: AskResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Ask { token_id: TOKEN_ID.to_string() })
+        .unwrap();
+    let ask = res.ask.unwrap();
+    assert_eq!(ask.price, coin(90, NATIVE_DENOM));
+    assert_eq!(ask.reserve_for, Some(bidder.clone()));
+
+    // The reserved buyer is removed explicitly
+    let clear_reserve_for = ExecuteMsg::UpdateAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(90, NATIVE_DENOM),
+        reserve_for: None,
+        clear_reserve_for: true,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &clear_reserve_for, &[]);
+    assert!(res.is_ok());
+    let res: AskResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Ask { token_id: TOKEN_ID.to_string() })
+        .unwrap();
+    assert_eq!(res.ask.unwrap().reserve_for, None);
+}
+
+#[test]
+fn try_receive_nft() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    let set_ask = ExecuteMsg::SetAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(100, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
+    };
+
+    // Only the collection can send NFTs to list
+    let receive_nft = ExecuteMsg::ReceiveNft(cw721::Cw721ReceiveMsg {
+        sender: bidder.to_string(),
+        token_id: TOKEN_ID.to_string(),
+        msg: to_binary(&set_ask).unwrap(),
+    });
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &receive_nft, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidReceivedNft(format!("collection {} is not traded here", bidder)).to_string()
+    );
+
+    // Lists the NFT in a single SendNft, without an approval
+    let send_nft = Cw721ExecuteMsg::<Empty>::SendNft {
+        contract: marketplace.to_string(),
+        token_id: TOKEN_ID.to_string(),
+        msg: to_binary(&set_ask).unwrap(),
+    };
+    let res = router.execute_contract(creator.clone(), collection.clone(), &send_nft, &[]);
+    assert!(res.is_ok());
+
+    let res: AskResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Ask { token_id: TOKEN_ID.to_string() })
+        .unwrap();
+    let ask = res.ask.unwrap();
+    assert_eq!(ask.seller, creator);
+    assert_eq!(ask.price, coin(100, NATIVE_DENOM));
+
+    // The listing sells like any other
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 100);
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder.to_string());
+}
+
+#[test]
+fn try_non_custodial_ask() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    let set_ask = |token_id: &str| ExecuteMsg::SetAsk {
+        token_id: token_id.to_string(),
+        price: coin(100, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: true,
+    };
+    let query_owner = |router: &App, token_id: &str| -> String {
+        let query_owner_msg = Cw721QueryMsg::OwnerOf {
+            token_id: token_id.to_string(),
+            include_expired: None,
+        };
+        let res: OwnerOfResponse = router
+            .wrap()
+            .query_wasm_smart(collection.clone(), &query_owner_msg)
+            .unwrap();
+        res.owner
+    };
+
+    // The marketplace must be approved to list without escrow
+    mint(&mut router, &creator, &collection, "1".to_string());
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask("1"), &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::NftNotDeliverable("1".to_string()).to_string()
+    );
+
+    // The NFT stays with the seller while listed
+    approve(&mut router, &creator, &collection, &marketplace, "1".to_string());
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask("1"), &[]);
+    assert!(res.is_ok());
+
+    // Sending the NFT cannot turn the listing into an escrowed one
+    let mut custodial_ask = set_ask("1");
+    if let ExecuteMsg::SetAsk { non_custodial, .. } = &mut custodial_ask {
+        *non_custodial = false;
+    }
+    let send_nft = Cw721ExecuteMsg::<Empty>::SendNft {
+        contract: marketplace.to_string(),
+        token_id: "1".to_string(),
+        msg: to_binary(&custodial_ask).unwrap(),
+    };
+    let res = router.execute_contract(creator.clone(), collection.clone(), &send_nft, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::CustodyChange("1".to_string()).to_string()
+    );
+    assert_eq!(query_owner(&router, "1"), creator.to_string());
+
+    // The sale transfers it from the seller
+    let buy_now = ExecuteMsg::BuyNow {
+        token_id: "1".to_string(),
+        expected_price: coin(100, NATIVE_DENOM),
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &buy_now, &coins(100, NATIVE_DENOM));
+    assert!(res.is_ok());
+    assert_eq!(query_owner(&router, "1"), bidder.to_string());
+    // The creator is seller, royalty recipient and fee collector
+    let creator_balance = router.wrap().query_balance(creator.to_string(), NATIVE_DENOM).unwrap();
+    assert_eq!(creator_balance.amount, Uint128::from(100u128));
+
+    // An ask whose NFT left the seller cannot be settled
+    mint(&mut router, &creator, &collection, "2".to_string());
+    approve(&mut router, &creator, &collection, &marketplace, "2".to_string());
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask("2"), &[]);
+    assert!(res.is_ok());
+    let transfer_nft = Cw721ExecuteMsg::<Empty>::TransferNft {
+        recipient: bidder2.to_string(),
+        token_id: "2".to_string(),
+    };
+    let res = router.execute_contract(creator.clone(), collection.clone(), &transfer_nft, &[]);
+    assert!(res.is_ok());
+
+    let buy_now = ExecuteMsg::BuyNow {
+        token_id: "2".to_string(),
+        expected_price: coin(100, NATIVE_DENOM),
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &buy_now, &coins(100, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::NftNotDeliverable("2".to_string()).to_string()
+    );
+
+    // Anyone can remove the stale ask
+    let remove_stale_ask = ExecuteMsg::RemoveStaleAsk { token_id: "2".to_string() };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &remove_stale_ask, &[]);
+    assert!(res.is_ok());
+    let res: AskResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Ask { token_id: "2".to_string() })
+        .unwrap();
+    assert_eq!(res.ask, None);
+    assert_eq!(query_owner(&router, "2"), bidder2.to_string());
+
+    // A SetAsk from a client unaware of non_custodial escrows the NFT
+    mint(&mut router, &creator, &collection, "3".to_string());
+    approve(&mut router, &creator, &collection, &marketplace, "3".to_string());
+    let set_ask: ExecuteMsg = from_slice(
+        br#"{"set_ask":{"token_id":"3","price":{"denom":"ujunox","amount":"100"},"funds_recipient":null}}"#
+    ).unwrap();
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
+    assert!(res.is_ok());
+    assert_eq!(query_owner(&router, "3"), marketplace.to_string());
+}
+
+#[test]
+fn try_sweep() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    for (token_id, price) in vec![("1", 300), ("2", 100), ("3", 200)] {
+        mint(&mut router, &creator, &collection, token_id.to_string());
+        approve(&mut router, &creator, &collection, &marketplace, token_id.to_string());
+        ask(&mut router, &creator, &marketplace, token_id.to_string(), price);
+    }
+
+    // The limit is bounded
+    let sweep = ExecuteMsg::Sweep {
+        limit: 0,
+        max_total_price: Uint128::from(450u128),
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &sweep, &coins(450, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidBatchSize(30).to_string()
+    );
+
+    // Buys the two cheapest listings that fit the budget and refunds the rest
+    let sweep = ExecuteMsg::Sweep {
+        limit: 3,
+        max_total_price: Uint128::from(450u128),
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &sweep, &coins(450, NATIVE_DENOM));
+    assert!(res.is_ok());
+    let purchases = res.unwrap().events.into_iter().filter(|e| e.ty == "wasm-sweep-purchase").count();
+    assert_eq!(purchases, 2);
+
+    for (token_id, owner) in vec![("1", &marketplace), ("2", &bidder), ("3", &bidder)] {
+        let query_owner_msg = Cw721QueryMsg::OwnerOf {
+            token_id: token_id.to_string(),
+            include_expired: None,
+        };
+        let res: OwnerOfResponse = router
+            .wrap()
+            .query_wasm_smart(collection.clone(), &query_owner_msg)
+            .unwrap();
+        assert_eq!(res.owner, owner.to_string());
+    }
+    let bidder_balance = router.wrap().query_balance(bidder.to_string(), NATIVE_DENOM).unwrap();
+    assert_eq!(bidder_balance.amount, Uint128::from(INITIAL_BALANCE - 300));
+
+    // Fails when nothing fits the budget
+    let sweep = ExecuteMsg::Sweep {
+        limit: 3,
+        max_total_price: Uint128::from(100u128),
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &sweep, &coins(100, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::EmptySweep {}.to_string()
+    );
+}
+
+#[test]
+fn try_batch_asks_and_bids() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    let token_ids = vec!["1".to_string(), "2".to_string()];
+    for token_id in &token_ids {
+        mint(&mut router, &creator, &collection, token_id.clone());
+        approve(&mut router, &creator, &collection, &marketplace, token_id.clone());
+    }
+
+    // Empty batches are rejected
+    let set_asks = ExecuteMsg::SetAsks { asks: vec![] };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_asks, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidBatchSize(30).to_string()
+    );
+
+    let set_asks = ExecuteMsg::SetAsks {
+        asks: token_ids.iter().map(|token_id| AskInput {
+            token_id: token_id.clone(),
+            price: coin(100, NATIVE_DENOM),
+            funds_recipient: None,
+            external_id: None,
+            min_offer: None,
+            pre_authorized: None,
+            min_net_proceeds: None,
+            hidden_min: None,
+            on_remove_msg: None,
+            reserve_for: None,
+            non_custodial: false,
+        }).collect(),
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_asks, &[]);
+    assert!(res.is_ok());
+    let res: AskCountResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::AskCount {})
+        .unwrap();
+    assert_eq!(res.count, 2);
+
+    for token_id in &token_ids {
+        bid(&mut router, &bidder, &marketplace, token_id.clone(), 50);
+    }
+    let bidder_balance = router.wrap().query_balance(bidder.to_string(), NATIVE_DENOM).unwrap();
+    assert_eq!(bidder_balance.amount, Uint128::from(INITIAL_BALANCE - 100));
+
+    // Removes both bids, refunding them
+    let remove_bids = ExecuteMsg::RemoveBids { token_ids: token_ids.clone() };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &remove_bids, &[]);
+    assert!(res.is_ok());
+    let bidder_balance = router.wrap().query_balance(bidder.to_string(), NATIVE_DENOM).unwrap();
+    assert_eq!(bidder_balance.amount, Uint128::from(INITIAL_BALANCE));
+
+    // Removes both asks, returning the NFTs
+    let remove_asks = ExecuteMsg::RemoveAsks { token_ids: token_ids.clone() };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &remove_asks, &[]);
+    assert!(res.is_ok());
+    for token_id in &token_ids {
+        let query_owner_msg = Cw721QueryMsg::OwnerOf {
+            token_id: token_id.clone(),
+            include_expired: None,
+        };
+        let res: OwnerOfResponse = router
+            .wrap()
+            .query_wasm_smart(collection.clone(), &query_owner_msg)
+            .unwrap();
+        assert_eq!(res.owner, creator.to_string());
+    }
+}
+
+#[test]
+fn try_accept_bid_min_price() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    for token_id in vec!["1", "2"] {
+        mint(&mut router, &creator, &collection, token_id.to_string());
+        approve(&mut router, &creator, &collection, &marketplace, token_id.to_string());
+    }
+    bid(&mut router, &bidder, &marketplace, "1".to_string(), 100);
+
+    // A bid below the seller's minimum is not accepted
+    let accept_bid = ExecuteMsg::AcceptBid {
+        token_id: "1".to_string(),
+        bidder: bidder.to_string(),
+        credit_bps: None,
+        min_price: Some(Uint128::from(120u128)),
+        referrer: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_bid, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::PriceMismatch(coin(120, NATIVE_DENOM), coin(100, NATIVE_DENOM)).to_string()
+    );
+
+    let accept_bid = ExecuteMsg::AcceptBid {
+        token_id: "1".to_string(),
+        bidder: bidder.to_string(),
+        credit_bps: None,
+        min_price: Some(Uint128::from(100u128)),
+        referrer: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_bid, &[]);
+    assert!(res.is_ok());
+
+    // The same guard applies to collection bids
+    let set_collection_bid = ExecuteMsg::SetCollectionBid {
+        units: 1,
+        price: coin(50u128, NATIVE_DENOM),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
+    };
+    let res = router.execute_contract(bidder2.clone(), marketplace.clone(), &set_collection_bid, &coins(50, NATIVE_DENOM));
+    assert!(res.is_ok());
+
+    let accept_collection_bid = ExecuteMsg::AcceptCollectionBid {
+        token_id: "2".to_string(),
+        bidder: bidder2.to_string(),
+        min_price: Some(Uint128::from(60u128)),
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_collection_bid, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::PriceMismatch(coin(60, NATIVE_DENOM), coin(50, NATIVE_DENOM)).to_string()
+    );
+}
+
+#[test]
+fn try_hooks() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+
+    // Only an operator can register hooks
+    let add_hook = ExecuteMsg::AddHook {
+        hook: collection.to_string(),
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &add_hook, &[]);
+    assert!(res.is_err());
+
+    // The collection does not understand hook messages, so it stands in for a failing hook
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &add_hook, &[]);
+    assert!(res.is_ok());
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &add_hook, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidHook(format!("{} is already registered", collection)).to_string()
+    );
+
+    let res: HooksResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Hooks {})
+        .unwrap();
+    assert_eq!(res.hooks, vec![collection.clone()]);
+
+    // A failing hook does not revert the bid that fired it
+    let set_bid = ExecuteMsg::SetBid {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(100, NATIVE_DENOM),
+        refund_to: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &coins(100, NATIVE_DENOM));
+    assert!(res.is_ok());
+    assert!(res.unwrap().events.iter().any(|e| e.ty == "wasm-hook-failed"));
+
+    let res: BidResponse = router
+        .wrap()
+        .query_wasm_smart(
+            marketplace.clone(),
+            &QueryMsg::Bid {
+                token_id: TOKEN_ID.to_string(),
+                bidder: bidder.to_string(),
+            },
+        )
+        .unwrap();
+    assert!(res.bid.is_some());
+
+    let remove_hook = ExecuteMsg::RemoveHook {
+        hook: collection.to_string(),
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &remove_hook, &[]);
+    assert!(res.is_ok());
+
+    let res: HooksResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Hooks {})
+        .unwrap();
+    assert!(res.hooks.is_empty());
+}
+
+#[test]
+fn try_sudo() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, _bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, _collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Governance updates the fee parameters without an operator key
+    let update_params = SudoMsg::UpdateParams {
+        collector_address: Some(creator.to_string()),
+        trading_fee_bps: Some(300),
+        min_price: Some(Uint128::from(10u128)),
+        denom: None,
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: None,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: None,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        accepted_denoms: None,
+        referral_fee_share: None,
+    };
+    let res = router.wasm_sudo(marketplace.clone(), &update_params);
+    assert!(res.is_ok());
+
+    let res: ConfigResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Config {})
+        .unwrap();
+    assert_eq!(res.config.collector_address, creator);
+    assert_eq!(res.config.trading_fee_percent, Decimal::percent(300));
+    assert_eq!(res.config.min_price, Uint128::from(10u128));
+
+    let add_operator = SudoMsg::AddOperator {
+        operator: String::from("operator2"),
+    };
+    let res = router.wasm_sudo(marketplace.clone(), &add_operator);
+    assert!(res.is_ok());
+    let res = router.wasm_sudo(marketplace.clone(), &add_operator);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidConfig(String::from("operator2 is already an operator")).to_string()
+    );
+
+    let remove_operator = SudoMsg::RemoveOperator {
+        operator: String::from("operator"),
+    };
+    let res = router.wasm_sudo(marketplace.clone(), &remove_operator);
+    assert!(res.is_ok());
+
+    let res: OperatorsResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Operators {})
+        .unwrap();
+    assert_eq!(res.operators, vec![Addr::unchecked("operator2")]);
+
+    // The last operator cannot be removed
+    let remove_operator = SudoMsg::RemoveOperator {
+        operator: String::from("operator2"),
+    };
+    let res = router.wasm_sudo(marketplace.clone(), &remove_operator);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidConfig(String::from("operators must be non-empty")).to_string()
+    );
+}
+
+#[test]
+fn try_migrate_legacy() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (_marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // A legacy marketplace listing an NFT that stays with its owner
+    let legacy_id = router.store_code(contract_marketplace_legacy());
+    let msg = marketplace_legacy::msg::InstantiateMsg {
+        admin: String::from("operator"),
+        nft_addr: collection.to_string(),
+        allowed_native: String::from(NATIVE_DENOM),
+        fee_percentage: Decimal::percent(2),
+        collector_addr: creator.to_string(),
+    };
+    let legacy = router
+        .instantiate_contract(legacy_id, creator.clone(), &msg, &[], "Legacy", Some(creator.to_string()))
+        .unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &legacy, TOKEN_ID.to_string());
+    mint(&mut router, &creator, &collection, "2".to_string());
+    approve(&mut router, &creator, &collection, &legacy, "2".to_string());
+    let list_tokens = marketplace_legacy::msg::ExecuteMsg::ListTokens {
+        tokens: vec![
+            marketplace_legacy::state::Token {
+                id: TOKEN_ID.to_string(),
+                price: Uint128::from(100u128),
+                on_sale: true,
+            },
+            marketplace_legacy::state::Token {
+                id: "2".to_string(),
+                price: Uint128::from(200u128),
+                on_sale: true,
+            },
+        ],
+    };
+    let res = router.execute_contract(creator.clone(), legacy.clone(), &list_tokens, &[]);
+    assert!(res.is_ok());
+
+    // The legacy config has no min price
+    let marketplace_id = router.store_code(contract_marketplace());
+    let res = router.migrate_contract(creator.clone(), legacy.clone(), &MigrateMsg { min_price: None }, marketplace_id);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidMigration(String::from("min_price is required to migrate the legacy marketplace")).to_string()
+    );
+
+    let migrate_msg = MigrateMsg {
+        min_price: Some(Uint128::from(5u128)),
+    };
+    let res = router.migrate_contract(creator.clone(), legacy.clone(), &migrate_msg, marketplace_id);
+    assert!(res.is_ok());
+
+    // The legacy listings are migrated in bounded batches
+    let res: AskResponse = router
+        .wrap()
+        .query_wasm_smart(legacy.clone(), &QueryMsg::Ask { token_id: TOKEN_ID.to_string() })
+        .unwrap();
+    assert_eq!(res.ask, None);
+    let res = router.execute_contract(bidder.clone(), legacy.clone(), &ExecuteMsg::MigrateLegacy { limit: 31 }, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidBatchSize(30).to_string()
+    );
+    let res = router.execute_contract(bidder.clone(), legacy.clone(), &ExecuteMsg::MigrateLegacy { limit: 1 }, &[]);
+    let event = res.unwrap().events.into_iter().find(|e| e.ty == "wasm-migrate-legacy").unwrap();
+    assert!(event.attributes.contains(&Attribute::new("migrated_asks", "1")));
+    assert!(event.attributes.contains(&Attribute::new("remaining", "true")));
+    let res = router.execute_contract(bidder.clone(), legacy.clone(), &ExecuteMsg::MigrateLegacy { limit: 1 }, &[]);
+    let event = res.unwrap().events.into_iter().find(|e| e.ty == "wasm-migrate-legacy").unwrap();
+    assert!(event.attributes.contains(&Attribute::new("migrated_asks", "1")));
+    assert!(event.attributes.contains(&Attribute::new("remaining", "false")));
+    let res: AskCountResponse = router
+        .wrap()
+        .query_wasm_smart(legacy.clone(), &QueryMsg::AskCount {})
+        .unwrap();
+    assert_eq!(res.count, 2);
+
+    let res: ConfigResponse = router
+        .wrap()
+        .query_wasm_smart(legacy.clone(), &QueryMsg::Config {})
+        .unwrap();
+    assert_eq!(res.config.cw721_address, collection);
+    assert_eq!(res.config.trading_fee_percent, Decimal::percent(TRADING_FEE_BPS));
+    assert_eq!(res.config.operators, vec![Addr::unchecked("operator")]);
+
+    // The legacy listing is a non-custodial ask
+    let res: AskResponse = router
+        .wrap()
+        .query_wasm_smart(legacy.clone(), &QueryMsg::Ask { token_id: TOKEN_ID.to_string() })
+        .unwrap();
+    let ask = res.ask.unwrap();
+    assert_eq!(ask.seller, creator);
+    assert_eq!(ask.price, coin(100, NATIVE_DENOM));
+    assert!(ask.non_custodial);
+
+    let buy_now = ExecuteMsg::BuyNow {
+        token_id: TOKEN_ID.to_string(),
+        expected_price: coin(100, NATIVE_DENOM),
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), legacy.clone(), &buy_now, &coins(100, NATIVE_DENOM));
+    assert!(res.is_ok());
+
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder.to_string());
+
+    // Later migrations are version upgrades of this contract
+    let res = router.migrate_contract(creator.clone(), legacy.clone(), &migrate_msg, marketplace_id);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn try_migrate_baseline_state() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (_marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // A marketplace holding state written before any field was added to it
+    let baseline_id = router.store_code(contract_baseline_marketplace());
+    let msg = baseline_marketplace::InstantiateMsg {
+        config: baseline_marketplace::Config {
+            cw721_address: collection.clone(),
+            denom: String::from(NATIVE_DENOM),
+            collector_address: creator.clone(),
+            trading_fee_percent: Decimal::percent(TRADING_FEE_BPS),
+            operators: vec![Addr::unchecked("operator")],
+            min_price: Uint128::from(5u128),
+        },
+        ask: baseline_marketplace::Ask {
+            token_id: TOKEN_ID.to_string(),
+            seller: creator.clone(),
+            price: coin(100, NATIVE_DENOM),
+            funds_recipient: None,
+        },
+        bid_price: coin(50, NATIVE_DENOM),
+        collection_bid: baseline_marketplace::CollectionBid {
+            bidder: bidder.clone(),
+            units: 2,
+            price: coin(40, NATIVE_DENOM),
+        },
+    };
+    let baseline = router
+        .instantiate_contract(baseline_id, bidder.clone(), &msg, &coins(130, NATIVE_DENOM), "Baseline", Some(creator.to_string()))
+        .unwrap();
+
+    // The listed NFT is escrowed by the marketplace
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    let transfer_nft = Cw721ExecuteMsg::<Empty>::TransferNft {
+        recipient: baseline.to_string(),
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res = router.execute_contract(creator.clone(), collection.clone(), &transfer_nft, &[]);
+    assert!(res.is_ok());
+
+    let marketplace_id = router.store_code(contract_marketplace());
+    let res = router.migrate_contract(creator.clone(), baseline.clone(), &MigrateMsg { min_price: None }, marketplace_id);
+    assert!(res.is_ok());
+
+    // Fields added since load with their defaults
+    let res: ConfigResponse = router
+        .wrap()
+        .query_wasm_smart(baseline.clone(), &QueryMsg::Config {})
+        .unwrap();
+    assert_eq!(res.config.min_price, Uint128::from(5u128));
+    assert!(!res.config.accumulate_fees);
+    assert!(!res.config.refund_bids_on_ask_removal);
+    assert!(!res.config.cw20_payments);
+    assert!(res.config.accepted_denoms.is_empty());
+    assert!(!res.config.halted);
+    assert_eq!(res.config.relist_cooldown_seconds, None);
+
+    let res: AskResponse = router
+        .wrap()
+        .query_wasm_smart(baseline.clone(), &QueryMsg::Ask { token_id: TOKEN_ID.to_string() })
+        .unwrap();
+    let ask = res.ask.unwrap();
+    assert_eq!(ask.price, coin(100, NATIVE_DENOM));
+    assert!(!ask.non_custodial);
+    assert_eq!(ask.reserve_for, None);
+
+    let query_bid = QueryMsg::Bid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.to_string(),
+    };
+    let res: BidResponse = router
+        .wrap()
+        .query_wasm_smart(baseline.clone(), &query_bid)
+        .unwrap();
+    assert_eq!(res.bid, Some(Bid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.clone(),
+        price: coin(50, NATIVE_DENOM),
+        refund_to: None,
+    }));
+
+    let res: CollectionBidResponse = router
+        .wrap()
+        .query_wasm_smart(baseline.clone(), &QueryMsg::CollectionBid { bidder: bidder.to_string() })
+        .unwrap();
+    assert_eq!(res.collection_bid, Some(CollectionBid {
+        bidder: bidder.clone(),
+        units: 2,
+        price: coin(40, NATIVE_DENOM),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
+    }));
+
+    // The migrated ask can be bought
+    let buy_now = ExecuteMsg::BuyNow {
+        token_id: TOKEN_ID.to_string(),
+        expected_price: coin(100, NATIVE_DENOM),
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder2.clone(), baseline.clone(), &buy_now, &coins(100, NATIVE_DENOM));
+    assert!(res.is_ok());
+
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder2.to_string());
+}
+
+#[test]
+fn try_halt() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+
+    // Only an operator can halt trading
+    let halt = ExecuteMsg::SetHalted { halted: true };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &halt, &[]);
+    assert!(res.is_err());
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &halt, &[]);
+    assert!(res.is_ok());
+
+    let set_bid = ExecuteMsg::SetBid {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(50, NATIVE_DENOM),
+        refund_to: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &coins(50, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::Halted {}.to_string()
+    );
+
+    let buy_now = ExecuteMsg::BuyNow {
+        token_id: TOKEN_ID.to_string(),
+        expected_price: coin(100, NATIVE_DENOM),
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &buy_now, &coins(100, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::Halted {}.to_string()
+    );
+
+    // Sellers can still exit while halted
+    let remove_ask = ExecuteMsg::RemoveAsk {
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &remove_ask, &[]);
+    assert!(res.is_ok());
+
+    // Governance resumes trading
+    let res = router.wasm_sudo(marketplace.clone(), &SudoMsg::SetHalted { halted: false });
+    assert!(res.is_ok());
+
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+}
+
+#[test]
+fn try_roles() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, _bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, _collection) = setup_contracts(&mut router, &creator).unwrap();
+    let bot = Addr::unchecked("bot");
+
+    // Only an operator can grant roles
+    let grant_pauser = ExecuteMsg::GrantRole {
+        address: bot.to_string(),
+        role: Role::Pauser,
+    };
+    let res = router.execute_contract(bot.clone(), marketplace.clone(), &grant_pauser, &[]);
+    assert!(res.is_err());
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &grant_pauser, &[]);
+    assert!(res.is_ok());
+    let grant_params_admin = ExecuteMsg::GrantRole {
+        address: bot.to_string(),
+        role: Role::ParamsAdmin,
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &grant_params_admin, &[]);
+    assert!(res.is_ok());
+
+    let res: RolesResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Roles { start_after: None, limit: None })
+        .unwrap();
+    assert_eq!(res.roles, vec![AddressRoles { address: bot.clone(), roles: vec![Role::Pauser, Role::ParamsAdmin] }]);
+
+    // The bot can only use the roles it was granted
+    let res = router.execute_contract(bot.clone(), marketplace.clone(), &ExecuteMsg::SetHalted { halted: true }, &[]);
+    assert!(res.is_ok());
+    let distribute_fees = ExecuteMsg::DistributeFees {
+        recipients: vec![(creator.to_string(), Decimal::one())],
+    };
+    let res = router.execute_contract(bot.clone(), marketplace.clone(), &distribute_fees, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::Unauthorized(String::from("only an operator or fee_admin can call this function")).to_string()
+    );
+
+    // A params admin cannot change the operators
+    let mut update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: Some(vec![bot.to_string()]),
+        min_price: None,
+        denom: None,
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: None,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: None,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        accepted_denoms: None,
+        referral_fee_share: None,
+    };
+    let res = router.execute_contract(bot.clone(), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_err());
+    if let ExecuteMsg::UpdateConfig { operators, min_price, .. } = &mut update_config {
+        *operators = None;
+        *min_price = Some(Uint128::from(10u128));
+    }
+    let res = router.execute_contract(bot.clone(), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    let revoke_pauser = ExecuteMsg::RevokeRole {
+        address: bot.to_string(),
+        role: Role::Pauser,
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &revoke_pauser, &[]);
+    assert!(res.is_ok());
+    let res = router.execute_contract(bot.clone(), marketplace.clone(), &ExecuteMsg::SetHalted { halted: false }, &[]);
+    assert!(res.is_err());
+
+    let res: RolesResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Roles { start_after: None, limit: None })
+        .unwrap();
+    assert_eq!(res.roles, vec![AddressRoles { address: bot, roles: vec![Role::ParamsAdmin] }]);
+}
+
+#[test]
+fn try_fee_splits() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // The shares must sum to 1
+    let invalid_fee_splits = ExecuteMsg::SetFeeSplits {
+        recipients: vec![(String::from("collector"), Decimal::percent(50))],
+        burn_share: Decimal::percent(20),
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &invalid_fee_splits, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidFeeDistribution(String::from("shares must sum to 1")).to_string()
+    );
+
+    // Each recipient is listed once
+    let invalid_fee_splits = ExecuteMsg::SetFeeSplits {
+        recipients: vec![
+            (String::from("collector"), Decimal::percent(50)),
+            (String::from("collector"), Decimal::percent(30)),
+        ],
+        burn_share: Decimal::percent(20),
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &invalid_fee_splits, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidFeeDistribution(String::from("recipient collector is duplicated")).to_string()
+    );
+
+    let set_fee_splits = ExecuteMsg::SetFeeSplits {
+        recipients: vec![
+            (String::from("collector"), Decimal::percent(50)),
+            (String::from("staking"), Decimal::percent(30)),
+        ],
+        burn_share: Decimal::percent(20),
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_fee_splits, &[]);
+    assert!(res.is_err());
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &set_fee_splits, &[]);
+    assert!(res.is_ok());
+
+    let res: FeeSplitsResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::FeeSplits {})
+        .unwrap();
+    assert_eq!(res.fee_splits.unwrap().burn_share, Decimal::percent(20));
+
+    // The 20 market fee of a 1000 sale is split 10 / 6 / 4 burned
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 1000);
+    let set_bid = ExecuteMsg::SetBid {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(1000, NATIVE_DENOM),
+        refund_to: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &coins(1000, NATIVE_DENOM));
+    assert!(res.is_ok());
+    let event = res.unwrap().events.into_iter().find(|e| e.ty == "wasm-burn-market-fee").unwrap();
+    assert!(event.attributes.contains(&Attribute::new("coin", coin(4, NATIVE_DENOM).to_string())));
+
+    let collector_balance = router.wrap().query_balance("collector", NATIVE_DENOM).unwrap();
+    assert_eq!(collector_balance.amount, Uint128::from(10u128));
+    let staking_balance = router.wrap().query_balance("staking", NATIVE_DENOM).unwrap();
+    assert_eq!(staking_balance.amount, Uint128::from(6u128));
+
+    // Clearing the splits pays the collector again
+    let clear_fee_splits = ExecuteMsg::SetFeeSplits {
+        recipients: vec![],
+        burn_share: Decimal::zero(),
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &clear_fee_splits, &[]);
+    assert!(res.is_ok());
+    let res: FeeSplitsResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::FeeSplits {})
+        .unwrap();
+    assert_eq!(res.fee_splits, None);
+}
+
+#[test]
+fn try_referral_fee() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Half of the market fee goes to the referrer
+    let mut update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: None,
+        min_price: None,
+        denom: None,
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: None,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: None,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        accepted_denoms: None,
+        referral_fee_share: Some(Decimal::percent(150)),
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &update_config, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidConfig(String::from("referral_fee_share must be less than or equal to 1")).to_string()
+    );
+    if let ExecuteMsg::UpdateConfig { referral_fee_share, .. } = &mut update_config {
+        *referral_fee_share = Some(Decimal::percent(50));
+    }
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 1000);
+
+    // Neither the buyer nor the seller can refer the sale
+    for party in [&bidder, &creator] {
+        let buy_now = ExecuteMsg::BuyNow {
+            token_id: TOKEN_ID.to_string(),
+            expected_price: coin(1000, NATIVE_DENOM),
+            referrer: Some(party.to_string()),
+        };
+        let res = router.execute_contract(bidder.clone(), marketplace.clone(), &buy_now, &coins(1000, NATIVE_DENOM));
+        assert_eq!(
+            res.unwrap_err().root_cause().to_string(),
+            ContractError::SelfReferral(party.to_string()).to_string()
+        );
+    }
+
+    // The 20 market fee of a 1000 sale is split between the referrer and the collector
+    let buy_now = ExecuteMsg::BuyNow {
+        token_id: TOKEN_ID.to_string(),
+        expected_price: coin(1000, NATIVE_DENOM),
+        referrer: Some(String::from("aggregator")),
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &buy_now, &coins(1000, NATIVE_DENOM));
+    assert!(res.is_ok());
+    let events = res.unwrap().events;
+    let event = events.iter().find(|e| e.ty == "wasm-payout-referral").unwrap();
+    assert!(event.attributes.contains(&Attribute::new("coin", coin(10, NATIVE_DENOM).to_string())));
+    let event = events.iter().find(|e| e.ty == "wasm-payout-market").unwrap();
+    assert!(event.attributes.contains(&Attribute::new("coin", coin(10, NATIVE_DENOM).to_string())));
+
+    let referrer_balance = router.wrap().query_balance("aggregator", NATIVE_DENOM).unwrap();
+    assert_eq!(referrer_balance.amount, Uint128::from(10u128));
+}
+
+#[test]
+fn try_distribute_fees() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+    let operator = Addr::unchecked("operator");
+
+    // Hold fees in the contract instead of paying the collector per sale
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: None,
+        min_price: None,
+        denom: None,
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: Some(true),
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: None,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        accepted_denoms: None,
+        referral_fee_share: None,
+    };
+    let res = router.execute_contract(operator.clone(), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    // Two sales of 500 each accumulate 2 * 10 in fees
+    for token_id in vec!["1", "2"] {
+        mint(&mut router, &creator, &collection, token_id.to_string());
+        approve(&mut router, &creator, &collection, &marketplace, token_id.to_string());
+        ask(&mut router, &creator, &marketplace, token_id.to_string(), 500);
+        bid(&mut router, &bidder, &marketplace, token_id.to_string(), 500);
+    }
+    let res = router.wrap().query_all_balances(marketplace.clone()).unwrap();
+    assert_eq!(res, coins(20, NATIVE_DENOM));
+
+    // Only operators can distribute fees
+    let distribute_fees = ExecuteMsg::DistributeFees {
+        recipients: vec![
+            (String::from("treasury"), Decimal::percent(70)),
+            (String::from("community"), Decimal::percent(30)),
+        ],
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &distribute_fees, &[]);
+    assert!(res.is_err());
+
+    // Weights must sum to 1
+    let invalid_distribute_fees = ExecuteMsg::DistributeFees {
+        recipients: vec![
+            (String::from("treasury"), Decimal::percent(70)),
+            (String::from("community"), Decimal::percent(20)),
+        ],
+    };
+    let res = router.execute_contract(operator.clone(), marketplace.clone(), &invalid_distribute_fees, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidFeeDistribution(String::from("weights must sum to 1")).to_string(),
+    );
+
+    // Fees are split 70/30
+    let res = router.execute_contract(operator.clone(), marketplace.clone(), &distribute_fees, &[]);
+    assert!(res.is_ok());
+    let res = router.wrap().query_all_balances("treasury").unwrap();
+    assert_eq!(res, coins(14, NATIVE_DENOM));
+    let res = router.wrap().query_all_balances("community").unwrap();
+    assert_eq!(res, coins(6, NATIVE_DENOM));
+    let res = router.wrap().query_all_balances(marketplace.clone()).unwrap();
+    assert!(res.is_empty());
+}
+
+#[test]
+fn try_bid_refund_to() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, _collection) = setup_contracts(&mut router, &creator).unwrap();
+    let refund_address = Addr::unchecked("refund_address");
+
+    let coin_send = coin(100, NATIVE_DENOM);
+    let set_bid = ExecuteMsg::SetBid {
+        token_id: TOKEN_ID.to_string(),
+        price: coin_send.clone(),
+        refund_to: Some(refund_address.to_string()),
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &[coin_send]);
+    assert!(res.is_ok());
+
+    // Removing the bid refunds the refund address rather than the bidder
+    let remove_bid = ExecuteMsg::RemoveBid {
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &remove_bid, &[]);
+    assert!(res.is_ok());
+
+    let res = router.wrap().query_all_balances(refund_address.clone()).unwrap();
+    assert_eq!(res, coins(100, NATIVE_DENOM));
+    let res = router.wrap().query_all_balances(bidder.clone()).unwrap();
+    assert_eq!(res, coins(INITIAL_BALANCE - 100, NATIVE_DENOM));
+}
+
+#[test]
+fn try_relist_cooldown() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Tokens cannot be listed again within an hour of a sale
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: None,
+        min_price: None,
+        denom: None,
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: None,
+        relist_cooldown_seconds: Some(3600),
+        refund_bids_on_ask_removal: None,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        accepted_denoms: None,
+        referral_fee_share: None,
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    // Sell the token to bidder
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 100);
+
+    // Relisting during the cooldown fails
+    approve(&mut router, &bidder, &collection, &marketplace, TOKEN_ID.to_string());
+    let set_ask = ExecuteMsg::SetAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(200, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_ask, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::RelistCooldown(TOKEN_ID.to_string()).to_string(),
+    );
+
+    // Relisting after the cooldown succeeds
+    let mut block = router.block_info();
+    block.time = block.time.plus_seconds(3600);
+    router.set_block(block);
+
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_ask, &[]);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn try_query_operators() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, _bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, _collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Operators set at instantiate
+    let res: OperatorsResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Operators {})
+        .unwrap();
+    assert_eq!(res.operators, vec![Addr::unchecked("operator")]);
+
+    // Operators after an update
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: Some(vec![String::from("operator"), String::from("operator2")]),
+        min_price: None,
+        denom: None,
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: None,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: None,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        accepted_denoms: None,
+        referral_fee_share: None,
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    let res: OperatorsResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Operators {})
+        .unwrap();
+    assert_eq!(res.operators, vec![Addr::unchecked("operator"), Addr::unchecked("operator2")]);
+}
+
+#[test]
+fn try_ask_external_id() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, _bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+
+    // External ids longer than the cap are rejected
+    let set_ask = ExecuteMsg::SetAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(110, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: Some("x".repeat(129)),
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::ExternalIdTooLong(128).to_string(),
+    );
+
+    // The external id is stored and echoed in the set-ask event
+    let set_ask = ExecuteMsg::SetAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(110, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: Some(String::from("order-42")),
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]).unwrap();
+    let set_ask_event = res.events.iter().find(|e| e.ty == "wasm-set-ask").unwrap();
+    assert!(set_ask_event.attributes.contains(&Attribute {
+        key: String::from("external_id"),
+        value: String::from("order-42")
+    }));
+
+    let query_ask = QueryMsg::Ask {
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res: AskResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_ask)
+        .unwrap();
+    assert_eq!(res.ask.unwrap().external_id, Some(String::from("order-42")));
+}
+
+#[test]
+fn try_refund_bids_on_ask_removal() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+    let remove_ask = ExecuteMsg::RemoveAsk {
+        token_id: TOKEN_ID.to_string(),
+    };
+    let query_bid = QueryMsg::Bid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.to_string(),
+    };
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 110);
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 100);
+
+    // By default, standing bids survive the ask being removed
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &remove_ask, &[]);
+    assert!(res.is_ok());
+    let res: BidResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_bid)
+        .unwrap();
+    assert!(res.bid.is_some());
+
+    // When enabled, standing bids are refunded when the ask is removed
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: None,
+        min_price: None,
+        denom: None,
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: None,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: Some(true),
+        display_decimals: None,
+        max_collection_bid_units: None,
+        accepted_denoms: None,
+        referral_fee_share: None,
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 110);
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &remove_ask, &[]);
+    assert!(res.is_ok());
+    let res: BidResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_bid)
+        .unwrap();
+    assert!(res.bid.is_none());
+    let res = router.wrap().query_all_balances(bidder.clone()).unwrap();
+    assert_eq!(res, coins(INITIAL_BALANCE, NATIVE_DENOM));
+}
+
+#[test]
+fn try_query_bootstrap() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 110);
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 100);
+    bid(&mut router, &bidder2, &marketplace, TOKEN_ID.to_string(), 90);
+
+    let collection_bid_price = coin(50u128, NATIVE_DENOM);
+    let set_collection_bid = ExecuteMsg::SetCollectionBid {
+        units: 1,
+        price: collection_bid_price.clone(),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_collection_bid, &[collection_bid_price]);
+    assert!(res.is_ok());
+
+    let res: BootstrapResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Bootstrap {})
+        .unwrap();
+    let config: ConfigResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Config {})
+        .unwrap();
+    assert_eq!(res.config, config.config);
+    assert_eq!(res.ask_count, 1);
+    assert_eq!(res.bid_count, 2);
+    assert_eq!(res.collection_bid_count, 1);
+}
+
+#[test]
+fn try_fill_signed_ask() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // The seller approves the marketplace without listing the NFT
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+
+    // The seller registers their signing key
+    let signing_key = SigningKey::from_bytes(&[7u8; 32]).unwrap();
+    let set_signing_key = ExecuteMsg::SetSigningKey {
+        pubkey: Binary::from(signing_key.verifying_key().to_bytes().as_slice()),
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_signing_key, &[]);
+    assert!(res.is_ok());
+
+    // The seller signs an ask off-chain
+    let expires_at = router.block_info().time.plus_seconds(100);
+    let signed_ask = SignedAsk {
+        token_id: TOKEN_ID.to_string(),
+        seller: creator.to_string(),
+        price: coin(110, NATIVE_DENOM),
+        funds_recipient: None,
+        nonce: 1,
+        expires_at,
+    };
+    let chain_id = router.block_info().chain_id;
+    let sign_for_chain = |chain_id: &str, ask: &SignedAsk| -> Binary {
+        let payload = to_binary(&SignedAskPayload {
+            chain_id: chain_id.to_string(),
+            marketplace: marketplace.to_string(),
+            ask: ask.clone(),
+        }).unwrap();
+        let signature: Signature = signing_key.sign(payload.as_slice());
+        Binary::from(signature.as_ref())
+    };
+    let sign = |ask: &SignedAsk| sign_for_chain(&chain_id, ask);
+    let signature = sign(&signed_ask);
+
+    // An ask signed for another chain cannot be filled
+    let fill_signed_ask = ExecuteMsg::FillSignedAsk {
+        signature: sign_for_chain("other-chain", &signed_ask),
+        ask: signed_ask.clone(),
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &fill_signed_ask, &coins(110, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidSignature {}.to_string(),
+    );
+
+    // An expired signed ask cannot be filled
+    let mut expired_ask = signed_ask.clone();
+    expired_ask.expires_at = router.block_info().time;
+    let fill_signed_ask = ExecuteMsg::FillSignedAsk {
+        signature: sign(&expired_ask),
+        ask: expired_ask,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &fill_signed_ask, &coins(110, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::SignedAskExpired {}.to_string(),
+    );
+
+    // A cancelled signed ask cannot be filled
+    let mut cancelled_ask = signed_ask.clone();
+    cancelled_ask.nonce = 2;
+    let cancel_signed_ask = ExecuteMsg::CancelSignedAsk { nonce: 2 };
+    router.execute_contract(creator.clone(), marketplace.clone(), &cancel_signed_ask, &[]).unwrap();
+    let fill_signed_ask = ExecuteMsg::FillSignedAsk {
+        signature: sign(&cancelled_ask),
+        ask: cancelled_ask,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &fill_signed_ask, &coins(110, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::NonceAlreadyUsed(2).to_string(),
+    );
+
+    // Nor can one for an NFT listed on-chain, even when the listing leaves it with the seller
+    let set_ask = ExecuteMsg::SetAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(100, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: true,
+    };
+    router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]).unwrap();
+    let fill_signed_ask = ExecuteMsg::FillSignedAsk {
+        ask: signed_ask.clone(),
+        signature: signature.clone(),
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &fill_signed_ask, &coins(110, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::AskAlreadyExists(TOKEN_ID.to_string()).to_string(),
+    );
+    let remove_ask = ExecuteMsg::RemoveAsk {
+        token_id: TOKEN_ID.to_string(),
+    };
+    router.execute_contract(creator.clone(), marketplace.clone(), &remove_ask, &[]).unwrap();
+
+    // A tampered ask fails verification
+    let mut tampered_ask = signed_ask.clone();
+    tampered_ask.price = coin(10, NATIVE_DENOM);
+    let fill_signed_ask = ExecuteMsg::FillSignedAsk {
+        ask: tampered_ask,
+        signature: signature.clone(),
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &fill_signed_ask, &coins(10, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidSignature {}.to_string(),
+    );
+
+    // A valid signature fills the ask
+    let fill_signed_ask = ExecuteMsg::FillSignedAsk {
+        ask: signed_ask,
+        signature,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &fill_signed_ask, &coins(110, NATIVE_DENOM));
+    assert!(res.is_ok());
+
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder.to_string());
+
+    // The same signed ask cannot be filled twice
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &fill_signed_ask, &coins(110, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::NonceAlreadyUsed(1).to_string(),
+    );
+}
+
+#[test]
+fn try_replace_bid_matching_ask() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 50);
+
+    // Replacing the bid with one that meets the ask refunds the old bid and finalizes the sale
+    let coin_send = coin(100, NATIVE_DENOM);
+    let set_bid = ExecuteMsg::SetBid {
+        token_id: TOKEN_ID.to_string(),
+        price: coin_send.clone(),
+        refund_to: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &[coin_send]).unwrap();
+
+    let refund_event = res.events.iter().find(|e| e.ty == "wasm-refund-bidder").unwrap();
+    assert_eq!(refund_event.attributes[1], Attribute {
+        key: String::from("coin"),
+        value: String::from("50ujunox")
+    });
+    assert!(res.events.iter().any(|e| e.ty == "wasm-finalize-sale"));
+
+    // Final state: no bid, no ask, NFT and funds moved
+    let query_bid = QueryMsg::Bid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.to_string(),
+    };
+    let res: BidResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_bid)
+        .unwrap();
+    assert_eq!(res.bid, None);
+
+    let query_ask = QueryMsg::Ask {
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res: AskResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_ask)
+        .unwrap();
+    assert_eq!(res.ask, None);
+
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder.to_string());
+
+    let res = router.wrap().query_all_balances(bidder.clone()).unwrap();
+    assert_eq!(res, coins(INITIAL_BALANCE - 100, NATIVE_DENOM));
+    let res = router.wrap().query_all_balances(marketplace.clone()).unwrap();
+    assert!(res.is_empty());
+}
+
+#[test]
+fn try_ask_min_offer() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // List with a buy-now price of 100, accepting offers from 60
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    let set_ask = ExecuteMsg::SetAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(100, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: Some(Uint128::from(60u128)),
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
+    assert!(res.is_ok());
+
+    // Bids below the min offer are rejected
+    let set_bid = ExecuteMsg::SetBid {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(50, NATIVE_DENOM),
+        refund_to: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &[coin(50, NATIVE_DENOM)]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::BidTooLow(Uint128::from(60u128), Uint128::from(50u128)).to_string(),
+    );
+
+    // Bids between the min offer and the price are stored as offers
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 80);
+    let query_bid = QueryMsg::Bid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.to_string(),
+    };
+    let res: BidResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_bid)
+        .unwrap();
+    assert_eq!(res.bid.unwrap().price, coin(80, NATIVE_DENOM));
+
+    // Bids at the price buy the NFT now
+    bid(&mut router, &bidder2, &marketplace, TOKEN_ID.to_string(), 100);
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder2.to_string());
+}
+
+#[test]
+fn try_ask_pre_authorized() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // List at 100, with bidder2 pre-authorized to buy at 70
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    let set_ask = ExecuteMsg::SetAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(100, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: Some((bidder2.to_string(), Uint128::from(70u128))),
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
+    assert!(res.is_ok());
+
+    // Other bidders below the price are stored
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 80);
+    let query_bid = QueryMsg::Bid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.to_string(),
+    };
+    let res: BidResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_bid)
+        .unwrap();
+    assert_eq!(res.bid.unwrap().price, coin(80, NATIVE_DENOM));
+
+    // The pre-authorized bidder settles at the agreed price, and is refunded the surplus
+    let prev_balance = router.wrap().query_balance(bidder2.clone(), NATIVE_DENOM).unwrap();
+    bid(&mut router, &bidder2, &marketplace, TOKEN_ID.to_string(), 75);
+    let post_balance = router.wrap().query_balance(bidder2.clone(), NATIVE_DENOM).unwrap();
+    assert_eq!(prev_balance.amount - post_balance.amount, Uint128::from(70u128));
+
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder2.to_string());
+}
+
+#[test]
+fn try_price_display() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Format event prices with 2 decimals
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: None,
+        min_price: None,
+        denom: None,
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: None,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: None,
+        display_decimals: Some(2),
+        max_collection_bid_units: None,
+        accepted_denoms: None,
+        referral_fee_share: None,
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    let set_ask = ExecuteMsg::SetAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(125, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]).unwrap();
+    let set_ask_event = res.events.iter().find(|e| e.ty == "wasm-set-ask").unwrap();
+    assert!(set_ask_event.attributes.contains(&Attribute {
+        key: String::from("price"),
+        value: coin(125, NATIVE_DENOM).to_string()
+    }));
+    assert!(set_ask_event.attributes.contains(&Attribute {
+        key: String::from("price_display"),
+        value: String::from("1.25")
+    }));
+
+    // The sale reports both the base unit and formatted price
+    let set_bid = ExecuteMsg::SetBid {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(125, NATIVE_DENOM),
+        refund_to: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &[coin(125, NATIVE_DENOM)]).unwrap();
+    let finalize_event = res.events.iter().find(|e| e.ty == "wasm-finalize-sale").unwrap();
+    assert!(finalize_event.attributes.contains(&Attribute {
+        key: String::from("payment_amount"),
+        value: String::from("125")
+    }));
+    assert!(finalize_event.attributes.contains(&Attribute {
+        key: String::from("price_display"),
+        value: String::from("1.25")
+    }));
+}
+
+#[test]
+fn try_reduce_collection_bid() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, _collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Create a bid of 5 units for 100 tokens each
+    let set_collection_bid = ExecuteMsg::SetCollectionBid {
+        units: 5,
+        price: coin(100, NATIVE_DENOM),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_collection_bid, &coins(500, NATIVE_DENOM));
+    assert!(res.is_ok());
+
+    // Units cannot be increased, or reduced to zero
+    for new_units in vec![0, 5, 6] {
+        let reduce_collection_bid = ExecuteMsg::ReduceCollectionBid { new_units };
+        let res = router.execute_contract(bidder.clone(), marketplace.clone(), &reduce_collection_bid, &[]);
+        assert_eq!(
+            res.unwrap_err().root_cause().to_string(),
+            ContractError::InvalidCollectionBid {}.to_string(),
+        );
+    }
+
+    // Reducing to 2 units refunds the escrow for 3 units
+    let prev_balance = router.wrap().query_balance(bidder.clone(), NATIVE_DENOM).unwrap();
+    let reduce_collection_bid = ExecuteMsg::ReduceCollectionBid { new_units: 2 };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &reduce_collection_bid, &[]);
+    assert!(res.is_ok());
+    let post_balance = router.wrap().query_balance(bidder.clone(), NATIVE_DENOM).unwrap();
+    assert_eq!(post_balance.amount - prev_balance.amount, Uint128::from(300u128));
+
+    let query_collection_bid = QueryMsg::CollectionBid {
+        bidder: bidder.to_string(),
+    };
+    let res: CollectionBidResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_collection_bid)
+        .unwrap();
+    assert_eq!(res.collection_bid.unwrap().units, 2);
+}
+
+#[test]
+fn try_query_settlements() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Sell one token to each bidder
+    let token_id_2 = "124".to_string();
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    mint(&mut router, &creator, &collection, token_id_2.clone());
+    approve(&mut router, &creator, &collection, &marketplace, token_id_2.clone());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+    ask(&mut router, &creator, &marketplace, token_id_2.clone(), 110);
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 100);
+    bid(&mut router, &bidder2, &marketplace, token_id_2.clone(), 110);
+
+    // All settlements are listed newest first
+    let query_settlements = QueryMsg::Settlements {
+        buyer: None,
+        seller: None,
+        query_options: QueryOptions {
+            descending: None,
+            start_after: None,
+            limit: None,
+        },
+    };
+    let res: SettlementsResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_settlements)
+        .unwrap();
+    assert_eq!(res.settlements.len(), 2);
+    assert_eq!(res.settlements[0].token_id, token_id_2);
+    assert_eq!(res.settlements[1].token_id, TOKEN_ID.to_string());
+
+    // Filtered by buyer
+    let query_settlements = QueryMsg::Settlements {
+        buyer: Some(bidder.to_string()),
+        seller: Some(creator.to_string()),
+        query_options: QueryOptions {
+            descending: None,
+            start_after: None,
+            limit: None,
+        },
+    };
+    let res: SettlementsResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_settlements)
+        .unwrap();
+    assert_eq!(res.settlements.len(), 1);
+    assert_eq!(res.settlements[0].token_id, TOKEN_ID.to_string());
+    assert_eq!(res.settlements[0].buyer, bidder);
+    assert_eq!(res.settlements[0].seller, creator);
+    assert_eq!(res.settlements[0].price, coin(100, NATIVE_DENOM));
+}
+
+#[test]
+fn try_refund_all_bids() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, _collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Escrow two bids and a collection bid
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 100);
+    bid(&mut router, &bidder2, &marketplace, TOKEN_ID.to_string(), 110);
+    let set_collection_bid = ExecuteMsg::SetCollectionBid {
+        units: 2,
+        price: coin(50, NATIVE_DENOM),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_collection_bid, &coins(100, NATIVE_DENOM));
+    assert!(res.is_ok());
+
+    // Only operators can refund all bids
+    let refund_all_bids = ExecuteMsg::RefundAllBids { limit: 2 };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &refund_all_bids, &[]);
+    assert!(res.is_err());
+
+    // Refund in batches until the books are empty
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &refund_all_bids, &[]);
+    let refund_event = res
+        .unwrap()
+        .events
+        .into_iter()
+        .find(|e| e.ty == "wasm-refund-all-bids")
+        .unwrap();
+    assert_eq!(refund_event.attributes[2].value, "2");
+    assert_eq!(refund_event.attributes[3].value, "0");
+
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &refund_all_bids, &[]);
+    assert!(res.is_ok());
+
+    let res: BootstrapResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Bootstrap {})
+        .unwrap();
+    assert_eq!(res.bid_count, 0);
+    assert_eq!(res.collection_bid_count, 0);
+
+    // All escrow is returned
+    let res = router.wrap().query_balance(bidder.clone(), NATIVE_DENOM).unwrap();
+    assert_eq!(res.amount, Uint128::from(INITIAL_BALANCE));
+    let res = router.wrap().query_balance(bidder2.clone(), NATIVE_DENOM).unwrap();
+    assert_eq!(res.amount, Uint128::from(INITIAL_BALANCE));
+    let res = router.wrap().query_balance(marketplace.clone(), NATIVE_DENOM).unwrap();
+    assert_eq!(res.amount, Uint128::zero());
+}
+
+#[test]
+fn try_ask_min_net_proceeds() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // List at 100, requiring 90 after the 2% market fee and 10% royalty
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    let set_ask = ExecuteMsg::SetAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(100, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: Some(Uint128::from(90u128)),
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
+    assert!(res.is_ok());
+
+    // A bid at the price nets the seller 88, so it is stored instead of matched
+    let set_bid = ExecuteMsg::SetBid {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(100, NATIVE_DENOM),
+        refund_to: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &coins(100, NATIVE_DENOM)).unwrap();
+    let match_event = res.events.iter().find(|e| e.ty == "wasm-match-bid").unwrap();
+    assert!(match_event.attributes.contains(&Attribute {
+        key: String::from("outcome"),
+        value: String::from("net-too-low")
+    }));
+
+    let query_bid = QueryMsg::Bid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.to_string(),
+    };
+    let res: BidResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_bid)
+        .unwrap();
+    assert_eq!(res.bid.unwrap().price, coin(100, NATIVE_DENOM));
+
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, marketplace.to_string());
+}
+
+#[test]
+fn try_sale_seq() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Two sales get consecutive sequence numbers
+    let mut sale_seqs = vec![];
+    for token_id in vec![TOKEN_ID.to_string(), "124".to_string()] {
+        mint(&mut router, &creator, &collection, token_id.clone());
+        approve(&mut router, &creator, &collection, &marketplace, token_id.clone());
+        ask(&mut router, &creator, &marketplace, token_id.clone(), 100);
+
+        let set_bid = ExecuteMsg::SetBid {
+            token_id: token_id.clone(),
+            price: coin(100, NATIVE_DENOM),
+            refund_to: None,
+            referrer: None,
+        };
+        let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &coins(100, NATIVE_DENOM)).unwrap();
+        let finalize_event = res.events.iter().find(|e| e.ty == "wasm-finalize-sale").unwrap();
+        let sale_seq = finalize_event
+            .attributes
+            .iter()
+            .find(|a| a.key == "sale_seq")
+            .unwrap()
+            .value
+            .parse::<u64>()
+            .unwrap();
+        sale_seqs.push(sale_seq);
+    }
+    assert_eq!(sale_seqs[1], sale_seqs[0] + 1);
+}
+
+#[test]
+fn try_sale_settled_reply() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+
+    // A matching bid settles the sale, and the reply confirms it with the same sale_seq
+    let set_bid = ExecuteMsg::SetBid {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(100, NATIVE_DENOM),
+        refund_to: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &coins(100, NATIVE_DENOM)).unwrap();
+    let sale_seq = |ty: &str| {
+        res.events
+            .iter()
+            .find(|e| e.ty == ty)
+            .unwrap()
+            .attributes
+            .iter()
+            .find(|a| a.key == "sale_seq")
+            .unwrap()
+            .value
+            .clone()
+    };
+    assert_eq!(sale_seq("wasm-sale-settled"), sale_seq("wasm-finalize-sale"));
+
+    // The confirmation is emitted only after the NFT has moved to the buyer
+    let settled = res.events.iter().position(|e| e.ty == "wasm-sale-settled").unwrap();
+    let transferred = res.events.iter().position(|e| e.ty == "wasm" && e.attributes.iter().any(|a| a.value == "transfer_nft")).unwrap();
+    assert!(transferred < settled);
+}
+
+#[test]
+fn try_sale_rolled_back_on_failed_transfer() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // The marketplace is not approved, so it cannot deliver the unlisted NFT
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 100);
+
+    let accept_bid = ExecuteMsg::AcceptBid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.to_string(),
+        credit_bps: None,
+        min_price: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_bid, &[]);
+    assert!(res.unwrap_err().root_cause().to_string().starts_with("NFT transfer failed, sale 1 rolled back"));
+
+    // Nothing moved: the bid is still escrowed and the seller was not paid
+    let res: BidResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Bid { token_id: TOKEN_ID.to_string(), bidder: bidder.to_string() })
+        .unwrap();
+    assert!(res.bid.is_some());
+    let creator_balance = router.wrap().query_balance(creator.to_string(), NATIVE_DENOM).unwrap();
+    assert_eq!(creator_balance.amount, Uint128::zero());
+}
+
+#[test]
+fn try_ask_hidden_min() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // List at 100, accepting nothing below 80
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    let set_ask = ExecuteMsg::SetAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(100, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: Some(Uint128::from(80u128)),
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
+    };
+
+    // The hidden minimum cannot be above the price
+    let mut invalid_set_ask = set_ask.clone();
+    if let ExecuteMsg::SetAsk { hidden_min, .. } = &mut invalid_set_ask {
+        *hidden_min = Some(Uint128::from(110u128));
+    }
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &invalid_set_ask, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidPrice {}.to_string()
+    );
+
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
+    assert!(res.is_ok());
+
+    // Nor can the price be lowered below it
+    let update_ask = ExecuteMsg::UpdateAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(70, NATIVE_DENOM),
+        reserve_for: None,
+        clear_reserve_for: false,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &update_ask, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidPrice {}.to_string()
+    );
+
+    // The hidden minimum is not revealed by queries
+    let query_ask = QueryMsg::Ask {
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res: AskResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_ask)
+        .unwrap();
+    assert_eq!(res.ask.unwrap().hidden_min, None);
+
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 70);
+    bid(&mut router, &bidder2, &marketplace, TOKEN_ID.to_string(), 85);
+
+    // Accepting a bid below the hidden minimum fails
+    let accept_bid = ExecuteMsg::AcceptBid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.to_string(),
+        credit_bps: None,
+        min_price: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_bid, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::BidTooLow(Uint128::from(80u128), Uint128::from(70u128)).to_string(),
+    );
+
+    // Accepting a bid above it succeeds
+    let accept_bid = ExecuteMsg::AcceptBid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder2.to_string(),
+        credit_bps: None,
+        min_price: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_bid, &[]);
+    assert!(res.is_ok());
+
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder2.to_string());
+}
+
+#[test]
+fn try_bid_preview() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+
+    // A bid below the ask would not match
+    let query_bid_preview = QueryMsg::BidPreview {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.to_string(),
+        price: coin(90, NATIVE_DENOM),
+    };
+    let res: BidPreviewResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_bid_preview)
+        .unwrap();
+    assert!(!res.matched);
+    assert_eq!(res.ask_price, Some(coin(100, NATIVE_DENOM)));
+
+    // A bid above the ask matches at the ask price
+    let query_bid_preview = QueryMsg::BidPreview {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.to_string(),
+        price: coin(110, NATIVE_DENOM),
+    };
+    let preview: BidPreviewResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_bid_preview)
+        .unwrap();
+    assert_eq!(preview, BidPreviewResponse {
+        matched: true,
+        ask_price: Some(coin(100, NATIVE_DENOM)),
+        sale_price: Uint128::from(100u128),
+        surplus: Uint128::from(10u128),
+        market_fee: Uint128::from(2u128),
+        royalty: Uint128::from(10u128),
+        seller_proceeds: Uint128::from(88u128),
+    });
+
+    // The actual bid pays out as previewed
+    let set_bid = ExecuteMsg::SetBid {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(110, NATIVE_DENOM),
+        refund_to: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &coins(110, NATIVE_DENOM)).unwrap();
+    for (event_type, amount) in vec![
+        ("wasm-payout-surplus", preview.surplus),
+        ("wasm-payout-market", preview.market_fee),
+        ("wasm-payout-royalty", preview.royalty),
+        ("wasm-payout-seller", preview.seller_proceeds),
+    ] {
+        let event = res.events.iter().find(|e| e.ty == event_type).unwrap();
+        assert!(event.attributes.contains(&Attribute {
+            key: String::from("coin"),
+            value: coin(amount.u128(), NATIVE_DENOM).to_string()
+        }));
+    }
+}
+
+#[test]
+fn try_accept_bid_credit() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    for token_id in vec![TOKEN_ID.to_string(), "124".to_string()] {
+        mint(&mut router, &creator, &collection, token_id.clone());
+        approve(&mut router, &creator, &collection, &marketplace, token_id.clone());
+        ask(&mut router, &creator, &marketplace, token_id.clone(), 200);
+        bid(&mut router, &bidder, &marketplace, token_id, 100);
+    }
+
+    // A credit share above 100% is rejected
+    let accept_bid = ExecuteMsg::AcceptBid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.to_string(),
+        credit_bps: Some(10_001),
+        min_price: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_bid, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidCreditShare(10_001).to_string(),
+    );
+
+    // Keep half of the net proceeds as credit: (100 - 2 fee - 10 royalty) / 2 = 44
+    let accept_bid = ExecuteMsg::AcceptBid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.to_string(),
+        credit_bps: Some(5_000),
+        min_price: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_bid, &[]).unwrap();
+    let accept_event = res.events.iter().find(|e| e.ty == "wasm-accept-bid").unwrap();
+    assert_eq!(accept_event.attributes[4], Attribute::new("credit", coin(44, NATIVE_DENOM).to_string()));
+    let payout_event = res.events.iter().find(|e| e.ty == "wasm-payout-seller").unwrap();
+    assert_eq!(payout_event.attributes[1], Attribute::new("coin", coin(44, NATIVE_DENOM).to_string()));
+
+    let query_credits = QueryMsg::Credits {
+        address: creator.to_string(),
+    };
+    let res: CreditsResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_credits)
+        .unwrap();
+    assert_eq!(res.credits, coin(44, NATIVE_DENOM));
+
+    // The next accepted bid redeems credit against its market fee
+    let accept_bid = ExecuteMsg::AcceptBid {
+        token_id: "124".to_string(),
+        bidder: bidder.to_string(),
+        credit_bps: None,
+        min_price: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_bid, &[]).unwrap();
+    let redeem_event = res.events.iter().find(|e| e.ty == "wasm-redeem-credit").unwrap();
+    assert_eq!(redeem_event.attributes[1], Attribute::new("coin", coin(2, NATIVE_DENOM).to_string()));
+
+    let res: CreditsResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_credits)
+        .unwrap();
+    assert_eq!(res.credits, coin(42, NATIVE_DENOM));
+
+    // The denom cannot change while fee credit is held in it
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: None,
+        min_price: None,
+        denom: Some(String::from("uatom")),
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: None,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: None,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        accepted_denoms: None,
+        referral_fee_share: None,
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &update_config, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidConfig(String::from("denom cannot be updated while fee credit is held")).to_string(),
+    );
+
+    // The seller withdraws the remaining credit
+    let withdraw_credit = ExecuteMsg::WithdrawCredit {};
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &withdraw_credit, &[]).unwrap();
+    let withdraw_event = res.events.iter().find(|e| e.ty == "wasm-withdraw-credit").unwrap();
+    assert_eq!(withdraw_event.attributes[1], Attribute::new("coin", coin(42, NATIVE_DENOM).to_string()));
+
+    let res: CreditsResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_credits)
+        .unwrap();
+    assert_eq!(res.credits, coin(0, NATIVE_DENOM));
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &withdraw_credit, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::NoCredit {}.to_string(),
+    );
+
+    // With no credit held the denom can change
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn try_ask_on_remove_msg() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, _bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+
+    // An empty on_remove_msg is rejected
+    let invalid_msg = "message must be a single action taking only a token_id".to_string();
+    let mut set_ask = ExecuteMsg::SetAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(100, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: Some(Binary::default()),
+        reserve_for: None,
+        non_custodial: false,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidOnRemoveMsg(invalid_msg.clone()).to_string(),
+    );
+
+    // A message acting on another escrowed NFT is rejected
+    mint(&mut router, &creator, &collection, "124".to_string());
+    let burn_other_msg = to_binary(&Cw721ExecuteMsg::<Empty>::Burn {
+        token_id: "124".to_string(),
+    }).unwrap();
+    if let ExecuteMsg::SetAsk { on_remove_msg, .. } = &mut set_ask {
+        *on_remove_msg = Some(burn_other_msg);
+    }
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidOnRemoveMsg(format!("message must target token_id {}", TOKEN_ID)).to_string(),
+    );
+
+    // So is one taking more than the token id, such as a transfer of the listed NFT
+    let transfer_msg = to_binary(&Cw721ExecuteMsg::<Empty>::TransferNft {
+        recipient: "attacker".to_string(),
+        token_id: TOKEN_ID.to_string(),
+    }).unwrap();
+    if let ExecuteMsg::SetAsk { on_remove_msg, .. } = &mut set_ask {
+        *on_remove_msg = Some(transfer_msg);
+    }
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidOnRemoveMsg(invalid_msg).to_string(),
+    );
+
+    // List with a burn message to send on removal
+    let burn_msg = to_binary(&Cw721ExecuteMsg::<Empty>::Burn {
+        token_id: TOKEN_ID.to_string(),
+    }).unwrap();
+    if let ExecuteMsg::SetAsk { on_remove_msg, .. } = &mut set_ask {
+        *on_remove_msg = Some(burn_msg);
+    }
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
+    assert!(res.is_ok());
+
+    // Removing the ask sends the message in place of returning the NFT
+    let remove_ask = ExecuteMsg::RemoveAsk {
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &remove_ask, &[]).unwrap();
+    let on_remove_event = res.events.iter().find(|e| e.ty == "wasm-on-remove-msg").unwrap();
+    assert_eq!(on_remove_event.attributes[2], Attribute::new("token_id", TOKEN_ID.to_string()));
+    assert!(res.events.iter().all(|e| e.ty != "wasm-nft-returned"));
+
+    // The NFT was burned
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: Result<OwnerOfResponse, _> = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg);
+    assert!(res.is_err());
+}
+
+#[test]
+fn try_query_can_list() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: None,
+        min_price: None,
+        denom: None,
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: None,
+        relist_cooldown_seconds: Some(3600),
+        refund_bids_on_ask_removal: None,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        accepted_denoms: None,
+        referral_fee_share: None,
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+
+    // The owner can list
+    let query_can_list = QueryMsg::CanList {
+        address: creator.to_string(),
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res: CanListResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_can_list)
+        .unwrap();
+    assert_eq!(res, CanListResponse { can_list: true, reason: None });
+
+    // Others cannot
+    let query_can_list = QueryMsg::CanList {
+        address: bidder.to_string(),
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res: CanListResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_can_list)
+        .unwrap();
+    assert_eq!(res, CanListResponse {
+        can_list: false,
+        reason: Some(ContractError::Unauthorized(String::from("only the owner can call this function")).to_string()),
+    });
+
+    // Nobody can list while trading is halted
+    let query_owner_can_list = QueryMsg::CanList {
+        address: creator.to_string(),
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &ExecuteMsg::SetHalted { halted: true }, &[]);
+    assert!(res.is_ok());
+    let res: CanListResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_owner_can_list)
+        .unwrap();
+    assert_eq!(res, CanListResponse {
+        can_list: false,
+        reason: Some(ContractError::Halted {}.to_string()),
+    });
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &ExecuteMsg::SetHalted { halted: false }, &[]);
+    assert!(res.is_ok());
+
+    // After a sale, the new owner must wait out the relist cooldown
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 100);
+    let res: CanListResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_can_list)
+        .unwrap();
+    assert_eq!(res, CanListResponse {
+        can_list: false,
+        reason: Some(ContractError::RelistCooldown(TOKEN_ID.to_string()).to_string()),
+    });
+}
+
+#[test]
+fn try_increase_collection_bid_units() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, _collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Create a bid of 2 units for 100 tokens each
+    let set_collection_bid = ExecuteMsg::SetCollectionBid {
+        units: 2,
+        price: coin(100, NATIVE_DENOM),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_collection_bid, &coins(200, NATIVE_DENOM));
+    assert!(res.is_ok());
+
+    // Underpaying the top-up is rejected
+    let increase_units = ExecuteMsg::IncreaseCollectionBidUnits { additional_units: 3 };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &increase_units, &coins(200, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::IncorrectBidPayment(Uint128::from(300u128), Uint128::from(200u128)).to_string(),
+    );
+
+    // Escrowing the price of 3 more units raises the bid to 5 units
+    let prev_balance = router.wrap().query_balance(bidder.clone(), NATIVE_DENOM).unwrap();
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &increase_units, &coins(300, NATIVE_DENOM));
+    assert!(res.is_ok());
+    let post_balance = router.wrap().query_balance(bidder.clone(), NATIVE_DENOM).unwrap();
+    assert_eq!(prev_balance.amount - post_balance.amount, Uint128::from(300u128));
+
+    let query_collection_bid = QueryMsg::CollectionBid {
+        bidder: bidder.to_string(),
+    };
+    let res: CollectionBidResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_collection_bid)
+        .unwrap();
+    assert_eq!(res.collection_bid.unwrap().units, 5);
+
+    // Removing the bid refunds the escrow for all 5 units
+    let remove_collection_bid = ExecuteMsg::RemoveCollectionBid {};
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &remove_collection_bid, &[]);
+    assert!(res.is_ok());
+    let final_balance = router.wrap().query_balance(bidder.clone(), NATIVE_DENOM).unwrap();
+    assert_eq!(final_balance.amount - post_balance.amount, Uint128::from(500u128));
+}
+
+#[test]
+fn try_set_collection_royalty() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Only operators can override royalties
+    let set_collection_royalty = ExecuteMsg::SetCollectionRoyalty {
+        collection: collection.to_string(),
+        recipient: owner.to_string(),
+        percent: Decimal::percent(5),
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_collection_royalty, &[]);
+    assert!(res.is_err());
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &set_collection_royalty, &[]);
+    assert!(res.is_ok());
+
+    // The override replaces the collection's 10% royalty to creator
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+    let set_bid = ExecuteMsg::SetBid {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(100, NATIVE_DENOM),
+        refund_to: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &coins(100, NATIVE_DENOM)).unwrap();
+    let royalty_event = res.events.iter().find(|e| e.ty == "wasm-payout-royalty").unwrap();
+    assert_eq!(royalty_event.attributes[1], Attribute::new("coin", coin(5, NATIVE_DENOM).to_string()));
+    assert_eq!(royalty_event.attributes[2], Attribute::new("recipient", owner.to_string()));
+    let seller_event = res.events.iter().find(|e| e.ty == "wasm-payout-seller").unwrap();
+    assert_eq!(seller_event.attributes[1], Attribute::new("coin", coin(93, NATIVE_DENOM).to_string()));
+
+    // The 2% trading fee and the royalty cannot take more than the sale price
+    let fee_exceeding_royalty = ExecuteMsg::SetCollectionRoyalty {
+        collection: collection.to_string(),
+        recipient: owner.to_string(),
+        percent: Decimal::percent(99),
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &fee_exceeding_royalty, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidConfig(String::from("trading fee and royalty percent must be less than or equal to 100")).to_string(),
+    );
+
+    // Nor can the trading fee be raised past what the royalty leaves
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: Some(9_600),
+        operators: None,
+        min_price: None,
+        denom: None,
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: None,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: None,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        accepted_denoms: None,
+        referral_fee_share: None,
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &update_config, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidConfig(String::from("trading fee and royalty percent must be less than or equal to 100")).to_string(),
+    );
+}
+
+#[test]
+fn try_include_sender_as_operator() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, _bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (_marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Instantiate a second marketplace with no listed operators, seeding the sender
+    let marketplace_id = router.store_code(contract_marketplace());
+    let msg = crate::msg::InstantiateMsg {
+        cw721_address: collection.to_string(),
+        denom: String::from(NATIVE_DENOM),
+        collector_address: creator.to_string(),
+        trading_fee_bps: TRADING_FEE_BPS,
+        operators: vec![],
+        include_sender_as_operator: true,
+        min_price: Uint128::from(5u128),
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: false,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: false,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        cw20_payments: false,
+        accepted_denoms: vec![],
+        referral_fee_share: None,
+    };
+    let marketplace = router
+        .instantiate_contract(marketplace_id, creator.clone(), &msg, &[], "Marketplace", None)
+        .unwrap();
+
+    let res: ConfigResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Config {})
+        .unwrap();
+    assert_eq!(res.config.operators, vec![creator.clone()]);
+}
+
+#[test]
+fn try_accept_best_bid_before() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 200);
+
+    // Without bids there is nothing to accept
+    let accept_best_bid = ExecuteMsg::AcceptBestBidBefore {
+        token_id: TOKEN_ID.to_string(),
+        not_after: block_time.plus_seconds(60),
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_best_bid, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::NoBids(TOKEN_ID.to_string()).to_string(),
+    );
+
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 100);
+    bid(&mut router, &bidder2, &marketplace, TOKEN_ID.to_string(), 110);
+
+    // An accept landing after its deadline fails
+    let late_accept = ExecuteMsg::AcceptBestBidBefore {
+        token_id: TOKEN_ID.to_string(),
+        not_after: block_time.minus_seconds(1),
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &late_accept, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::DeadlinePassed(block_time.minus_seconds(1)).to_string(),
+    );
+
+    // Before the deadline, the highest bid is accepted
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_best_bid, &[]);
+    assert!(res.is_ok());
+
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder2.to_string());
+}
+
+#[test]
+fn try_counter_with_ask() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+
+    // Countering requires incoming bids
+    let counter_with_ask = ExecuteMsg::CounterWithAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(150, NATIVE_DENOM),
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &counter_with_ask, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::NoBids(TOKEN_ID.to_string()).to_string(),
+    );
+
+    // Only the owner can counter a lowball bid
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 100);
+    let res = router.execute_contract(bidder2.clone(), marketplace.clone(), &counter_with_ask, &[]);
+    assert!(res.is_err());
+
+    // The counter lists the token and escrows the NFT
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &counter_with_ask, &[]);
+    assert!(res.is_ok());
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, marketplace.to_string());
+
+    // A bid at the counter price matches the ask
+    bid(&mut router, &bidder2, &marketplace, TOKEN_ID.to_string(), 150);
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder2.to_string());
+}
+
+#[test]
+fn try_query_highest_bids() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Two tokens with bids, one without
+    for token_id in vec!["1", "2", "3"] {
+        mint(&mut router, &creator, &collection, token_id.to_string());
+    }
+    bid(&mut router, &bidder, &marketplace, "1".to_string(), 100);
+    bid(&mut router, &bidder2, &marketplace, "1".to_string(), 120);
+    bid(&mut router, &bidder, &marketplace, "2".to_string(), 90);
+
+    let query_highest_bids = QueryMsg::HighestBids {
+        token_ids: vec!["1".to_string(), "2".to_string(), "3".to_string()],
+    };
+    let res: HighestBidsResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_highest_bids)
+        .unwrap();
+    let highest_bids = res
+        .highest_bids
+        .into_iter()
+        .map(|h| (h.token_id, h.bid.map(|b| (b.bidder, b.price.amount))))
+        .collect::<Vec<_>>();
+    assert_eq!(highest_bids, vec![
+        ("1".to_string(), Some((bidder2.clone(), Uint128::from(120u128)))),
+        ("2".to_string(), Some((bidder.clone(), Uint128::from(90u128)))),
+        ("3".to_string(), None),
+    ]);
+}
+
+#[test]
+fn try_linked_addresses() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Only operators can link addresses
+    let link_addresses = ExecuteMsg::LinkAddresses {
+        set_id: 1,
+        addresses: vec![creator.to_string(), bidder.to_string()],
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &link_addresses, &[]);
+    assert!(res.is_err());
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &link_addresses, &[]);
+    assert!(res.is_ok());
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 200);
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 100);
+    bid(&mut router, &bidder2, &marketplace, TOKEN_ID.to_string(), 100);
+
+    // The seller cannot accept a bid from a linked address
+    let accept_bid = ExecuteMsg::AcceptBid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder.to_string(),
+        credit_bps: None,
+        min_price: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_bid, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::LinkedAddresses(creator.to_string(), bidder.to_string()).to_string(),
+    );
+
+    // A bid from an unlinked address can be accepted
+    let accept_bid = ExecuteMsg::AcceptBid {
+        token_id: TOKEN_ID.to_string(),
+        bidder: bidder2.to_string(),
+        credit_bps: None,
+        min_price: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_bid, &[]);
+    assert!(res.is_ok());
+
+    // A linked bid meeting the ask does not settle it
+    let linked_error = ContractError::LinkedAddresses(creator.to_string(), bidder.to_string()).to_string();
+    mint(&mut router, &creator, &collection, "124".to_string());
+    approve(&mut router, &creator, &collection, &marketplace, "124".to_string());
+    ask(&mut router, &creator, &marketplace, "124".to_string(), 100);
+    let set_bid = ExecuteMsg::SetBid {
+        token_id: "124".to_string(),
+        price: coin(100, NATIVE_DENOM),
+        refund_to: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &coins(100, NATIVE_DENOM));
+    assert_eq!(res.unwrap_err().root_cause().to_string(), linked_error);
+
+    // Nor can a linked address buy the ask outright
+    let buy_now = ExecuteMsg::BuyNow {
+        token_id: "124".to_string(),
+        expected_price: coin(100, NATIVE_DENOM),
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &buy_now, &coins(100, NATIVE_DENOM));
+    assert_eq!(res.unwrap_err().root_cause().to_string(), linked_error);
+
+    // Nor does an ask set at or below a linked bid settle it
+    mint(&mut router, &creator, &collection, "125".to_string());
+    approve(&mut router, &creator, &collection, &marketplace, "125".to_string());
+    bid(&mut router, &bidder, &marketplace, "125".to_string(), 100);
+    let set_ask = ExecuteMsg::SetAsk {
+        token_id: "125".to_string(),
+        price: coin(100, NATIVE_DENOM),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]);
+    assert_eq!(res.unwrap_err().root_cause().to_string(), linked_error);
+
+    // An unlinked address still buys outright
+    let res = router.execute_contract(bidder2.clone(), marketplace.clone(), &buy_now, &coins(100, NATIVE_DENOM));
+    assert!(res.is_ok());
+}
+
+#[test]
+fn try_query_contract_version() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, _bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, _collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    let res: ContractVersionResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::ContractVersion {})
+        .unwrap();
+    assert_eq!(res, ContractVersionResponse {
+        contract: String::from("crates.io:marketplace-v2"),
+        version: String::from(env!("CARGO_PKG_VERSION")),
+    });
+}
+
+#[test]
+fn try_collection_royalty_splits() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Splits cannot add up to more than the sale price
+    let set_royalty_splits = ExecuteMsg::SetCollectionRoyaltySplits {
+        collection: collection.to_string(),
+        splits: vec![
+            (owner.to_string(), Decimal::percent(60)),
+            (bidder2.to_string(), Decimal::percent(50)),
+        ],
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &set_royalty_splits, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidConfig(String::from("royalty percent must be less than or equal to 100")).to_string(),
+    );
+
+    // Every share must pay something
+    let set_royalty_splits = ExecuteMsg::SetCollectionRoyaltySplits {
+        collection: collection.to_string(),
+        splits: vec![
+            (owner.to_string(), Decimal::percent(3)),
+            (bidder2.to_string(), Decimal::zero()),
+        ],
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &set_royalty_splits, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidConfig(String::from("royalty shares must be greater than zero")).to_string(),
+    );
+
+    // Each creator appears once
+    let set_royalty_splits = ExecuteMsg::SetCollectionRoyaltySplits {
+        collection: collection.to_string(),
+        splits: vec![
+            (owner.to_string(), Decimal::percent(3)),
+            (owner.to_string(), Decimal::percent(2)),
+        ],
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &set_royalty_splits, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidConfig(format!("royalty recipient {} is duplicated", owner)).to_string(),
+    );
+
+    // Together with the 2% trading fee the splits cannot take more than the sale price
+    let set_royalty_splits = ExecuteMsg::SetCollectionRoyaltySplits {
+        collection: collection.to_string(),
+        splits: vec![
+            (owner.to_string(), Decimal::percent(60)),
+            (bidder2.to_string(), Decimal::percent(39)),
+        ],
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &set_royalty_splits, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidConfig(String::from("trading fee and royalty percent must be less than or equal to 100")).to_string(),
+    );
+
+    // A 5% royalty split 3% / 2% between two creators
+    let set_royalty_splits = ExecuteMsg::SetCollectionRoyaltySplits {
+        collection: collection.to_string(),
+        splits: vec![
+            (owner.to_string(), Decimal::percent(3)),
+            (bidder2.to_string(), Decimal::percent(2)),
+        ],
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &set_royalty_splits, &[]);
+    assert!(res.is_ok());
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+    let set_bid = ExecuteMsg::SetBid {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(100, NATIVE_DENOM),
+        refund_to: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid, &coins(100, NATIVE_DENOM)).unwrap();
+
+    // Each creator is paid their split, and the seller the remainder
+    let royalty_payouts = res
+        .events
+        .iter()
+        .filter(|e| e.ty == "wasm-payout-royalty")
+        .map(|e| (e.attributes[2].value.clone(), e.attributes[1].value.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(royalty_payouts, vec![
+        (owner.to_string(), coin(3, NATIVE_DENOM).to_string()),
+        (bidder2.to_string(), coin(2, NATIVE_DENOM).to_string()),
+    ]);
+    let seller_event = res.events.iter().find(|e| e.ty == "wasm-payout-seller").unwrap();
+    assert_eq!(seller_event.attributes[1], Attribute::new("coin", coin(93, NATIVE_DENOM).to_string()));
+}
+
+#[test]
+fn try_max_collection_bid_units() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, _collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Collection bids are capped at 3 units
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: None,
+        min_price: None,
+        denom: None,
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: None,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: None,
+        display_decimals: None,
+        max_collection_bid_units: Some(3),
+        accepted_denoms: None,
+        referral_fee_share: None,
+    };
+    let res = router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    // A bid above the cap is rejected
+    let set_collection_bid = ExecuteMsg::SetCollectionBid {
+        units: 4,
+        price: coin(100, NATIVE_DENOM),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_collection_bid, &coins(400, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::TooManyUnits(3).to_string(),
+    );
+
+    // Bids up to the cap are accepted
+    let set_collection_bid = ExecuteMsg::SetCollectionBid {
+        units: 2,
+        price: coin(100, NATIVE_DENOM),
+        all_or_nothing: false,
+        only_below_last_sale: false,
+        traits: vec![],
+        budget: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_collection_bid, &coins(200, NATIVE_DENOM));
+    assert!(res.is_ok());
+    let increase_units = ExecuteMsg::IncreaseCollectionBidUnits { additional_units: 1 };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &increase_units, &coins(100, NATIVE_DENOM));
+    assert!(res.is_ok());
+
+    // Increasing past the cap is rejected
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &increase_units, &coins(100, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::TooManyUnits(3).to_string(),
+    );
+}
+
+#[test]
+fn try_accept_bids() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // One bid on each of two listed tokens
+    let token_id_2 = "124".to_string();
+    for token_id in vec![TOKEN_ID.to_string(), token_id_2.clone()] {
+        mint(&mut router, &creator, &collection, token_id.clone());
+        approve(&mut router, &creator, &collection, &marketplace, token_id.clone());
+        ask(&mut router, &creator, &marketplace, token_id, 200);
+    }
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 100);
+    bid(&mut router, &bidder2, &marketplace, token_id_2.clone(), 110);
+
+    // A batch with a missing bid is rejected as a whole
+    let accept_bids = ExecuteMsg::AcceptBids {
+        items: vec![
+            (TOKEN_ID.to_string(), bidder.to_string()),
+            (token_id_2.clone(), bidder.to_string()),
+        ],
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_bids, &[]);
+    assert!(res.is_err());
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, marketplace.to_string());
+
+    // Both bids are accepted in one call
+    let accept_bids = ExecuteMsg::AcceptBids {
+        items: vec![
+            (TOKEN_ID.to_string(), bidder.to_string()),
+            (token_id_2.clone(), bidder2.to_string()),
+        ],
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &accept_bids, &[]).unwrap();
+    assert_eq!(res.events.iter().filter(|e| e.ty == "wasm-accept-bid").count(), 2);
+
+    for (token_id, buyer) in vec![(TOKEN_ID.to_string(), &bidder), (token_id_2, &bidder2)] {
+        let query_owner_msg = Cw721QueryMsg::OwnerOf {
+            token_id,
+            include_expired: None,
+        };
+        let res: OwnerOfResponse = router
+            .wrap()
+            .query_wasm_smart(collection.clone(), &query_owner_msg)
+            .unwrap();
+        assert_eq!(res.owner, buyer.to_string());
+    }
+}
+
+#[test]
+fn try_query_liquidation_estimate() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Two tokens with bids, one without
+    for token_id in vec!["1", "2", "3"] {
+        mint(&mut router, &creator, &collection, token_id.to_string());
+    }
+    bid(&mut router, &bidder, &marketplace, "1".to_string(), 100);
+    bid(&mut router, &bidder2, &marketplace, "1".to_string(), 200);
+    bid(&mut router, &bidder, &marketplace, "2".to_string(), 50);
+
+    let query_estimate = QueryMsg::LiquidationEstimate {
+        token_ids: vec!["1".to_string(), "2".to_string(), "3".to_string()],
+    };
+    let res: LiquidationEstimateResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &query_estimate)
+        .unwrap();
+
+    // Net of the 2% fee and 10% royalty: 200 -> 176, 50 -> 44
+    let breakdown = res
+        .tokens
+        .iter()
+        .map(|t| (t.token_id.clone(), t.bid.as_ref().map(|b| b.bidder.clone()), t.net_proceeds))
+        .collect::<Vec<_>>();
+    assert_eq!(breakdown, vec![
+        ("1".to_string(), Some(bidder2.clone()), Uint128::from(176u128)),
+        ("2".to_string(), Some(bidder.clone()), Uint128::from(44u128)),
+        ("3".to_string(), None, Uint128::zero()),
+    ]);
+    assert_eq!(res.total, coin(220, NATIVE_DENOM));
+}
+
+#[test]
+fn try_cw20_payments() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (_marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // A CW20 token held by the bidder
+    let cw20_id = router.store_code(contract_cw20());
+    let msg = mock_cw20::InstantiateMsg {
+        name: String::from("Passage USD"),
+        symbol: String::from("PUSD"),
+        decimals: 6,
+        initial_balances: vec![Cw20Coin {
+            address: bidder.to_string(),
+            amount: Uint128::from(1000u128),
+        }],
+    };
+    let token = router
+        .instantiate_contract(cw20_id, creator.clone(), &msg, &[], "PUSD", None)
+        .unwrap();
+
+    // A marketplace priced in the CW20 token
+    let marketplace_id = router.store_code(contract_marketplace());
+    let msg = crate::msg::InstantiateMsg {
+        cw721_address: collection.to_string(),
+        denom: token.to_string(),
+        collector_address: "collector".to_string(),
+        trading_fee_bps: TRADING_FEE_BPS,
+        operators: vec!["operator".to_string()],
+        include_sender_as_operator: false,
+        min_price: Uint128::from(5u128),
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: false,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: false,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        cw20_payments: true,
+        accepted_denoms: vec![],
+        referral_fee_share: None,
+    };
+    let marketplace = router
+        .instantiate_contract(marketplace_id, creator.clone(), &msg, &[], "Marketplace", None)
+        .unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    let set_ask = ExecuteMsg::SetAsk {
+        token_id: TOKEN_ID.to_string(),
+        price: coin(100, token.as_str()),
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
+    };
+    router.execute_contract(creator.clone(), marketplace.clone(), &set_ask, &[]).unwrap();
+
+    let balance = |router: &App, address: &str| -> Uint128 {
+        let res: BalanceResponse = router
+            .wrap()
+            .query_wasm_smart(token.clone(), &Cw20QueryMsg::Balance { address: address.to_string() })
+            .unwrap();
+        res.balance
+    };
+    let send_bid = |price: u128| Cw20ExecuteMsg::Send {
+        contract: marketplace.to_string(),
+        amount: Uint128::from(price),
+        msg: to_binary(&ExecuteMsg::SetBid {
+            token_id: TOKEN_ID.to_string(),
+            price: coin(price, token.as_str()),
+            refund_to: None,
+            referrer: None,
+        })
+        .unwrap(),
+    };
+
+    // Only payable messages can be sent with the token
+    let send_remove_bid = Cw20ExecuteMsg::Send {
+        contract: marketplace.to_string(),
+        amount: Uint128::from(50u128),
+        msg: to_binary(&ExecuteMsg::RemoveBid { token_id: TOKEN_ID.to_string() }).unwrap(),
+    };
+    let res = router.execute_contract(bidder.clone(), token.clone(), &send_remove_bid, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidCw20Payment(String::from("message does not take a payment")).to_string()
+    );
+
+    // A bid below the ask is escrowed in the token, and refunded in the token
+    router.execute_contract(bidder.clone(), token.clone(), &send_bid(50), &[]).unwrap();
+    assert_eq!(balance(&router, bidder.as_str()), Uint128::from(950u128));
+    assert_eq!(balance(&router, marketplace.as_str()), Uint128::from(50u128));
+    let remove_bid = ExecuteMsg::RemoveBid { token_id: TOKEN_ID.to_string() };
+    router.execute_contract(bidder.clone(), marketplace.clone(), &remove_bid, &[]).unwrap();
+    assert_eq!(balance(&router, bidder.as_str()), Uint128::from(1000u128));
+
+    // A bid at the ask completes the sale, paying out in the token
+    router.execute_contract(bidder.clone(), token.clone(), &send_bid(100), &[]).unwrap();
+    assert_eq!(balance(&router, bidder.as_str()), Uint128::from(900u128));
+    // 88 net proceeds plus the 10% royalty, both to the creator
+    assert_eq!(balance(&router, creator.as_str()), Uint128::from(98u128));
+    assert_eq!(balance(&router, "collector"), Uint128::from(2u128));
+    assert_eq!(balance(&router, marketplace.as_str()), Uint128::zero());
+
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder.to_string());
+}
+
+#[test]
+fn try_accepted_denoms() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+    router
+        .sudo(CwSudoMsg::Bank(BankSudo::Mint {
+            to_address: bidder.to_string(),
+            amount: coins(1000, "uusdc"),
+        }))
+        .unwrap();
+
+    // Accept uusdc with its own min price
+    let update_config = ExecuteMsg::UpdateConfig {
+        collector_address: Some("collector".to_string()),
+        trading_fee_bps: None,
+        operators: None,
+        min_price: None,
+        denom: None,
+        min_bid_fraction_of_ask: None,
+        accumulate_fees: None,
+        relist_cooldown_seconds: None,
+        refund_bids_on_ask_removal: None,
+        display_decimals: None,
+        max_collection_bid_units: None,
+        accepted_denoms: Some(vec![coin(50, "uusdc")]),
+        referral_fee_share: None,
+    };
+    router.execute_contract(Addr::unchecked("operator"), marketplace.clone(), &update_config, &[]).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    let set_ask = |price: Coin| ExecuteMsg::SetAsk {
+        token_id: TOKEN_ID.to_string(),
+        price,
+        funds_recipient: None,
+        external_id: None,
+        min_offer: None,
+        pre_authorized: None,
+        min_net_proceeds: None,
+        hidden_min: None,
+        on_remove_msg: None,
+        reserve_for: None,
+        non_custodial: false,
+    };
+
+    // Denoms that are not accepted, or below their min price, are rejected
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask(coin(100, "uatom")), &[]);
+    assert_eq!(res.unwrap_err().root_cause().to_string(), ContractError::InvalidPrice {}.to_string());
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_ask(coin(40, "uusdc")), &[]);
+    assert_eq!(res.unwrap_err().root_cause().to_string(), ContractError::InvalidPrice {}.to_string());
+    router.execute_contract(creator.clone(), marketplace.clone(), &set_ask(coin(100, "uusdc")), &[]).unwrap();
+
+    // Bids on the listed token must be in the ask's denom
+    let set_bid = |price: Coin| ExecuteMsg::SetBid {
+        token_id: TOKEN_ID.to_string(),
+        price,
+        refund_to: None,
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid(coin(100, NATIVE_DENOM)), &coins(100, NATIVE_DENOM));
+    assert_eq!(res.unwrap_err().root_cause().to_string(), ContractError::InvalidPrice {}.to_string());
+
+    // A matching bid settles the sale in uusdc
+    router.execute_contract(bidder.clone(), marketplace.clone(), &set_bid(coin(100, "uusdc")), &coins(100, "uusdc")).unwrap();
+    let balance = |router: &App, address: &str| router.wrap().query_balance(address, "uusdc").unwrap().amount;
+    assert_eq!(balance(&router, bidder.as_str()), Uint128::from(900u128));
+    // 88 net proceeds plus the 10% royalty, both to the creator
+    assert_eq!(balance(&router, creator.as_str()), Uint128::from(98u128));
+    assert_eq!(balance(&router, "collector"), Uint128::from(2u128));
+
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder.to_string());
+}
+
+#[test]
+fn try_buy_now() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &marketplace, TOKEN_ID.to_string());
+    ask(&mut router, &creator, &marketplace, TOKEN_ID.to_string(), 100);
+    bid(&mut router, &bidder, &marketplace, TOKEN_ID.to_string(), 50);
+
+    // Fails if the ask price is not the expected price
+    let buy_now = ExecuteMsg::BuyNow {
+        token_id: TOKEN_ID.to_string(),
+        expected_price: coin(90, NATIVE_DENOM),
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &buy_now, &coins(90, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::PriceMismatch(coin(90, NATIVE_DENOM), coin(100, NATIVE_DENOM)).to_string()
+    );
+
+    // Fails if the attached funds are not the price
+    let buy_now = ExecuteMsg::BuyNow {
+        token_id: TOKEN_ID.to_string(),
+        expected_price: coin(100, NATIVE_DENOM),
+        referrer: None,
+    };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &buy_now, &coins(90, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::IncorrectBidPayment(Uint128::from(100u128), Uint128::from(90u128)).to_string()
+    );
+
+    // Buys the NFT, refunding the buyer's standing bid
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &buy_now, &coins(100, NATIVE_DENOM));
+    assert!(res.is_ok());
+    let query_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: TOKEN_ID.to_string(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &query_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, bidder.to_string());
+
+    let bidder_balance = router.wrap().query_balance(bidder.to_string(), NATIVE_DENOM).unwrap();
+    assert_eq!(bidder_balance.amount, Uint128::from(INITIAL_BALANCE - 100));
+    // The creator is seller, royalty recipient and fee collector
+    let creator_balance = router.wrap().query_balance(creator.to_string(), NATIVE_DENOM).unwrap();
+    assert_eq!(creator_balance.amount, Uint128::from(100u128));
+
+    // The ask is gone
+    let res: AskResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::Ask { token_id: TOKEN_ID.to_string() })
+        .unwrap();
+    assert_eq!(res.ask, None);
+}
+
+#[test]
+fn try_bundle_ask() {
+    let mut router = custom_mock_app();
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (marketplace, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    for token_id in vec!["1", "2", "3", "4"] {
+        mint(&mut router, &creator, &collection, token_id.to_string());
+        approve(&mut router, &creator, &collection, &marketplace, token_id.to_string());
+    }
+
+    // A bundle needs at least two distinct tokens
+    let set_bundle_ask = ExecuteMsg::SetBundleAsk {
+        token_ids: vec!["1".to_string(), "1".to_string()],
+        price: coin(101, NATIVE_DENOM),
+        funds_recipient: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_bundle_ask, &[]);
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::InvalidBundle("duplicate token_id 1".to_string()).to_string()
+    );
+
+    // Lists and escrows both tokens
+    let set_bundle_ask = ExecuteMsg::SetBundleAsk {
+        token_ids: vec!["1".to_string(), "2".to_string()],
+        price: coin(101, NATIVE_DENOM),
+        funds_recipient: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_bundle_ask, &[]);
+    assert!(res.is_ok());
+    let event = res.unwrap().events.into_iter().find(|e| e.ty == "wasm-set-bundle-ask").unwrap();
+    assert!(event.attributes.contains(&Attribute::new("token_ids", "1,2")));
+
+    let res: BundleAskResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::BundleAsk { bundle_id: 1 })
+        .unwrap();
+    assert_eq!(res.bundle_ask.unwrap().token_ids, vec!["1".to_string(), "2".to_string()]);
+
+    // Fails if the attached funds are not the bundle price
+    let buy_bundle = ExecuteMsg::BuyBundle { bundle_id: 1 };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &buy_bundle, &coins(100, NATIVE_DENOM));
+    assert_eq!(
+        res.unwrap_err().root_cause().to_string(),
+        ContractError::IncorrectBidPayment(Uint128::from(101u128), Uint128::from(100u128)).to_string()
+    );
+
+    // Buys both tokens
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &buy_bundle, &coins(101, NATIVE_DENOM));
+    assert!(res.is_ok());
+    for token_id in vec!["1", "2"] {
+        let query_owner_msg = Cw721QueryMsg::OwnerOf {
+            token_id: token_id.to_string(),
+            include_expired: None,
+        };
+        let res: OwnerOfResponse = router
+            .wrap()
+            .query_wasm_smart(collection.clone(), &query_owner_msg)
+            .unwrap();
+        assert_eq!(res.owner, bidder.to_string());
+    }
+    let bidder_balance = router.wrap().query_balance(bidder.to_string(), NATIVE_DENOM).unwrap();
+    assert_eq!(bidder_balance.amount, Uint128::from(INITIAL_BALANCE - 101));
+    // The creator is seller, royalty recipient and fee collector
+    let creator_balance = router.wrap().query_balance(creator.to_string(), NATIVE_DENOM).unwrap();
+    assert_eq!(creator_balance.amount, Uint128::from(101u128));
+
+    let res: BundleAskResponse = router
+        .wrap()
+        .query_wasm_smart(marketplace.clone(), &QueryMsg::BundleAsk { bundle_id: 1 })
+        .unwrap();
+    assert_eq!(res.bundle_ask, None);
+
+    // Only the seller can remove a bundle ask, which returns its tokens
+    let set_bundle_ask = ExecuteMsg::SetBundleAsk {
+        token_ids: vec!["3".to_string(), "4".to_string()],
+        price: coin(200, NATIVE_DENOM),
+        funds_recipient: None,
+    };
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &set_bundle_ask, &[]);
+    assert!(res.is_ok());
+
+    let remove_bundle_ask = ExecuteMsg::RemoveBundleAsk { bundle_id: 2 };
+    let res = router.execute_contract(bidder.clone(), marketplace.clone(), &remove_bundle_ask, &[]);
+    assert!(res.is_err());
+    let res = router.execute_contract(creator.clone(), marketplace.clone(), &remove_bundle_ask, &[]);
+    assert!(res.is_ok());
+    for token_id in vec!["3", "4"] {
+        let query_owner_msg = Cw721QueryMsg::OwnerOf {
+            token_id: token_id.to_string(),
+            include_expired: None,
+        };
+        let res: OwnerOfResponse = router
+            .wrap()
+            .query_wasm_smart(collection.clone(), &query_owner_msg)
+            .unwrap();
+        assert_eq!(res.owner, creator.to_string());
+    }
+}
@@ -1,28 +1,50 @@
 use crate::msg::{
-    QueryMsg, AskResponse, AsksResponse, QueryOptions, TokenPriceOffset,
-    AskCountResponse, BidResponse, BidsResponse, BidTokenPriceOffset,
-    ConfigResponse, CollectionBidResponse, CollectionBidsResponse, CollectionBidPriceOffset, TokenAddrOffset,
+    QueryMsg, AskResponse, BundleAskResponse, AsksResponse, QueryOptions, TokenPriceOffset,
+    AskCountResponse, IsMatchableResponse, BidResponse, BidsResponse, BidTokenPriceOffset,
+    ConfigResponse, OperatorsResponse, BootstrapResponse, CollectionBidResponse, CollectionBidsResponse, CollectionBidPriceOffset, TokenAddrOffset,
+    SettlementsResponse, BidPreviewResponse, CreditsResponse, CanListResponse,
+    HighestBidsResponse, TokenHighestBid, ContractVersionResponse, LiquidationEstimateResponse, TokenProceeds, HooksResponse, AddressRoles, RolesResponse, FeeSplitsResponse,
 };
 use crate::state::{
-    CONFIG, asks, TokenId, bids, bid_key, collection_bids,
+    CONFIG, asks, BUNDLE_ASKS, TokenId, bids, bid_key, collection_bids, settlements, Settlement, Bid, CREDITS, HOOKS, ROLES, FEE_SPLITS,
 };
-use crate::helpers::option_bool_to_order;
-use cosmwasm_std::{entry_point, to_binary, Addr, Binary, Deps, Env, Order, StdResult};
+use crate::error::ContractError;
+use crate::helpers::{option_bool_to_order, match_bid, market_fee, royalty_amount, only_lister, highest_bid,
+    seller_net_proceeds, is_deliverable};
+use cosmwasm_std::{entry_point, coin, to_binary, Addr, Binary, Coin, Deps, Env, MessageInfo, Order, Response, StdError, StdResult, Uint128};
+use cw2::get_contract_version;
 use cw_storage_plus::{Bound};
+use cw_utils::maybe_addr;
 
 // Query limits
 const DEFAULT_QUERY_LIMIT: u32 = 10;
 const MAX_QUERY_LIMIT: u32 = 30;
+const MAX_LEADERBOARD_SIZE: u32 = 10;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     let api = deps.api;
 
     match msg {
         QueryMsg::Config { } => to_binary(&query_config(deps)?),
+        QueryMsg::Operators { } => to_binary(&query_operators(deps)?),
+        QueryMsg::FeeSplits { } => to_binary(&query_fee_splits(deps)?),
+        QueryMsg::Roles {
+            start_after,
+            limit,
+        } => to_binary(&query_roles(deps, maybe_addr(api, start_after)?, limit)?),
+        QueryMsg::Hooks { } => to_binary(&query_hooks(deps)?),
+        QueryMsg::ContractVersion { } => to_binary(&query_contract_version(deps)?),
+        QueryMsg::Bootstrap { } => to_binary(&query_bootstrap(deps)?),
         QueryMsg::Ask {
             token_id,
         } => to_binary(&query_ask(deps, token_id)?),
+        QueryMsg::BundleAsk {
+            bundle_id,
+        } => to_binary(&query_bundle_ask(deps, bundle_id)?),
+        QueryMsg::IsMatchable {
+            token_id,
+        } => to_binary(&query_is_matchable(deps, env, token_id)?),
         QueryMsg::AsksSortedByPrice {
             query_options
         } => to_binary(&query_asks_sorted_by_price(
@@ -35,6 +57,15 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             deps,
             &query_options,
         )?),
+        QueryMsg::CanList {
+            address,
+            token_id,
+        } => to_binary(&query_can_list(
+            deps,
+            env.clone(),
+            api.addr_validate(&address)?,
+            token_id,
+        )?),
         QueryMsg::AskCount { } => to_binary(&query_ask_count(deps)?),
         QueryMsg::Bid {
             token_id,
@@ -52,6 +83,31 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             token_id,
             &query_options,
         )?),
+        QueryMsg::BidPreview {
+            token_id,
+            bidder,
+            price,
+        } => to_binary(&query_bid_preview(
+            deps,
+            env,
+            token_id,
+            api.addr_validate(&bidder)?,
+            price,
+        )?),
+        QueryMsg::BidLeaderboard {
+            token_id,
+            top_n,
+        } => to_binary(&query_bid_leaderboard(
+            deps,
+            token_id,
+            top_n,
+        )?),
+        QueryMsg::HighestBids {
+            token_ids,
+        } => to_binary(&query_highest_bids(deps, token_ids)?),
+        QueryMsg::LiquidationEstimate {
+            token_ids,
+        } => to_binary(&query_liquidation_estimate(deps, token_ids)?),
         QueryMsg::BidsByBidder {
             query_options,
         } => to_binary(&query_bids_by_bidder(
@@ -70,6 +126,19 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             deps,
             &query_options,
         )?),
+        QueryMsg::Credits {
+            address,
+        } => to_binary(&query_credits(deps, api.addr_validate(&address)?)?),
+        QueryMsg::Settlements {
+            buyer,
+            seller,
+            query_options,
+        } => to_binary(&query_settlements(
+            deps,
+            maybe_addr(api, buyer)?,
+            maybe_addr(api, seller)?,
+            &query_options,
+        )?),
     }
 }
 
@@ -79,12 +148,89 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     Ok(ConfigResponse { config })
 }
 
+pub fn query_operators(deps: Deps) -> StdResult<OperatorsResponse> {
+    let config = CONFIG.load(deps.storage)?;
+
+    Ok(OperatorsResponse { operators: config.operators })
+}
+
+pub fn query_fee_splits(deps: Deps) -> StdResult<FeeSplitsResponse> {
+    let fee_splits = FEE_SPLITS.may_load(deps.storage)?;
+
+    Ok(FeeSplitsResponse { fee_splits })
+}
+
+pub fn query_roles(deps: Deps, start_after: Option<Addr>, limit: Option<u32>) -> StdResult<RolesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
+
+    let roles = ROLES
+        .range(deps.storage, start_after.as_ref().map(Bound::exclusive), None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(address, roles)| AddressRoles { address, roles }))
+        .collect::<StdResult<_>>()?;
+
+    Ok(RolesResponse { roles })
+}
+
+pub fn query_hooks(deps: Deps) -> StdResult<HooksResponse> {
+    let hooks = HOOKS.may_load(deps.storage)?.unwrap_or_default();
+
+    Ok(HooksResponse { hooks })
+}
+
+pub fn query_contract_version(deps: Deps) -> StdResult<ContractVersionResponse> {
+    let contract_version = get_contract_version(deps.storage)?;
+
+    Ok(ContractVersionResponse {
+        contract: contract_version.contract,
+        version: contract_version.version,
+    })
+}
+
+pub fn query_bootstrap(deps: Deps) -> StdResult<BootstrapResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let ask_count = query_ask_count(deps)?.count;
+    let bid_count = bids()
+        .keys_raw(deps.storage, None, None, Order::Ascending)
+        .count() as u32;
+    let collection_bid_count = collection_bids()
+        .keys_raw(deps.storage, None, None, Order::Ascending)
+        .count() as u32;
+
+    Ok(BootstrapResponse {
+        config,
+        ask_count,
+        bid_count,
+        collection_bid_count,
+    })
+}
+
 pub fn query_ask(deps: Deps, token_id: TokenId) -> StdResult<AskResponse> {
-    let ask = asks().may_load(deps.storage, token_id)?;
+    let ask = asks().may_load(deps.storage, token_id)?.map(|a| a.public());
 
     Ok(AskResponse { ask })
 }
 
+pub fn query_bundle_ask(deps: Deps, bundle_id: u64) -> StdResult<BundleAskResponse> {
+    let bundle_ask = BUNDLE_ASKS.may_load(deps.storage, bundle_id)?;
+
+    Ok(BundleAskResponse { bundle_ask })
+}
+
+/// Whether anyone could buy the token now: trading is not halted, and its ask is not reserved for
+/// one buyer and can deliver the NFT, as checked when an ask is matched
+pub fn query_is_matchable(deps: Deps, env: Env, token_id: TokenId) -> StdResult<IsMatchableResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let is_matchable = match asks().may_load(deps.storage, token_id)? {
+        Some(ask) if !config.halted && ask.reserve_for.is_none() => {
+            !ask.non_custodial || is_deliverable(deps, &env, &ask, &config)?
+        },
+        _ => false,
+    };
+
+    Ok(IsMatchableResponse { is_matchable })
+}
+
 pub fn query_asks_sorted_by_price(
     deps: Deps,
     query_options: &QueryOptions<TokenPriceOffset>
@@ -100,7 +246,7 @@ pub fn query_asks_sorted_by_price(
         .price
         .range(deps.storage, start, None, order)
         .take(limit)
-        .map(|res| res.map(|item| item.1))
+        .map(|res| res.map(|item| item.1.public()))
         .collect::<StdResult<Vec<_>>>()?;
 
     Ok(AsksResponse { asks })
@@ -121,12 +267,30 @@ pub fn query_asks_by_seller(
         .seller
         .range(deps.storage, start, None, order)
         .take(limit)
-        .map(|res| res.map(|item| item.1))
+        .map(|res| res.map(|item| item.1.public()))
         .collect::<StdResult<Vec<_>>>()?;
 
     Ok(AsksResponse { asks })
 }
 
+pub fn query_can_list(
+    deps: Deps,
+    env: Env,
+    address: Addr,
+    token_id: TokenId,
+) -> StdResult<CanListResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.halted {
+        return Ok(CanListResponse { can_list: false, reason: Some(ContractError::Halted {}.to_string()) });
+    }
+    let info = MessageInfo { sender: address, funds: vec![] };
+
+    Ok(match only_lister(deps, &env, &info, &token_id, &config) {
+        Ok(_) => CanListResponse { can_list: true, reason: None },
+        Err(err) => CanListResponse { can_list: false, reason: Some(err.to_string()) },
+    })
+}
+
 pub fn query_ask_count(deps: Deps) -> StdResult<AskCountResponse> {
     let count = asks()
         .keys_raw(deps.storage, None, None, Order::Ascending)
@@ -168,6 +332,114 @@ pub fn query_bids_token_price(
     Ok(BidsResponse { bids })
 }
 
+/// Runs the same matching as `execute_set_bid` without changing state
+pub fn query_bid_preview(
+    deps: Deps,
+    env: Env,
+    token_id: TokenId,
+    bidder: Addr,
+    price: Coin,
+) -> StdResult<BidPreviewResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let ask_price = asks().may_load(deps.storage, token_id.clone())?.map(|a| a.price);
+    let bid = Bid {
+        token_id,
+        bidder,
+        price,
+        refund_to: None,
+    };
+
+    let mut preview = BidPreviewResponse {
+        matched: false,
+        ask_price,
+        sale_price: Uint128::zero(),
+        surplus: Uint128::zero(),
+        market_fee: Uint128::zero(),
+        royalty: Uint128::zero(),
+        seller_proceeds: Uint128::zero(),
+    };
+
+    if let Some(ask) = match_bid(deps, &env, &bid, &mut Response::new())? {
+        let sale_price = ask.sale_price_for(&bid.bidder);
+        preview.matched = true;
+        preview.sale_price = sale_price;
+        preview.surplus = bid.price.amount - sale_price;
+        preview.market_fee = market_fee(sale_price, &config);
+        preview.royalty = royalty_amount(deps, sale_price, &config)?;
+        preview.seller_proceeds = sale_price
+            .checked_sub(preview.market_fee)?
+            .checked_sub(preview.royalty)?;
+    }
+
+    Ok(preview)
+}
+
+pub fn query_bid_leaderboard(
+    deps: Deps,
+    token_id: TokenId,
+    top_n: u32,
+) -> StdResult<BidsResponse> {
+    query_bids_token_price(
+        deps,
+        token_id,
+        &QueryOptions {
+            descending: Some(true),
+            start_after: None,
+            limit: Some(top_n.min(MAX_LEADERBOARD_SIZE)),
+        },
+    )
+}
+
+pub fn query_highest_bids(
+    deps: Deps,
+    token_ids: Vec<TokenId>,
+) -> StdResult<HighestBidsResponse> {
+    if token_ids.len() > MAX_QUERY_LIMIT as usize {
+        return Err(StdError::generic_err(format!("at most {} token ids per query", MAX_QUERY_LIMIT)));
+    }
+
+    let highest_bids = token_ids
+        .into_iter()
+        .map(|token_id| -> StdResult<_> {
+            let bid = highest_bid(deps, &token_id)?;
+            Ok(TokenHighestBid { token_id, bid })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(HighestBidsResponse { highest_bids })
+}
+
+pub fn query_liquidation_estimate(
+    deps: Deps,
+    token_ids: Vec<TokenId>,
+) -> StdResult<LiquidationEstimateResponse> {
+    if token_ids.len() > MAX_QUERY_LIMIT as usize {
+        return Err(StdError::generic_err(format!("at most {} token ids per query", MAX_QUERY_LIMIT)));
+    }
+    let config = CONFIG.load(deps.storage)?;
+
+    let tokens = token_ids
+        .into_iter()
+        .map(|token_id| -> StdResult<_> {
+            let bid = highest_bid(deps, &token_id)?;
+            let net_proceeds = match &bid {
+                Some(bid) => seller_net_proceeds(deps, bid.price.amount, &config)?,
+                None => Uint128::zero(),
+            };
+            Ok(TokenProceeds { token_id, bid, net_proceeds })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    let total = tokens
+        .iter()
+        .filter(|token| token.bid.as_ref().map_or(false, |bid| bid.price.denom == config.denom))
+        .fold(Uint128::zero(), |total, token| total + token.net_proceeds);
+
+    Ok(LiquidationEstimateResponse {
+        total: coin(total.u128(), config.denom),
+        tokens,
+    })
+}
+
 pub fn query_bids_by_bidder(
     deps: Deps,
     query_options: &QueryOptions<TokenAddrOffset>
@@ -216,3 +488,53 @@ pub fn query_collection_bids_by_price(
 
     Ok(CollectionBidsResponse { collection_bids })
 }
+
+pub fn query_credits(deps: Deps, address: Addr) -> StdResult<CreditsResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let credits = CREDITS.may_load(deps.storage, &address)?.unwrap_or_default();
+
+    Ok(CreditsResponse { credits: coin(credits.u128(), config.denom) })
+}
+
+pub fn query_settlements(
+    deps: Deps,
+    buyer: Option<Addr>,
+    seller: Option<Addr>,
+    query_options: &QueryOptions<u64>,
+) -> StdResult<SettlementsResponse> {
+    let limit = query_options.limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
+    let order = match query_options.descending {
+        Some(false) => Order::Ascending,
+        _ => Order::Descending,
+    };
+    let offset = query_options.start_after.map(Bound::exclusive);
+    let (min, max) = match order {
+        Order::Ascending => (offset, None),
+        Order::Descending => (None, offset),
+    };
+
+    let items: Box<dyn Iterator<Item = StdResult<(u64, Settlement)>> + '_> = match (&buyer, &seller) {
+        (Some(buyer), _) => settlements()
+            .idx
+            .buyer
+            .prefix(buyer.clone())
+            .range(deps.storage, min, max, order),
+        (None, Some(seller)) => settlements()
+            .idx
+            .seller
+            .prefix(seller.clone())
+            .range(deps.storage, min, max, order),
+        (None, None) => settlements().range(deps.storage, min, max, order),
+    };
+
+    let settlements = items
+        .filter(|item| match (item, &seller) {
+            (Ok((_, settlement)), Some(seller)) => &settlement.seller == seller,
+            _ => true,
+        })
+        .take(limit)
+        .map(|item| item.map(|(_, s)| s))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(SettlementsResponse { settlements })
+}
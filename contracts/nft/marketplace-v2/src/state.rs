@@ -1,5 +1,5 @@
-use cosmwasm_std::{Addr, Decimal, Uint128, Coin};
-use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex};
+use cosmwasm_std::{Addr, Binary, Decimal, Uint128, Coin, Timestamp};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -17,12 +17,197 @@ pub struct Config {
     pub operators: Vec<Addr>,
     /// Min value for a bid
     pub min_price: Uint128,
+    /// Minimum bid on a listed NFT, as a fraction of the ask price
+    pub min_bid_fraction_of_ask: Option<Decimal>,
+    /// Hold marketplace fees in the contract until they are distributed
+    #[serde(default)]
+    pub accumulate_fees: bool,
+    /// Seconds after a sale before the token can be listed again
+    pub relist_cooldown_seconds: Option<u64>,
+    /// Refund and remove bids on a token when its ask is removed
+    #[serde(default)]
+    pub refund_bids_on_ask_removal: bool,
+    /// Decimals used to format the price_display attribute on events
+    pub display_decimals: Option<u32>,
+    /// Max units of a single collection bid
+    pub max_collection_bid_units: Option<u32>,
+    /// Prices are in the CW20 token at the denom address, paid in through Receive
+    #[serde(default)]
+    pub cw20_payments: bool,
+    /// Denoms accepted besides denom, each with its own min price
+    #[serde(default)]
+    pub accepted_denoms: Vec<Coin>,
+    /// Trading is paused, only removing asks and bids still works so users can exit
+    #[serde(default)]
+    pub halted: bool,
+    /// Share of the market fee paid to the referrer of a sale
+    pub referral_fee_share: Option<Decimal>,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
+/// A permission granted to an address besides the operators, who hold every role
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Distribute collected fees
+    FeeAdmin,
+    /// Update the config, except the operators
+    ParamsAdmin,
+    /// Halt and resume trading
+    Pauser,
+    /// Set collection royalties and link addresses
+    CollectionManager,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::FeeAdmin => "fee_admin",
+            Role::ParamsAdmin => "params_admin",
+            Role::Pauser => "pauser",
+            Role::CollectionManager => "collection_manager",
+        }
+    }
+}
+
+/// The roles granted to each address
+pub const ROLES: Map<&Addr, Vec<Role>> = Map::new("roles");
+
+/// Config of the legacy marketplace, stored under the same key and replaced by `Config` on migration
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyConfig {
+    pub admin: Addr,
+    pub nft_contract_addr: Addr,
+    pub allowed_native: String,
+    pub fee_percentage: Decimal,
+    pub collector_addr: Addr,
+}
+
+pub const LEGACY_CONFIG: Item<LegacyConfig> = Item::new("config");
+
+/// A listing of the legacy marketplace, the NFT stays with its owner
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyToken {
+    pub id: String,
+    pub price: Uint128,
+    pub on_sale: bool,
+}
+
+/// Defines the on_sale index of the legacy token map, so migrated listings leave no index entries
+pub struct LegacyTokenIndices<'a> {
+    pub on_sale: MultiIndex<'a, &'a [u8], LegacyToken, String>,
+}
+
+impl<'a> IndexList<LegacyToken> for LegacyTokenIndices<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<LegacyToken>> + '_> {
+        let v: Vec<&dyn Index<LegacyToken>> = vec![&self.on_sale];
+        Box::new(v.into_iter())
+    }
+}
+
+const LEGACY_ON_SALE: &[u8] = &[0u8];
+const LEGACY_NON_SALE: &[u8] = &[1u8];
+
+/// The legacy marketplace's token map, emptied as its listings are migrated
+pub fn legacy_tokens<'a>() -> IndexedMap<'a, String, LegacyToken, LegacyTokenIndices<'a>> {
+    let indexes = LegacyTokenIndices {
+        on_sale: MultiIndex::new(
+            |d: &LegacyToken| if d.on_sale { LEGACY_ON_SALE } else { LEGACY_NON_SALE },
+            "tokens",
+            "tokens__on_sale",
+        ),
+    };
+    IndexedMap::new("tokens", indexes)
+}
+
+/// Marketplace fees held by the contract, in the config denom
+pub const COLLECTED_FEES: Item<Uint128> = Item::new("collected_fees");
+
 pub type TokenId = String;
 
+/// The price of the most recent marketplace sale of each token
+pub const LAST_SALE_PRICES: Map<&str, Coin> = Map::new("last_sale_prices");
+
+/// The time of the most recent marketplace sale of each token
+pub const LAST_SALE_TIMES: Map<&str, Timestamp> = Map::new("last_sale_times");
+
+/// Marketplace fee credit held for each seller, in the config denom
+pub const CREDITS: Map<&Addr, Uint128> = Map::new("credits");
+
+/// A royalty set by operators for a collection, taking precedence over the collection's own royalty info
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollectionRoyalty {
+    /// Each creator and their share of the sale price, e.g. 0.05 for 5%.
+    /// The total royalty is the sum of the shares.
+    pub splits: Vec<(Addr, Decimal)>,
+}
+
+impl CollectionRoyalty {
+    /// The total royalty, as a share of the sale price
+    pub fn percent(&self) -> Decimal {
+        self.splits
+            .iter()
+            .fold(Decimal::zero(), |total, (_, share)| total + *share)
+    }
+
+    /// The royalty paid to each creator on a sale
+    pub fn payouts(&self, payment_amount: Uint128) -> Vec<(Addr, Uint128)> {
+        self.splits
+            .iter()
+            .map(|(recipient, share)| (recipient.clone(), payment_amount * *share))
+            .collect()
+    }
+}
+
+pub const COLLECTION_ROYALTIES: Map<&Addr, CollectionRoyalty> = Map::new("collection_royalties");
+
+/// How the market fee of each sale is split, in place of paying it all to the collector
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeSplits {
+    /// Each recipient and their share of the market fee
+    pub recipients: Vec<(Addr, Decimal)>,
+    /// The share of the market fee that is burned
+    pub burn_share: Decimal,
+}
+
+impl FeeSplits {
+    /// The fee paid to each recipient and the fee burned. The burn, or the last recipient
+    /// when nothing is burned, receives any rounding remainder.
+    pub fn payouts(&self, market_fee: Uint128) -> (Vec<(Addr, Uint128)>, Uint128) {
+        let mut payouts = self
+            .recipients
+            .iter()
+            .map(|(recipient, share)| (recipient.clone(), market_fee * *share))
+            .collect::<Vec<_>>();
+        let paid = payouts
+            .iter()
+            .fold(Uint128::zero(), |total, (_, amount)| total + *amount);
+        if !self.burn_share.is_zero() {
+            return (payouts, market_fee - paid);
+        }
+        if let Some((_, amount)) = payouts.last_mut() {
+            *amount += market_fee - paid;
+        }
+        (payouts, Uint128::zero())
+    }
+}
+
+pub const FEE_SPLITS: Item<FeeSplits> = Item::new("fee_splits");
+
+/// Operator maintained sets of addresses controlled by the same party, by set id.
+/// Linked addresses cannot accept each other's bids.
+pub const LINKED_ADDRESSES: Map<&Addr, u64> = Map::new("linked_addresses");
+
+/// The secp256k1 public key each seller signs off-chain asks with
+pub const SIGNING_KEYS: Map<&Addr, Binary> = Map::new("signing_keys");
+
+/// Contracts notified of new asks, new bids and finalized sales
+pub const HOOKS: Item<Vec<Addr>> = Item::new("hooks");
+
+/// Nonces of signed asks that have already been filled, per seller
+pub const SIGNED_ASK_NONCES: Map<(&Addr, u64), bool> = Map::new("signed_ask_nonces");
+
 pub trait Recipient {
     fn get_recipient(&self) -> Addr;
 }
@@ -34,6 +219,39 @@ pub struct Ask {
     pub seller: Addr,
     pub price: Coin,
     pub funds_recipient: Option<Addr>,
+    /// Off-chain order id, for reconciliation with external order books
+    pub external_id: Option<String>,
+    /// Bids below this amount are rejected, bids between it and the price are stored as offers
+    pub min_offer: Option<Uint128>,
+    /// A bidder and agreed price, a bid from that bidder at or above the price settles immediately
+    pub pre_authorized: Option<(Addr, Uint128)>,
+    /// Bids are only matched when the seller's proceeds after fees and royalties reach this amount
+    pub min_net_proceeds: Option<Uint128>,
+    /// The lowest bid the seller can accept, cleared from query responses
+    pub hidden_min: Option<Uint128>,
+    /// A message sent to the collection in place of returning the NFT when the ask is removed,
+    /// e.g. to burn or redeem a wrapper token. It must be a single action taking only this token_id.
+    pub on_remove_msg: Option<Binary>,
+    /// The only buyer the NFT can be sold to by a bid or a purchase, for private sales
+    pub reserve_for: Option<Addr>,
+    /// The NFT stays with the seller, who approves the marketplace to transfer it at sale time
+    #[serde(default)]
+    pub non_custodial: bool,
+}
+
+impl Ask {
+    /// The ask as returned by queries, without the seller's hidden minimum
+    pub fn public(self) -> Ask {
+        Ask { hidden_min: None, ..self }
+    }
+
+    /// The price the given bidder pays to settle this ask
+    pub fn sale_price_for(&self, bidder: &Addr) -> Uint128 {
+        match &self.pre_authorized {
+            Some((authorized, price)) if authorized == bidder => *price,
+            _ => self.price.amount,
+        }
+    }
 }
 
 impl Recipient for Ask {
@@ -67,12 +285,43 @@ pub fn asks<'a>() -> IndexedMap<'a, AskKey, Ask, AskIndices<'a>> {
     IndexedMap::new("asks", indexes)
 }
 
+/// An ask selling several NFTs together at a single price
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BundleAsk {
+    pub id: u64,
+    pub token_ids: Vec<TokenId>,
+    pub seller: Addr,
+    pub price: Coin,
+    pub funds_recipient: Option<Addr>,
+}
+
+impl Recipient for BundleAsk {
+    fn get_recipient(&self) -> Addr {
+        let self_cpy = self.clone();
+        self_cpy.funds_recipient.map_or(self_cpy.seller, |a| a)
+    }
+}
+
+pub const BUNDLE_ASKS: Map<u64, BundleAsk> = Map::new("bundle_asks");
+
+/// Id of the most recently created bundle ask
+pub const BUNDLE_ASK_SEQ: Item<u64> = Item::new("bundle_ask_seq");
+
 /// Represents a bid (offer) on the marketplace
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Bid {
     pub token_id: TokenId,
     pub bidder: Addr,
     pub price: Coin,
+    /// Address that receives refunds instead of the bidder
+    pub refund_to: Option<Addr>,
+}
+
+impl Recipient for Bid {
+    fn get_recipient(&self) -> Addr {
+        let self_cpy = self.clone();
+        self_cpy.refund_to.map_or(self_cpy.bidder, |a| a)
+    }
 }
 
 /// Primary key for bids: (token_id, bidder)
@@ -109,17 +358,38 @@ pub fn bids<'a>() -> IndexedMap<'a, BidKey, Bid, BidIndices<'a>> {
     IndexedMap::new("bids", indexes)
 }
 
+/// A metadata attribute a token must carry to fill a collection bid
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TraitFilter {
+    pub trait_type: String,
+    pub value: String,
+}
+
 /// Represents a bid (offer) across an entire collection in the marketplace
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct CollectionBid {
     pub bidder: Addr,
     pub units: u32,
     pub price: Coin,
+    /// When true, the bid can only be accepted for all remaining units at once
+    #[serde(default)]
+    pub all_or_nothing: bool,
+    /// When true, the bid can only be accepted for tokens last sold below the bid price
+    #[serde(default)]
+    pub only_below_last_sale: bool,
+    /// Attributes a token must all carry to fill the bid, any token when empty
+    #[serde(default)]
+    pub traits: Vec<TraitFilter>,
+    /// Remaining escrow of a budget bid, which pays up to `price` per unit until it is spent
+    pub budget: Option<Uint128>,
 }
 
 impl CollectionBid {
     pub fn total_cost(&self) -> u128 {
-        &self.price.amount.u128() * u128::from(self.units)
+        match self.budget {
+            Some(budget) => budget.u128(),
+            None => &self.price.amount.u128() * u128::from(self.units),
+        }
     }
 }
 
@@ -147,3 +417,41 @@ pub fn collection_bids<'a>(
     };
     IndexedMap::new("col_bids", indexes)
 }
+
+/// A completed sale, kept for the settlements query
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Settlement {
+    pub id: u64,
+    pub token_id: TokenId,
+    pub buyer: Addr,
+    pub seller: Addr,
+    pub price: Coin,
+    pub time: Timestamp,
+}
+
+/// Id of the most recent settlement, emitted as `sale_seq` to give a total ordering of sales
+pub const SETTLEMENT_SEQ: Item<u64> = Item::new("settlement_seq");
+
+/// Primary key for settlements
+pub type SettlementKey = u64;
+
+/// Defines indices for accessing settlements
+pub struct SettlementIndices<'a> {
+    pub buyer: MultiIndex<'a, Addr, Settlement, SettlementKey>,
+    pub seller: MultiIndex<'a, Addr, Settlement, SettlementKey>,
+}
+
+impl<'a> IndexList<Settlement> for SettlementIndices<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Settlement>> + '_> {
+        let v: Vec<&dyn Index<Settlement>> = vec![&self.buyer, &self.seller];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn settlements<'a>() -> IndexedMap<'a, SettlementKey, Settlement, SettlementIndices<'a>> {
+    let indexes = SettlementIndices {
+        buyer: MultiIndex::new(|d: &Settlement| d.buyer.clone(), "settlements", "settlements__buyer"),
+        seller: MultiIndex::new(|d: &Settlement| d.seller.clone(), "settlements", "settlements__seller"),
+    };
+    IndexedMap::new("settlements", indexes)
+}
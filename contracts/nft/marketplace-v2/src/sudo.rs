@@ -0,0 +1,86 @@
+use crate::error::ContractError;
+use crate::execute::{set_halted, update_config};
+use crate::helpers::validate_config;
+use crate::msg::SudoMsg;
+use crate::state::CONFIG;
+use cosmwasm_std::{entry_point, Addr, DepsMut, Env, Event, Response};
+
+/// Lets the chain's governance module manage the marketplace without an operator key
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    let api = deps.api;
+
+    match msg {
+        SudoMsg::UpdateParams {
+            collector_address,
+            trading_fee_bps,
+            min_price,
+            denom,
+            min_bid_fraction_of_ask,
+            accumulate_fees,
+            relist_cooldown_seconds,
+            refund_bids_on_ask_removal,
+            display_decimals,
+            max_collection_bid_units,
+            accepted_denoms,
+            referral_fee_share,
+        } => {
+            let config = update_config(
+                deps,
+                collector_address,
+                trading_fee_bps,
+                None,
+                min_price,
+                denom,
+                min_bid_fraction_of_ask,
+                accumulate_fees,
+                relist_cooldown_seconds,
+                refund_bids_on_ask_removal,
+                display_decimals,
+                max_collection_bid_units,
+                accepted_denoms,
+                referral_fee_share,
+            )?;
+
+            let event = Event::new("sudo-update-params")
+                .add_attribute("collector_address", config.collector_address)
+                .add_attribute("trading_fee_percent", config.trading_fee_percent.to_string())
+                .add_attribute("min_price", config.min_price)
+                .add_attribute("denom", config.denom);
+
+            Ok(Response::new().add_event(event))
+        }
+        SudoMsg::SetHalted { halted } => set_halted(deps, halted),
+        SudoMsg::AddOperator { operator } => sudo_add_operator(deps, api.addr_validate(&operator)?),
+        SudoMsg::RemoveOperator { operator } => sudo_remove_operator(deps, api.addr_validate(&operator)?),
+    }
+}
+
+pub fn sudo_add_operator(deps: DepsMut, operator: Addr) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if config.operators.contains(&operator) {
+        return Err(ContractError::InvalidConfig(format!("{} is already an operator", operator)));
+    }
+    config.operators.push(operator.clone());
+    CONFIG.save(deps.storage, &config)?;
+
+    let event = Event::new("sudo-add-operator")
+        .add_attribute("operator", operator);
+
+    Ok(Response::new().add_event(event))
+}
+
+pub fn sudo_remove_operator(deps: DepsMut, operator: Addr) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if !config.operators.contains(&operator) {
+        return Err(ContractError::InvalidConfig(format!("{} is not an operator", operator)));
+    }
+    config.operators.retain(|a| a != &operator);
+    validate_config(&config)?;
+    CONFIG.save(deps.storage, &config)?;
+
+    let event = Event::new("sudo-remove-operator")
+        .add_attribute("operator", operator);
+
+    Ok(Response::new().add_event(event))
+}
@@ -81,6 +81,8 @@ fn setup_whitelist_contract(router: &mut App, creator: &Addr) -> Addr {
         unit_price: coin(WHITELIST_AMOUNT, NATIVE_DENOM),
         per_address_limit: WL_PER_ADDRESS_LIMIT,
         member_limit: 1000,
+        start_time_grace_period: 0,
+        minter: None,
     };
     router
         .instantiate_contract(
@@ -478,6 +480,7 @@ fn mint_count_query() {
     // Add buyer to whitelist
     let inner_msg = AddMembersMsg {
         to_add: vec![buyer.to_string()],
+        tag: None,
     };
     let wasm_msg = WhitelistExecuteMsg::AddMembers(inner_msg);
     let res = router.execute_contract(creator.clone(), whitelist_addr, &wasm_msg, &[]);
@@ -725,6 +728,7 @@ fn whitelist_access_len_add_remove_expiration() {
     // Add buyer to whitelist
     let inner_msg = AddMembersMsg {
         to_add: vec![buyer.to_string()],
+        tag: None,
     };
     let wasm_msg = WhitelistExecuteMsg::AddMembers(inner_msg);
     let res = router.execute_contract(creator.clone(), whitelist_addr.clone(), &wasm_msg, &[]);
@@ -815,7 +819,7 @@ fn whitelist_access_len_add_remove_expiration() {
     );
 
     // Remove buyer from whitelist
-    let inner_msg = AddMembersMsg { to_add: vec![] };
+    let inner_msg = AddMembersMsg { to_add: vec![], tag: None };
     let wasm_msg = WhitelistExecuteMsg::AddMembers(inner_msg);
     let res = router.execute_contract(creator.clone(), whitelist_addr, &wasm_msg, &[]);
     assert!(res.is_ok());
@@ -1,14 +1,14 @@
 use crate::error::ContractError;
 use crate::msg::{
     AddMembersMsg, ConfigResponse, ExecuteMsg, HasEndedResponse, HasMemberResponse,
-    HasStartedResponse, InstantiateMsg, IsActiveResponse, MembersResponse, QueryMsg,
-    RemoveMembersMsg,
+    HasStartedResponse, InstantiateMsg, IsActiveResponse, MemberCountResponse, MembersResponse,
+    MinterHookMsg, QueryMsg, RemoveMembersMsg,
 };
-use crate::state::{Config, CONFIG, WHITELIST};
+use crate::state::{Config, Member, CONFIG, MINTER_NOTIFIED, TAG_COUNTS, TAG_MEMBERS, WHITELIST};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, StdResult, Response};
-use cosmwasm_std::{Order, Timestamp};
+use cosmwasm_std::{Order, Timestamp, WasmMsg};
 use cw2::set_contract_version;
 use cw_storage_plus::Bound;
 use cw_utils::{maybe_addr};
@@ -54,6 +54,11 @@ pub fn instantiate(
     msg.members.sort_unstable();
     msg.members.dedup();
 
+    let minter = msg
+        .minter
+        .map(|minter| deps.api.addr_validate(&minter))
+        .transpose()?;
+
     let config = Config {
         admin: info.sender.clone(),
         start_time: msg.start_time,
@@ -62,6 +67,8 @@ pub fn instantiate(
         unit_price: msg.unit_price,
         per_address_limit: msg.per_address_limit,
         member_limit: msg.member_limit,
+        start_time_grace_period: msg.start_time_grace_period,
+        minter,
     };
     CONFIG.save(deps.storage, &config)?;
 
@@ -88,7 +95,7 @@ pub fn instantiate(
 
     for member in msg.members.into_iter() {
         let addr = deps.api.addr_validate(&member.clone())?;
-        WHITELIST.save(deps.storage, addr, &true)?;
+        WHITELIST.save(deps.storage, addr, &Member { tag: None })?;
     }
 
     Ok(Response::new()
@@ -117,6 +124,7 @@ pub fn execute(
         ExecuteMsg::IncreaseMemberLimit(member_limit) => {
             execute_increase_member_limit(deps, info, member_limit)
         }
+        ExecuteMsg::NotifyMinter {} => execute_notify_minter(deps, env),
     }
 }
 
@@ -131,9 +139,18 @@ pub fn execute_update_start_time(
         return Err(ContractError::Unauthorized {});
     }
 
-    // don't allow updating start time if whitelist is active
+    // don't allow updating start time if whitelist is active, unless it is
+    // within the grace period, the start time is pushed back and no member can
+    // have minted yet. The whitelist does not see mints, so members are assumed
+    // to be minting once the minter has been told the whitelist is active
     if env.block.time >= config.start_time {
-        return Err(ContractError::AlreadyStarted {});
+        let grace_end = config.start_time.plus_seconds(config.start_time_grace_period);
+        if env.block.time >= grace_end || start_time <= env.block.time {
+            return Err(ContractError::AlreadyStarted {});
+        }
+        if config.num_members > 0 && MINTER_NOTIFIED.may_load(deps.storage)?.unwrap_or_default() {
+            return Err(ContractError::AlreadyStarted {});
+        }
     }
 
     if start_time > config.end_time {
@@ -202,7 +219,12 @@ pub fn execute_add_members(
         if WHITELIST.has(deps.storage, addr.clone()) {
             return Err(ContractError::DuplicateMember(addr.to_string()));
         }
-        WHITELIST.save(deps.storage, addr, &true)?;
+        WHITELIST.save(deps.storage, addr.clone(), &Member { tag: msg.tag.clone() })?;
+        if let Some(tag) = &msg.tag {
+            let count = TAG_COUNTS.may_load(deps.storage, tag)?.unwrap_or_default();
+            TAG_COUNTS.save(deps.storage, tag, &(count + 1))?;
+            TAG_MEMBERS.save(deps.storage, (tag, addr), &true)?;
+        }
         config.num_members += 1;
     }
 
@@ -230,8 +252,13 @@ pub fn execute_remove_members(
 
     for remove in msg.to_remove.into_iter() {
         let addr = deps.api.addr_validate(&remove)?;
-        if !WHITELIST.has(deps.storage, addr.clone()) {
-            return Err(ContractError::NoMemberFound(addr.to_string()));
+        let member = WHITELIST
+            .may_load(deps.storage, addr.clone())?
+            .ok_or_else(|| ContractError::NoMemberFound(addr.to_string()))?;
+        if let Some(tag) = &member.tag {
+            let count = TAG_COUNTS.load(deps.storage, tag)?;
+            TAG_COUNTS.save(deps.storage, tag, &(count - 1))?;
+            TAG_MEMBERS.remove(deps.storage, (tag, addr.clone()));
         }
         WHITELIST.remove(deps.storage, addr);
         config.num_members -= 1;
@@ -283,17 +310,44 @@ pub fn execute_increase_member_limit(
     )
 }
 
+/// Notify the minter that the whitelist is active. Only the first call after start_time sends the message.
+pub fn execute_notify_minter(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let minter = config.minter.ok_or(ContractError::NoMinter {})?;
+
+    if env.block.time < config.start_time {
+        return Err(ContractError::NotStarted {});
+    }
+
+    let res = Response::new().add_attribute("action", "notify_minter");
+    if MINTER_NOTIFIED.may_load(deps.storage)?.unwrap_or_default() {
+        return Ok(res.add_attribute("notified", "false"));
+    }
+    MINTER_NOTIFIED.save(deps.storage, &true)?;
+
+    let msg = WasmMsg::Execute {
+        contract_addr: minter.to_string(),
+        msg: to_binary(&MinterHookMsg::WhitelistActivated {})?,
+        funds: vec![],
+    };
+    Ok(res
+        .add_attribute("notified", "true")
+        .add_attribute("minter", minter)
+        .add_message(msg))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
-        QueryMsg::Members { start_after, limit } => {
-            to_binary(&query_members(deps, start_after, limit)?)
+        QueryMsg::Members { start_after, limit, tag } => {
+            to_binary(&query_members(deps, start_after, limit, tag)?)
         }
 
         QueryMsg::HasStarted {} => to_binary(&query_has_started(deps, env)?),
         QueryMsg::HasEnded {} => to_binary(&query_has_ended(deps, env)?),
         QueryMsg::IsActive {} => to_binary(&query_is_active(deps, env)?),
         QueryMsg::HasMember { member } => to_binary(&query_has_member(deps, member)?),
+        QueryMsg::MemberCountByTag { tag } => to_binary(&query_member_count_by_tag(deps, tag)?),
         QueryMsg::Config {} => to_binary(&query_config(deps, env)?),
     }
 }
@@ -323,17 +377,26 @@ fn query_members(
     deps: Deps,
     start_after: Option<String>,
     limit: Option<u32>,
+    tag: Option<String>,
 ) -> StdResult<MembersResponse> {
     let limit = limit
         .unwrap_or(PAGINATION_DEFAULT_LIMIT)
         .min(PAGINATION_MAX_LIMIT) as usize;
     let start_addr = maybe_addr(deps.api, start_after)?;
     let start = start_addr.map(Bound::exclusive);
-    let members = WHITELIST
-        .range(deps.storage, start, None, Order::Ascending)
-        .take(limit)
-        .map(|addr| addr.unwrap().0.to_string())
-        .collect::<Vec<String>>();
+    let members = match tag {
+        Some(tag) => TAG_MEMBERS
+            .prefix(tag.as_str())
+            .keys(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|addr| addr.unwrap().to_string())
+            .collect::<Vec<String>>(),
+        None => WHITELIST
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|addr| addr.unwrap().0.to_string())
+            .collect::<Vec<String>>(),
+    };
 
     Ok(MembersResponse { members })
 }
@@ -341,11 +404,20 @@ fn query_members(
 fn query_has_member(deps: Deps, member: String) -> StdResult<HasMemberResponse> {
     let addr = deps.api.addr_validate(&member)?;
 
+    let member = WHITELIST.may_load(deps.storage, addr)?;
+
     Ok(HasMemberResponse {
-        has_member: WHITELIST.has(deps.storage, addr),
+        has_member: member.is_some(),
+        tag: member.and_then(|m| m.tag),
     })
 }
 
+fn query_member_count_by_tag(deps: Deps, tag: String) -> StdResult<MemberCountResponse> {
+    let count = TAG_COUNTS.may_load(deps.storage, &tag)?.unwrap_or_default();
+
+    Ok(MemberCountResponse { count })
+}
+
 fn query_config(deps: Deps, env: Env) -> StdResult<ConfigResponse> {
     let config = CONFIG.load(deps.storage)?;
     Ok(ConfigResponse {
@@ -356,6 +428,8 @@ fn query_config(deps: Deps, env: Env) -> StdResult<ConfigResponse> {
         end_time: config.end_time,
         unit_price: config.unit_price,
         is_active: (env.block.time >= config.start_time) && (env.block.time < config.end_time),
+        start_time_grace_period: config.start_time_grace_period,
+        minter: config.minter.map(|minter| minter.to_string()),
     })
 }
 
@@ -383,6 +457,8 @@ mod tests {
             unit_price: coin(UNIT_AMOUNT, NATIVE_DENOM),
             per_address_limit: 1,
             member_limit: 1000,
+            start_time_grace_period: 0,
+            minter: None,
         };
         let info = mock_info(ADMIN, &[coin(100_000_000, "ujuno")]);
         let res = instantiate(deps, mock_env(), info.clone(), msg).unwrap();
@@ -408,6 +484,8 @@ mod tests {
             unit_price: coin(1, NATIVE_DENOM),
             per_address_limit: 1,
             member_limit: 1000,
+            start_time_grace_period: 0,
+            minter: None,
         };
         let info = mock_info(ADMIN, &[coin(100_000_000, "ujuno")]);
         instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
@@ -427,6 +505,8 @@ mod tests {
             unit_price: coin(UNIT_AMOUNT, NATIVE_DENOM),
             per_address_limit: 1,
             member_limit: 1000,
+            start_time_grace_period: 0,
+            minter: None,
         };
         let info = mock_info(ADMIN, &[coin(100_000_000, "ujuno")]);
         let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -443,6 +523,8 @@ mod tests {
             unit_price: coin(UNIT_AMOUNT, NATIVE_DENOM),
             per_address_limit: 1,
             member_limit: 1000,
+            start_time_grace_period: 0,
+            minter: None,
         };
         let info = mock_info(ADMIN, &[coin(100_000_000, "ujuno")]);
         let mut deps = mock_dependencies();
@@ -463,6 +545,71 @@ mod tests {
         assert_eq!(res.start_time, new_start_time);
     }
 
+    #[test]
+    fn update_start_time_grace_period() {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg {
+            members: vec!["adsfsa".to_string()],
+            start_time: START_TIME,
+            end_time: START_TIME.plus_seconds(1000),
+            unit_price: coin(UNIT_AMOUNT, NATIVE_DENOM),
+            per_address_limit: 1,
+            member_limit: 1000,
+            start_time_grace_period: 60,
+            minter: None,
+        };
+        let info = mock_info(ADMIN, &[coin(100_000_000, "ujuno")]);
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // within the grace period the start time can be pushed back
+        let mut env = mock_env();
+        env.block.time = START_TIME.plus_seconds(30);
+        let new_start_time = START_TIME.plus_seconds(120);
+        let msg = ExecuteMsg::UpdateStartTime(new_start_time);
+        let info = mock_info(ADMIN, &[]);
+        execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let res = query_config(deps.as_ref(), env.clone()).unwrap();
+        assert_eq!(res.start_time, new_start_time);
+
+        // but not moved into the past
+        env.block.time = new_start_time.plus_seconds(10);
+        let msg = ExecuteMsg::UpdateStartTime(new_start_time.plus_seconds(5));
+        execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+
+        // beyond the grace period the start time is locked
+        env.block.time = new_start_time.plus_seconds(61);
+        let msg = ExecuteMsg::UpdateStartTime(new_start_time.plus_seconds(120));
+        execute(deps.as_mut(), env, info, msg).unwrap_err();
+    }
+
+    #[test]
+    fn update_start_time_grace_period_after_activation() {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg {
+            members: vec!["adsfsa".to_string()],
+            start_time: START_TIME,
+            end_time: START_TIME.plus_seconds(1000),
+            unit_price: coin(UNIT_AMOUNT, NATIVE_DENOM),
+            per_address_limit: 1,
+            member_limit: 1000,
+            start_time_grace_period: 60,
+            minter: Some("minter".to_string()),
+        };
+        let info = mock_info(ADMIN, &[coin(100_000_000, "ujuno")]);
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // once the minter is told the whitelist is active, members may have minted
+        let mut env = mock_env();
+        env.block.time = START_TIME.plus_seconds(10);
+        execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::NotifyMinter {}).unwrap();
+
+        // so the start time is locked even within the grace period
+        env.block.time = START_TIME.plus_seconds(30);
+        let msg = ExecuteMsg::UpdateStartTime(START_TIME.plus_seconds(120));
+        let err = execute(deps.as_mut(), env, mock_info(ADMIN, &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::AlreadyStarted {}));
+    }
+
     #[test]
     fn update_end_time() {
         let mut deps = mock_dependencies();
@@ -485,12 +632,13 @@ mod tests {
         // dedupe addrs
         let add_msg = AddMembersMsg {
             to_add: vec!["adsfsa1".to_string(), "adsfsa1".to_string()],
+            tag: None,
         };
         let msg = ExecuteMsg::AddMembers(add_msg);
         let info = mock_info(ADMIN, &[]);
         let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
         assert_eq!(res.attributes.len(), 2);
-        let res = query_members(deps.as_ref(), None, None).unwrap();
+        let res = query_members(deps.as_ref(), None, None, None).unwrap();
         assert_eq!(res.members.len(), 2);
 
         execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
@@ -501,10 +649,93 @@ mod tests {
         let msg = ExecuteMsg::RemoveMembers(remove_msg);
         let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
         assert_eq!(res.attributes.len(), 2);
-        let res = query_members(deps.as_ref(), None, None).unwrap();
+        let res = query_members(deps.as_ref(), None, None, None).unwrap();
         assert_eq!(res.members.len(), 1);
     }
 
+    #[test]
+    fn query_members_by_tag() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        let info = mock_info(ADMIN, &[]);
+
+        let add_msg = AddMembersMsg {
+            to_add: vec!["team1".to_string(), "team2".to_string()],
+            tag: Some("team".to_string()),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::AddMembers(add_msg)).unwrap();
+        let add_msg = AddMembersMsg {
+            to_add: vec!["og1".to_string()],
+            tag: Some("og".to_string()),
+        };
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::AddMembers(add_msg)).unwrap();
+
+        // all members, tagged or not
+        let res = query_members(deps.as_ref(), None, None, None).unwrap();
+        assert_eq!(res.members.len(), 4);
+
+        // filtered by tag
+        let res = query_members(deps.as_ref(), None, None, Some("team".to_string())).unwrap();
+        assert_eq!(res.members, vec!["team1".to_string(), "team2".to_string()]);
+        let res = query_members(deps.as_ref(), None, None, Some("og".to_string())).unwrap();
+        assert_eq!(res.members, vec!["og1".to_string()]);
+
+        // filtered pages only read the tag's members
+        let res = query_members(deps.as_ref(), None, Some(1), Some("team".to_string())).unwrap();
+        assert_eq!(res.members, vec!["team1".to_string()]);
+        let res = query_members(deps.as_ref(), Some("team1".to_string()), Some(1), Some("team".to_string())).unwrap();
+        assert_eq!(res.members, vec!["team2".to_string()]);
+
+        // removed members leave the tag
+        let remove_msg = RemoveMembersMsg {
+            to_remove: vec!["team1".to_string()],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), ExecuteMsg::RemoveMembers(remove_msg)).unwrap();
+        let res = query_members(deps.as_ref(), None, None, Some("team".to_string())).unwrap();
+        assert_eq!(res.members, vec!["team2".to_string()]);
+
+        // tag is returned with membership
+        let res = query_has_member(deps.as_ref(), "og1".to_string()).unwrap();
+        assert!(res.has_member);
+        assert_eq!(res.tag, Some("og".to_string()));
+        let res = query_has_member(deps.as_ref(), "adsfsa".to_string()).unwrap();
+        assert!(res.has_member);
+        assert_eq!(res.tag, None);
+    }
+
+    #[test]
+    fn member_count_by_tag() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        let info = mock_info(ADMIN, &[]);
+
+        let add_msg = AddMembersMsg {
+            to_add: vec!["team1".to_string(), "team2".to_string(), "team3".to_string()],
+            tag: Some("team".to_string()),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::AddMembers(add_msg)).unwrap();
+        let add_msg = AddMembersMsg {
+            to_add: vec!["og1".to_string()],
+            tag: Some("og".to_string()),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::AddMembers(add_msg)).unwrap();
+
+        let res = query_member_count_by_tag(deps.as_ref(), "team".to_string()).unwrap();
+        assert_eq!(res.count, 3);
+        let res = query_member_count_by_tag(deps.as_ref(), "og".to_string()).unwrap();
+        assert_eq!(res.count, 1);
+        let res = query_member_count_by_tag(deps.as_ref(), "public".to_string()).unwrap();
+        assert_eq!(res.count, 0);
+
+        // removing a member decrements its tag count
+        let remove_msg = RemoveMembersMsg {
+            to_remove: vec!["team2".to_string()],
+        };
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RemoveMembers(remove_msg)).unwrap();
+        let res = query_member_count_by_tag(deps.as_ref(), "team".to_string()).unwrap();
+        assert_eq!(res.count, 2);
+    }
+
     #[test]
     fn update_per_address_limit() {
         let mut deps = mock_dependencies();
@@ -533,6 +764,8 @@ mod tests {
             unit_price: coin(UNIT_AMOUNT, NATIVE_DENOM),
             per_address_limit: 1,
             member_limit: 1000,
+            start_time_grace_period: 0,
+            minter: None,
         };
         let info = mock_info(ADMIN, &[coin(100_000_000, "ujuno")]);
         let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -540,15 +773,15 @@ mod tests {
         let mut all_elements: Vec<String> = vec![];
 
         // enforcing a min
-        let res = query_members(deps.as_ref(), None, None).unwrap();
+        let res = query_members(deps.as_ref(), None, None, None).unwrap();
         assert_eq!(res.members.len(), 25);
 
         // enforcing a max
-        let res = query_members(deps.as_ref(), None, Some(125)).unwrap();
+        let res = query_members(deps.as_ref(), None, Some(125), None).unwrap();
         assert_eq!(res.members.len(), 100);
 
         // first fetch
-        let res = query_members(deps.as_ref(), None, Some(50)).unwrap();
+        let res = query_members(deps.as_ref(), None, Some(50), None).unwrap();
         assert_eq!(res.members.len(), 50);
         all_elements.append(&mut res.members.clone());
 
@@ -557,6 +790,7 @@ mod tests {
             deps.as_ref(),
             Some(res.members[res.members.len() - 1].clone()),
             Some(50),
+            None,
         )
         .unwrap();
         assert_eq!(res.members.len(), 50);
@@ -567,6 +801,7 @@ mod tests {
             deps.as_ref(),
             Some(res.members[res.members.len() - 1].clone()),
             Some(50),
+            None,
         )
         .unwrap();
         all_elements.append(&mut res.members.clone());
@@ -579,6 +814,47 @@ mod tests {
         assert_eq!(members, all_elements);
     }
 
+    #[test]
+    fn notify_minter() {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg {
+            members: vec!["adsfsa".to_string()],
+            start_time: START_TIME,
+            end_time: END_TIME,
+            unit_price: coin(UNIT_AMOUNT, NATIVE_DENOM),
+            per_address_limit: 1,
+            member_limit: 1000,
+            start_time_grace_period: 0,
+            minter: Some("minter".to_string()),
+        };
+        let info = mock_info(ADMIN, &[coin(100_000_000, "ujuno")]);
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("anyone", &[]);
+
+        // not before start
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::NotifyMinter {}).unwrap_err();
+        assert!(matches!(err, ContractError::NotStarted {}));
+
+        // first call after start notifies the minter
+        let mut env = mock_env();
+        env.block.time = START_TIME;
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::NotifyMinter {}).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(
+            res.messages[0].msg,
+            WasmMsg::Execute {
+                contract_addr: "minter".to_string(),
+                msg: to_binary(&MinterHookMsg::WhitelistActivated {}).unwrap(),
+                funds: vec![],
+            }
+            .into()
+        );
+
+        // later calls are a no-op
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::NotifyMinter {}).unwrap();
+        assert!(res.messages.is_empty());
+    }
+
     #[test]
     fn increase_member_limit() {
         let mut deps = mock_dependencies();
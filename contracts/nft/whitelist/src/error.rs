@@ -37,6 +37,12 @@ pub enum ContractError {
     #[error("Max minting limit per address exceeded")]
     MaxPerAddressLimitExceeded {},
 
+    #[error("NotStarted")]
+    NotStarted {},
+
+    #[error("NoMinter")]
+    NoMinter {},
+
     #[error("InvalidUnitPrice {0}")]
     InvalidUnitPrice(u128),
 
@@ -10,6 +10,11 @@ pub struct InstantiateMsg {
     pub unit_price: Coin,
     pub per_address_limit: u32,
     pub member_limit: u32,
+    /// Seconds after start_time during which the start time can still be pushed back, until
+    /// the minter has been notified that the whitelist is active
+    pub start_time_grace_period: u64,
+    /// Minter to notify once the whitelist becomes active
+    pub minter: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -21,11 +26,22 @@ pub enum ExecuteMsg {
     RemoveMembers(RemoveMembersMsg),
     UpdatePerAddressLimit(u32),
     IncreaseMemberLimit(u32),
+    /// Tell the minter the whitelist is active. Can be called by anyone after start_time, only the first call notifies.
+    NotifyMinter {},
+}
+
+/// Message sent to the minter when the whitelist becomes active
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MinterHookMsg {
+    WhitelistActivated {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AddMembersMsg {
     pub to_add: Vec<String>,
+    /// Tag applied to every added member
+    pub tag: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -42,10 +58,15 @@ pub enum QueryMsg {
     Members {
         start_after: Option<String>,
         limit: Option<u32>,
+        /// Only list members with this tag
+        tag: Option<String>,
     },
     HasMember {
         member: String,
     },
+    MemberCountByTag {
+        tag: String,
+    },
     Config {},
 }
 
@@ -54,9 +75,15 @@ pub struct MembersResponse {
     pub members: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct MemberCountResponse {
+    pub count: u32,
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct HasMemberResponse {
     pub has_member: bool,
+    pub tag: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -88,4 +115,6 @@ pub struct ConfigResponse {
     pub end_time: Timestamp,
     pub unit_price: Coin,
     pub is_active: bool,
+    pub start_time_grace_period: u64,
+    pub minter: Option<String>,
 }
@@ -12,7 +12,22 @@ pub struct Config {
     pub unit_price: Coin,
     pub per_address_limit: u32,
     pub member_limit: u32,
+    pub start_time_grace_period: u64,
+    /// Minter told once the whitelist becomes active
+    pub minter: Option<Addr>,
+}
+
+/// A whitelist member, optionally tagged to segment members (e.g. "team", "og")
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Member {
+    pub tag: Option<String>,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
-pub const WHITELIST: Map<Addr, bool> = Map::new("wl");
+pub const WHITELIST: Map<Addr, Member> = Map::new("wl");
+/// Number of members carrying each tag
+pub const TAG_COUNTS: Map<&str, u32> = Map::new("tag_counts");
+/// Members by tag, so listing the members of a tag only reads that tag's entries
+pub const TAG_MEMBERS: Map<(&str, Addr), bool> = Map::new("tag_members");
+/// Whether the minter has been notified of activation
+pub const MINTER_NOTIFIED: Item<bool> = Item::new("minter_notified");